@@ -1,8 +1,64 @@
-use crate::components::Config;
-use clap::{App as Clap, Arg as ClapArg, ArgMatches as ClapArgMatches};
+use crate::components::{
+    check::{self, Severity},
+    confirm::{Confirmation, TerminalConfirmer},
+    control::{self, Request, Response},
+    dedupe,
+    evaluation::Selection,
+    explain,
+    filesystem::{Filesystem, StdFs},
+    format, init,
+    lsp::LanguageServer,
+    output::{Event, Output, OutputFormat},
+    review::Review,
+    schedule::{self, Scheduler},
+    service::{self, ServiceAction, ServiceOptions},
+    tui::{Step, Tui},
+    watch::Watcher,
+    Config, Rule,
+};
+use clap::{
+    App as Clap, AppSettings as ClapSettings, Arg as ClapArg, ArgMatches as ClapArgMatches,
+    SubCommand as ClapSubCommand,
+};
 use regex::Regex;
+use std::env;
 use std::fs;
-use std::path::Path;
+use std::io::{self, BufReader};
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::Arc;
+
+/// The command the `Application` was launched with
+#[derive(Clone, Debug, PartialEq)]
+pub enum Command {
+    /// Execute the rules of the configurations on the folder to clean
+    Clean,
+    /// Report the groups of duplicated files that matches with a pattern
+    Dupes { pattern: String },
+    /// Apply the rules to the files that appear in some folders, until the process is stopped
+    Watch {
+        targets: Vec<String>,
+        socket: PathBuf,
+    },
+    /// Run the scheduled rules on some folders when they are due, until the process is stopped
+    Schedule { targets: Vec<String> },
+    /// Send a request to the control socket of a running `watch` command
+    Ctl { request: Request, socket: PathBuf },
+    /// Install, uninstall or check the `systemd --user` service running Crabby
+    Service(ServiceAction),
+    /// Show the plan of the rules on the folder to clean, and apply the operations the user selects
+    Tui,
+    /// Report the errors and the suspicious rules of some `.crc` files without executing them
+    Check { files: Vec<String> },
+    /// Write some `.crc` files in their canonical form, or only report the ones that are not with `check`
+    Fmt { files: Vec<String>, check: bool },
+    /// Serve the Language Server Protocol over the standard input and output, for the editors of `.crc` files
+    Lsp,
+    /// Report, for every rule, if it would touch a file and which part of the rule decided it
+    Explain { file: String },
+    /// Write a `.crc` file proposing rules for the files of the folder to clean
+    Init { file: String },
+}
 
 #[derive(Clone, Debug)]
 /// The App object is here to determine arguments and know what configuration(s) should run and how
@@ -12,6 +68,9 @@ pub struct App {
     clean_path: String,
     conf_file_path: String,
     dry_run: bool,
+    command: Command,
+    output: OutputFormat,
+    filesystem: Arc<dyn Filesystem>,
 }
 
 impl App {
@@ -27,18 +86,26 @@ impl App {
 
     /// Create a new `Application` in a test environment
     pub fn new_for_test() -> Self {
-        let matches = Self::cli_for_test();
-        let mut configurations: Vec<Config> = Vec::<Config>::new();
+        Self::new_for_test_in(Arc::new(StdFs))
+    }
 
-        configurations.push(Config::new(App::get_arg(&matches, "file")));
+    /// Create a new `Application` in a test environment, looking for its `.crc` file in a `Filesystem`
+    pub fn new_for_test_in(filesystem: Arc<dyn Filesystem>) -> Self {
+        Self::builder()
+            .filesystem(filesystem)
+            .target(".")
+            .build()
+            .expect("Could not find a configuration file in the current directory.")
+    }
 
-        Self {
-            configurations: configurations,
-            interactive_mode: App::is_present(&matches, "interactive"),
-            clean_path: App::get_arg(&matches, "clean"),
-            conf_file_path: App::get_arg(&matches, "file"),
-            dry_run: App::is_present(&matches, "dry-run"),
-        }
+    /// Return the command the current `Application` was launched with
+    pub fn get_command(&self) -> &Command {
+        &self.command
+    }
+
+    /// Return the format of the output of the current `Application`
+    pub fn get_output(&self) -> OutputFormat {
+        self.output
     }
 
     /// Return the current configurations
@@ -59,10 +126,427 @@ impl App {
         Self::left(line, "//")
     }
 
-    /// Execute the configurations rules
-    pub fn launch(&self) {
-        for config in self.configurations.clone() {
-            config.execute_rules(self.clean_path.clone(), self.dry_run, self.interactive_mode);
+    /// Execute the configurations rules, or the subcommand, and return the exit code of the process
+    pub fn launch(&self) -> i32 {
+        let mut output = Output::new(self.output);
+        let mut code = 0;
+        match &self.command {
+            Command::Clean => {
+                let mut terminal = match self.output {
+                    OutputFormat::Text => TerminalConfirmer::new(),
+                    _ => TerminalConfirmer::with_io(
+                        Box::new(BufReader::new(io::stdin())),
+                        Box::new(io::stderr()),
+                    ),
+                };
+                let mut confirmation = match self.interactive_mode {
+                    true => Confirmation::new(&mut terminal),
+                    false => Confirmation::none(),
+                };
+                for config in self.configurations.clone() {
+                    config.execute_rules_with(
+                        self.clean_path.clone(),
+                        self.dry_run,
+                        &mut confirmation,
+                        &mut output,
+                    );
+                }
+            }
+            Command::Dupes { pattern } => self.report_duplicates(pattern, &mut output),
+            Command::Watch { targets, socket } => self.watch(targets, socket, &mut output),
+            Command::Ctl { request, socket } => Self::ctl(request, socket, &mut output),
+            Command::Schedule { targets } => self.schedule(targets, &mut output),
+            Command::Service(action) => Self::service(action, &mut output),
+            Command::Tui => self.review(&mut output),
+            Command::Check { files } => code = self.check(files, &mut output),
+            Command::Fmt { files, check } => code = self.format(files, *check, &mut output),
+            Command::Lsp => code = self.serve_language(),
+            Command::Explain { file } => code = self.explain(file, &mut output),
+            Command::Init { file } => code = self.init(file, &mut output),
+        }
+        output.finish();
+        code
+    }
+
+    /// Format some `.crc` files, or report the ones that are not formatted, and return 1 if one of them is not
+    fn format(&self, files: &[String], check: bool, output: &mut Output) -> i32 {
+        let mut code = 0;
+        for file in files {
+            let result = self
+                .filesystem
+                .read_to_string(Path::new(file))
+                .map_err(|e| format!("could not read `{}`: {}", file, e))
+                .and_then(|x| {
+                    format::format(&x)
+                        .map(|y| (x, y))
+                        .map_err(|e| format!("could not format `{}`: {}", file, e))
+                });
+            let (content, formatted) = match result {
+                Ok(x) => x,
+                Err(e) => {
+                    output.message("error", e);
+                    code = 1;
+                    continue;
+                }
+            };
+            if content == formatted {
+                continue;
+            }
+            if check {
+                let line = content
+                    .split('\n')
+                    .zip(formatted.split('\n'))
+                    .position(|(x, y)| x != y)
+                    .unwrap_or_else(|| {
+                        content
+                            .split('\n')
+                            .count()
+                            .min(formatted.split('\n').count())
+                    })
+                    + 1;
+                let text = format!("fmt: `{}` is not formatted, from line {}.", file, line);
+                output.message("info", text);
+                code = 1;
+                continue;
+            }
+            match self.filesystem.write(Path::new(file), formatted.as_bytes()) {
+                Ok(_) => output.message("info", format!("fmt: formatted `{}`.", file)),
+                Err(e) => {
+                    output.message("error", format!("could not write `{}`: {}", file, e));
+                    code = 1;
+                }
+            }
+        }
+        code
+    }
+
+    /// Print the diagnostics of some `.crc` files, and return 1 if one of them has an error
+    fn check(&self, files: &[String], output: &mut Output) -> i32 {
+        let mut code = 0;
+        for file in files {
+            let content = match self.filesystem.read_to_string(Path::new(file)) {
+                Ok(x) => x,
+                Err(e) => {
+                    output.message("error", format!("could not read `{}`: {}", file, e));
+                    code = 1;
+                    continue;
+                }
+            };
+            let diagnostics = check::check(file, &content);
+            let errors = diagnostics
+                .iter()
+                .filter(|x| x.severity == Severity::Error)
+                .count();
+            if errors > 0 {
+                code = 1;
+            }
+            for diagnostic in diagnostics.iter().cloned() {
+                output.emit(Event::Diagnostic { diagnostic });
+            }
+            let text = format!(
+                "check: {} error(s) and {} warning(s) in `{}`.",
+                errors,
+                diagnostics.len() - errors,
+                file
+            );
+            output.message("info", text);
+        }
+        code
+    }
+
+    /// Write a `.crc` file with the rules proposed for the folder to clean, and return 1 if it could not be written.
+    /// In interactive mode, the user accepts or rejects each rule.
+    fn init(&self, file: &str, output: &mut Output) -> i32 {
+        if self.filesystem.exists(Path::new(file)) {
+            output.message("error", format!("`{}` already exists", file));
+            return 1;
+        }
+        let user_dirs = init::user_dirs(&*self.filesystem);
+        let mut scaffold = init::scaffold(&*self.filesystem, &self.clean_path, &user_dirs);
+        if self.interactive_mode {
+            let mut terminal = TerminalConfirmer::new();
+            let mut confirmation = Confirmation::new(&mut terminal);
+            scaffold.proposals = init::select_proposals(scaffold.proposals, &mut confirmation);
+        }
+        let content = init::render_scaffold(&self.clean_path, file, &scaffold);
+        match self.filesystem.write(Path::new(file), content.as_bytes()) {
+            Ok(()) => {
+                let text = format!(
+                    "init: wrote `{}` with {} rule(s).",
+                    file,
+                    scaffold.proposals.len()
+                );
+                output.message("info", text);
+                0
+            }
+            Err(e) => {
+                output.message("error", format!("could not write `{}`: {}", file, e));
+                1
+            }
+        }
+    }
+
+    /// Tell what every rule would do to a file and why, then the operations that would run, and return 1 if the
+    /// file does not exist
+    fn explain(&self, file: &str, output: &mut Output) -> i32 {
+        if !self.filesystem.is_file(Path::new(file)) {
+            output.message("error", format!("`{}` is not a file", file));
+            return 1;
+        }
+        let explanations = explain::explain(&self.configurations, &self.clean_path, file);
+        let operations: Vec<String> = explanations
+            .iter()
+            .flat_map(|x| x.operations.iter())
+            .map(|x| format!("[line {}] {}", x.line, x.describe()))
+            .collect();
+        for explanation in explanations {
+            output.emit(Event::RuleExplained { explanation });
+        }
+        let text = match operations.is_empty() {
+            true => format!("explain: no rule would touch `{}`.", file),
+            false => format!("explain: the clean would {}.", operations.join(", then ")),
+        };
+        output.message("info", text);
+        0
+    }
+
+    /// Serve the Language Server Protocol over the standard input and output, and return 1 if it failed.
+    /// The standard output belongs to the protocol, so the errors are written on the standard error.
+    fn serve_language(&self) -> i32 {
+        let stdin = io::stdin();
+        let mut server = LanguageServer::new(stdin.lock(), io::stdout());
+        server.set_filesystem(self.filesystem.clone());
+        match server.run() {
+            Ok(()) => 0,
+            Err(e) => {
+                eprintln!("error: lsp: {}.", e);
+                1
+            }
+        }
+    }
+
+    /// Let the user review the plan of the rules in a terminal UI, then apply the selected operations
+    fn review(&self, output: &mut Output) {
+        let review = Review::plan(&self.configurations, &self.clean_path);
+        if review.get_groups().iter().all(|x| x.items.is_empty()) {
+            output.message("info", String::from("tui: no operation to review."));
+            return;
+        }
+        let mut tui = Tui::new(review, &self.clean_path);
+        match tui.run() {
+            Ok(Step::Apply) => {
+                tui.into_review().apply(self.dry_run, output);
+            }
+            Ok(_) => output.message("info", String::from("tui: nothing was applied.")),
+            Err(e) => output.message("error", format!("could not show the terminal UI: {}", e)),
+        }
+    }
+
+    /// Install, uninstall or check the `systemd --user` service
+    fn service(action: &ServiceAction, output: &mut Output) {
+        match action {
+            ServiceAction::Install(options) => {
+                let executable = env::current_exe().unwrap_or_else(|_| PathBuf::from("crabby"));
+                match service::install_service(options, &executable) {
+                    Ok(units) => {
+                        for unit in &units {
+                            output.message("info", format!("service: wrote `{}`.", unit.display()));
+                        }
+                        let enabled = match options.timer {
+                            Some(_) => format!("{}.timer", options.name),
+                            None => format!("{}.service", options.name),
+                        };
+                        let text = format!(
+                            "service: enable it with `systemctl --user daemon-reload && systemctl --user enable --now {}`.",
+                            enabled
+                        );
+                        output.message("info", text);
+                        output.message(
+                            "info",
+                            format!(
+                                "service: see its logs with `{}`.",
+                                service::journal_filter(&options.name)
+                            ),
+                        );
+                    }
+                    Err(e) => {
+                        output.message("error", format!("could not install the service: {}", e))
+                    }
+                }
+            }
+            ServiceAction::Uninstall(name) => match service::uninstall_service(name) {
+                Ok(units) if units.is_empty() => {
+                    output.message("info", format!("service: `{}` is not installed.", name))
+                }
+                Ok(units) => {
+                    for unit in &units {
+                        output.message("info", format!("service: removed `{}`.", unit.display()));
+                    }
+                    let text = "service: run `systemctl --user daemon-reload` to forget it, after stopping it.";
+                    output.message("info", text.to_string());
+                }
+                Err(e) => {
+                    output.message("error", format!("could not uninstall the service: {}", e))
+                }
+            },
+            ServiceAction::Status(name) => {
+                let units = service::installed_units(name);
+                if units.is_empty() {
+                    output.message("info", format!("service: `{}` is not installed.", name));
+                    return;
+                }
+                for unit in &units {
+                    let unit_name = unit.file_name().unwrap_or_default().to_string_lossy();
+                    let state = process::Command::new("systemctl")
+                        .args(["--user", "is-active", &unit_name])
+                        .output()
+                        .map(|x| String::from_utf8_lossy(&x.stdout).trim().to_string())
+                        .ok()
+                        .filter(|x| !x.is_empty())
+                        .unwrap_or_else(|| String::from("state unknown"));
+                    output.message(
+                        "info",
+                        format!("service: `{}` is installed ({}).", unit.display(), state),
+                    );
+                }
+                output.message(
+                    "info",
+                    format!(
+                        "service: see its logs with `{}`.",
+                        service::journal_filter(name)
+                    ),
+                );
+            }
+        }
+    }
+
+    /// Apply the rules of the first configuration to the new files of some folders
+    fn watch(&self, targets: &[String], socket: &Path, output: &mut Output) {
+        let config_file = PathBuf::from(self.configurations[0].get_filename());
+        let targets = targets.iter().map(PathBuf::from).collect();
+        let result = Watcher::new(targets, config_file, self.dry_run).and_then(|mut x| {
+            x.set_socket(socket.to_path_buf());
+            x.set_output(self.output);
+            x.set_selection(self.configurations[0].get_selection().clone());
+            x.run()
+        });
+        if let Err(e) = result {
+            output.message("error", format!("could not watch the folders: {}", e));
+        }
+    }
+
+    /// Run the scheduled rules of the first configuration on some folders
+    fn schedule(&self, targets: &[String], output: &mut Output) {
+        let config_file = self.configurations[0].get_filename().clone();
+        let state_file = schedule::state_file();
+        let result = Scheduler::new(targets.to_vec(), config_file, &state_file, self.dry_run)
+            .and_then(|mut x| {
+                x.set_output(self.output);
+                x.set_selection(self.configurations[0].get_selection().clone());
+                x.run()
+            });
+        if let Err(e) = result {
+            output.message("error", format!("could not run the scheduled rules: {}", e));
+        }
+    }
+
+    /// Send a request to a running `watch` command and print its response
+    fn ctl(request: &Request, socket: &Path, output: &mut Output) {
+        let response = match control::send(socket, request) {
+            Ok(x) => x,
+            Err(e) => {
+                let text = format!(
+                    "could not reach the daemon on `{}`: {}",
+                    socket.display(),
+                    e
+                );
+                output.message("error", text);
+                return;
+            }
+        };
+        if output.get_format() != OutputFormat::Text {
+            output.emit(Event::Response { response });
+            return;
+        }
+        match response {
+            Response {
+                ok: false, error, ..
+            } => println!("error: {}.", error.unwrap_or_default()),
+            Response {
+                status: Some(status),
+                ..
+            } => {
+                println!(
+                    "status: {}, {} file(s) queued.",
+                    if status.paused { "paused" } else { "running" },
+                    status.queue_depth
+                );
+                for target in &status.targets {
+                    println!("target: {}", target.target);
+                    for rule in &target.rules {
+                        println!(
+                            "    rule line {} - {} : {} run(s), {} file(s), {} error(s)",
+                            rule.line, rule.rule, rule.runs, rule.files, rule.errors
+                        );
+                    }
+                }
+                for error in &status.recent_errors {
+                    println!("recent error: {}", error);
+                }
+            }
+            Response {
+                report: Some(report),
+                ..
+            } => {
+                println!("report: {} ({})", report.time, report.trigger);
+                for entry in &report.entries {
+                    println!(
+                        "    {} rule line {} - {} : {} file(s), {}",
+                        entry.target,
+                        entry.line,
+                        entry.rule,
+                        entry.files,
+                        if entry.succeeded {
+                            "succeeded"
+                        } else {
+                            "failed"
+                        }
+                    );
+                }
+            }
+            _ => println!("ctl: done."),
+        }
+    }
+
+    /// Print the groups of duplicated files of the folder to clean
+    fn report_duplicates(&self, pattern: &str, output: &mut Output) {
+        let files: Vec<PathBuf> =
+            Rule::files_matching_in(&*self.filesystem, &self.clean_path, pattern)
+                .iter()
+                .map(PathBuf::from)
+                .collect();
+        match dedupe::find_duplicates_in(&*self.filesystem, &files) {
+            Ok(groups) => {
+                for group in &groups {
+                    output.emit(Event::DuplicateGroup {
+                        size: self
+                            .filesystem
+                            .metadata(&group[0])
+                            .map(|x| x.len)
+                            .unwrap_or(0),
+                        files: group
+                            .iter()
+                            .map(|x| x.to_string_lossy().to_string())
+                            .collect(),
+                    });
+                }
+                let text = format!("{} group(s) of duplicated files found.", groups.len());
+                output.message("info", text);
+            }
+            Err(e) => {
+                let text = format!("could not look for duplicated files: {}", e);
+                output.message("error", text);
+            }
         }
     }
 
@@ -71,57 +555,218 @@ impl App {
         &self.configurations.push(config);
     }
 
-    /// Return a new `Application`
+    /// Return a builder of an `Application` that does not parse the command line
+    pub fn builder() -> AppBuilder {
+        AppBuilder::default()
+    }
+
+    /// Return a new `Application` from the command line arguments
     pub fn new() -> Self {
-        let matches = Self::cli();
-        let mut configurations: Vec<Config> = Vec::<Config>::new();
+        let result = Self::builder_from_cli(&Self::cli()).build();
+        result.unwrap_or_else(|e| {
+            println!("error: {}.", e);
+            process::exit(1)
+        })
+    }
 
-        configurations.push(Config::new(App::get_arg(&matches, "file")));
+    /// Return the builder of the `Application` described by the command line arguments
+    fn builder_from_cli(matches: &ClapArgMatches) -> AppBuilder {
+        let builder = Self::builder().output(Self::output_arg(matches));
+        match matches.subcommand() {
+            ("dupes", Some(dupes)) => {
+                builder
+                    .target(&App::get_arg(dupes, "clean"))
+                    .command(Command::Dupes {
+                        pattern: App::get_arg(dupes, "pattern"),
+                    })
+            }
+            ("service", Some(service)) => {
+                builder.command(Command::Service(Self::service_action(service)))
+            }
+            ("ctl", Some(ctl)) => builder.command(Command::Ctl {
+                request: Self::ctl_request(ctl),
+                socket: Self::socket_arg(ctl),
+            }),
+            ("check", Some(check)) => builder.command(Command::Check {
+                files: match check.values_of("file") {
+                    Some(x) => x.map(|y| y.to_string()).collect(),
+                    None => vec![],
+                },
+            }),
+            ("fmt", Some(fmt)) => builder.command(Command::Fmt {
+                files: match fmt.values_of("file") {
+                    Some(x) => x.map(|y| y.to_string()).collect(),
+                    None => vec![],
+                },
+                check: App::is_present(fmt, "check"),
+            }),
+            ("lsp", Some(_)) => builder.command(Command::Lsp),
+            ("init", Some(init)) => builder
+                .target(&App::get_arg(init, "clean"))
+                .interactive(App::is_present(init, "interactive"))
+                .command(Command::Init {
+                    file: match init.value_of("file") {
+                        Some(x) => x.to_string(),
+                        None => String::from("common.crc"),
+                    },
+                }),
+            ("explain", Some(explain)) => Self::selection_from(builder, explain)
+                .target(&App::get_arg(explain, "clean"))
+                .config_file(&App::get_arg(explain, "file"))
+                .command(Command::Explain {
+                    file: App::get_arg(explain, "path"),
+                }),
+            ("tui", Some(tui)) => Self::selection_from(builder, tui)
+                .target(&App::get_arg(tui, "clean"))
+                .config_file(&App::get_arg(tui, "file"))
+                .dry_run(App::is_present(tui, "dry-run"))
+                .command(Command::Tui),
+            ("watch", Some(daemon)) | ("schedule", Some(daemon)) => {
+                let targets: Vec<String> = match daemon.values_of("clean") {
+                    Some(x) => x.map(|y| y.to_string()).collect(),
+                    None => vec![],
+                };
+                let builder = targets
+                    .iter()
+                    .fold(Self::selection_from(builder, daemon), |x, y| x.target(y))
+                    .config_file(&App::get_arg(daemon, "file"))
+                    .dry_run(App::is_present(daemon, "dry-run"));
+                match matches.subcommand_name() {
+                    Some("watch") => builder.command(Command::Watch {
+                        targets,
+                        socket: Self::socket_arg(daemon),
+                    }),
+                    _ => builder.command(Command::Schedule { targets }),
+                }
+            }
+            _ => Self::selection_from(builder, matches)
+                .target(&App::get_arg(matches, "clean"))
+                .config_file(&App::get_arg(matches, "file"))
+                .dry_run(App::is_present(matches, "dry-run"))
+                .interactive(App::is_present(matches, "interactive")),
+        }
+    }
 
-        Self {
-            configurations: configurations,
-            interactive_mode: App::is_present(&matches, "interactive"),
-            clean_path: App::get_arg(&matches, "clean"),
-            conf_file_path: App::get_arg(&matches, "file"),
-            dry_run: App::is_present(&matches, "dry-run"),
+    /// Add the names and tags given to `--only` and `--skip` to a builder
+    fn selection_from(builder: AppBuilder, matches: &ClapArgMatches) -> AppBuilder {
+        let values = |arg: &str| -> Vec<String> {
+            match matches.values_of(arg) {
+                Some(x) => x.map(|y| y.to_string()).collect(),
+                None => vec![],
+            }
+        };
+        let builder = values("only").iter().fold(builder, |x, y| x.only(y));
+        values("skip").iter().fold(builder, |x, y| x.skip(y))
+    }
+
+    /// Return the request of the `ctl` subcommand
+    fn ctl_request(matches: &ClapArgMatches) -> Request {
+        match matches.subcommand() {
+            ("pause", _) => Request::Pause,
+            ("resume", _) => Request::Resume,
+            ("reload", _) => Request::Reload,
+            ("run-now", Some(x)) => {
+                let target = App::get_arg(x, "clean");
+                Request::RunNow {
+                    target: match fs::canonicalize(&target) {
+                        Ok(y) => y.to_string_lossy().to_string(),
+                        Err(_) => target,
+                    },
+                }
+            }
+            ("last-report", _) => Request::LastReport,
+            _ => Request::Status,
+        }
+    }
+
+    /// Return the format given by the `--output` argument, that can follow any subcommand
+    fn output_arg(matches: &ClapArgMatches) -> OutputFormat {
+        if let (_, Some(subcommand)) = matches.subcommand() {
+            if subcommand.is_present("output") {
+                return Self::output_arg(subcommand);
+            }
+        }
+        OutputFormat::from_name(&App::get_arg(matches, "output")).unwrap_or(OutputFormat::Text)
+    }
+
+    /// Return the action of the `service` subcommand
+    fn service_action(matches: &ClapArgMatches) -> ServiceAction {
+        let values = |x: &ClapArgMatches, arg: &str| -> Vec<PathBuf> {
+            match x.values_of(arg) {
+                Some(y) => y.map(PathBuf::from).collect(),
+                None => vec![],
+            }
+        };
+        match matches.subcommand() {
+            ("install", Some(install)) => ServiceAction::Install(ServiceOptions {
+                name: App::get_arg(install, "name"),
+                targets: values(install, "clean"),
+                config_files: values(install, "file"),
+                timer: install.value_of("timer").map(|x| x.to_string()),
+            }),
+            ("uninstall", Some(uninstall)) => {
+                ServiceAction::Uninstall(App::get_arg(uninstall, "name"))
+            }
+            (_, Some(status)) => ServiceAction::Status(App::get_arg(status, "name")),
+            _ => ServiceAction::Status(String::from("crabby")),
         }
     }
 
     /// Return the default configurations lines from a `.crc` file in the root folder
     pub fn default_conf_lines() -> (String, String) {
-        let current_dir =
-            fs::read_dir(Path::new(".")).expect("Could not read the current directory.");
+        Self::default_conf_lines_in(&StdFs)
+    }
+
+    /// Return the default configurations lines from a `.crc` file in the root folder of a `Filesystem`
+    pub fn default_conf_lines_in(filesystem: &dyn Filesystem) -> (String, String) {
+        let path = Self::default_conf_file_in(filesystem)
+            .expect("Could not find a configuration file in the current directory.");
+        (
+            path.clone(),
+            filesystem
+                .read_to_string(Path::new(&path))
+                .expect("Could not read the configuration file specified."),
+        )
+    }
+
+    /// Return the path of the first `.crc` file in the root folder of a `Filesystem`
+    pub fn default_conf_file_in(filesystem: &dyn Filesystem) -> Option<String> {
+        let current_dir = filesystem.read_dir(Path::new(".")).ok()?;
         let conf_file_regex = Regex::new(r"^*.crc$").unwrap();
+        current_dir
+            .iter()
+            .filter_map(|x| x.to_str())
+            .find(|x| conf_file_regex.is_match(x))
+            .map(|x| x.to_string())
+    }
 
-        for files_and_folders in current_dir {
-            match files_and_folders {
-                Ok(file_or_folder) => {
-                    let path = file_or_folder.path();
-                    let path_str = match path.to_str() {
-                        Some(x) => x,
-                        None => continue,
-                    };
-                    if conf_file_regex.is_match(path_str) {
-                        return (
-                            path_str.to_string(),
-                            fs::read_to_string(path_str)
-                                .expect("Could not read the configuration file specified."),
-                        );
-                    }
-                }
-                Err(_) => continue,
-            };
+    /// Return the first `.crc` file in the root folder of a `Filesystem`, for the commands reading files without rules
+    fn default_conf_files_in(filesystem: &dyn Filesystem) -> io::Result<Vec<String>> {
+        match Self::default_conf_file_in(filesystem) {
+            Some(x) => Ok(vec![x]),
+            None => Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "no configuration file in the current folder",
+            )),
         }
-        panic!("Could not find a configuration file in the current directory.")
     }
 
     /// Return the configuration lines
     pub fn conf_file_lines(configuration_file: &str) -> (String, String) {
+        Self::conf_file_lines_in(&StdFs, configuration_file)
+    }
+
+    /// Return the configuration lines of a file of a `Filesystem`
+    pub fn conf_file_lines_in(
+        filesystem: &dyn Filesystem,
+        configuration_file: &str,
+    ) -> (String, String) {
         match configuration_file {
-            "" => Self::default_conf_lines(),
+            "" => Self::default_conf_lines_in(filesystem),
             x => (
                 x.to_string(),
-                fs::read_to_string(configuration_file)
+                filesystem
+                    .read_to_string(Path::new(configuration_file))
                     .expect("Could not read the configuration file specified."),
             ),
         }
@@ -141,6 +786,7 @@ impl App {
         .version("0.1.0")
         .about("Crabby is a fast and safe way to manipulate files directly from your command line.")
         .author("Théo Huchard <theo.huchard@protonmail.com>")
+        .setting(ClapSettings::SubcommandsNegateReqs)
         .arg(
             ClapArg::with_name("clean")
                 .short("c")
@@ -166,6 +812,15 @@ impl App {
                 .required(false)
                 .takes_value(false)
         )
+        .arg(
+            ClapArg::with_name("output")
+                .long("output")
+                .value_name("FORMAT")
+                .help("Format of the output: sentences, one JSON document, or one JSON object per line")
+                .possible_values(&["text", "json", "ndjson"])
+                .takes_value(true)
+                .global(true)
+        )
         .arg (
             ClapArg::with_name("interactive")
                 .long("interactive")
@@ -173,9 +828,334 @@ impl App {
                 .required(false)
                 .takes_value(false)
         )
+        .args(&Self::selection_args())
+        .subcommand(
+            ClapSubCommand::with_name("dupes")
+                .about("Lists the groups of files having the same content")
+                .arg(
+                    ClapArg::with_name("clean")
+                        .short("c")
+                        .long("clean")
+                        .value_name("PATH")
+                        .help("Path of the directory to look for duplicates in")
+                        .required(true)
+                        .takes_value(true)
+                )
+                .arg(
+                    ClapArg::with_name("pattern")
+                        .long("pattern")
+                        .value_name("PATTERN")
+                        .help("Pattern of the files to compare")
+                        .default_value("*")
+                        .takes_value(true)
+                )
+        )
+        .subcommand(
+            ClapSubCommand::with_name("check")
+                .about("Reports the errors and the suspicious rules of some .crc files without executing them")
+                .arg(
+                    ClapArg::with_name("file")
+                        .value_name("FILE PATH")
+                        .help("Path of a .crc file, the one of the current folder by default")
+                        .required(false)
+                        .multiple(true)
+                )
+        )
+        .subcommand(
+            ClapSubCommand::with_name("fmt")
+                .about("Writes some .crc files in their canonical form, keeping their comments")
+                .arg(
+                    ClapArg::with_name("file")
+                        .value_name("FILE PATH")
+                        .help("Path of a .crc file, the one of the current folder by default")
+                        .required(false)
+                        .multiple(true)
+                )
+                .arg(
+                    ClapArg::with_name("check")
+                        .long("check")
+                        .help("Only report the files that are not formatted, with a non-zero exit code")
+                        .required(false)
+                        .takes_value(false)
+                )
+        )
+        .subcommand(
+            ClapSubCommand::with_name("lsp")
+                .about("Speaks the Language Server Protocol over stdio, for the editors of .crc files")
+        )
+        .subcommand(
+            ClapSubCommand::with_name("init")
+                .about("Writes a commented .crc file proposing rules for the files of a folder")
+                .arg(
+                    ClapArg::with_name("clean")
+                        .short("c")
+                        .long("clean")
+                        .value_name("PATH")
+                        .help("Path of the directory to scan")
+                        .required(true)
+                        .takes_value(true)
+                )
+                .arg(
+                    ClapArg::with_name("file")
+                        .short("f")
+                        .long("file")
+                        .value_name("FILE PATH")
+                        .help("File path of the .crc file to write, common.crc by default")
+                        .required(false)
+                        .takes_value(true)
+                )
+                .arg(
+                    ClapArg::with_name("interactive")
+                        .long("interactive")
+                        .help("Asks the user to accept or reject each proposed rule")
+                        .required(false)
+                        .takes_value(false)
+                )
+        )
+        .subcommand(
+            ClapSubCommand::with_name("explain")
+                .about("Tells which rules would handle a file, and which part of each rule decided it")
+                .arg(
+                    ClapArg::with_name("path")
+                        .value_name("PATH")
+                        .help("Path of the file to explain")
+                        .required(true)
+                )
+                .arg(
+                    ClapArg::with_name("clean")
+                        .short("c")
+                        .long("clean")
+                        .value_name("PATH")
+                        .help("Path of the directory cleaned, the folder of the file by default")
+                        .required(false)
+                        .takes_value(true)
+                )
+                .arg(
+                    ClapArg::with_name("file")
+                        .short("f")
+                        .long("file")
+                        .value_name("FILE PATH")
+                        .help("File path of the rules, the .crc file of the current folder by default")
+                        .required(false)
+                        .takes_value(true)
+                )
+                .args(&Self::selection_args())
+        )
+        .subcommand(
+            ClapSubCommand::with_name("tui")
+                .about("Shows the plan of the rules grouped by rule, to select and edit the operations before applying them")
+                .arg(
+                    ClapArg::with_name("clean")
+                        .short("c")
+                        .long("clean")
+                        .value_name("PATH")
+                        .help("Path of the directory to clean")
+                        .required(true)
+                        .takes_value(true)
+                )
+                .arg(
+                    ClapArg::with_name("file")
+                        .short("f")
+                        .long("file")
+                        .value_name("FILE PATH")
+                        .help("File path that must be used for cleaning")
+                        .required(false)
+                        .takes_value(true)
+                )
+                .arg(
+                    ClapArg::with_name("dry-run")
+                        .long("dry-run")
+                        .help("List the effects of the selected operations without executing them")
+                        .required(false)
+                        .takes_value(false)
+                )
+                .args(&Self::selection_args())
+        )
+        .subcommand(
+            ClapSubCommand::with_name("watch")
+                .about("Applies the rules to the new files of some folders until it is stopped")
+                .arg(
+                    ClapArg::with_name("clean")
+                        .short("c")
+                        .long("clean")
+                        .value_name("PATH")
+                        .help("Path of a directory to watch, can be given several times")
+                        .required(true)
+                        .takes_value(true)
+                        .multiple(true)
+                        .number_of_values(1)
+                )
+                .arg(
+                    ClapArg::with_name("file")
+                        .short("f")
+                        .long("file")
+                        .value_name("FILE PATH")
+                        .help("File path that must be used for cleaning")
+                        .required(false)
+                        .takes_value(true)
+                )
+                .arg(
+                    ClapArg::with_name("dry-run")
+                        .long("dry-run")
+                        .help("List the effects of the rules without executing them")
+                        .required(false)
+                        .takes_value(false)
+                )
+                .arg(Self::socket_path_arg())
+                .args(&Self::selection_args())
+        )
+        .subcommand(
+            ClapSubCommand::with_name("schedule")
+                .about("Runs the rules having an `every` or `at` clause when they are due, until it is stopped")
+                .arg(
+                    ClapArg::with_name("clean")
+                        .short("c")
+                        .long("clean")
+                        .value_name("PATH")
+                        .help("Path of a directory to clean, can be given several times")
+                        .required(true)
+                        .takes_value(true)
+                        .multiple(true)
+                        .number_of_values(1)
+                )
+                .arg(
+                    ClapArg::with_name("file")
+                        .short("f")
+                        .long("file")
+                        .value_name("FILE PATH")
+                        .help("File path that must be used for cleaning")
+                        .required(false)
+                        .takes_value(true)
+                )
+                .arg(
+                    ClapArg::with_name("dry-run")
+                        .long("dry-run")
+                        .help("List the effects of the rules without executing them")
+                        .required(false)
+                        .takes_value(false)
+                )
+                .args(&Self::selection_args())
+        )
+        .subcommand(
+            ClapSubCommand::with_name("ctl")
+                .about("Talks to a running `crabby watch` through its control socket")
+                .arg(Self::socket_path_arg())
+                .subcommand(ClapSubCommand::with_name("status").about("Shows the statistics of the rules of each folder"))
+                .subcommand(ClapSubCommand::with_name("pause").about("Stops handling the new files until resumed"))
+                .subcommand(ClapSubCommand::with_name("resume").about("Handles the new files again"))
+                .subcommand(ClapSubCommand::with_name("reload").about("Reads the .crc file again"))
+                .subcommand(
+                    ClapSubCommand::with_name("run-now")
+                        .about("Applies the rules to all the files of a watched folder")
+                        .arg(
+                            ClapArg::with_name("clean")
+                                .value_name("PATH")
+                                .help("Path of the watched directory")
+                                .required(true)
+                                .index(1)
+                        )
+                )
+                .subcommand(ClapSubCommand::with_name("last-report").about("Shows what the last rules applied did"))
+        )
+        .subcommand(
+            ClapSubCommand::with_name("service")
+                .about("Manages a systemd user service running Crabby")
+                .setting(ClapSettings::SubcommandRequiredElseHelp)
+                .subcommand(
+                    ClapSubCommand::with_name("install")
+                        .about("Writes the units of the service, after validating the configuration files")
+                        .arg(
+                            ClapArg::with_name("clean")
+                                .short("c")
+                                .long("clean")
+                                .value_name("PATH")
+                                .help("Path of a directory to clean, can be given several times")
+                                .required(true)
+                                .takes_value(true)
+                                .multiple(true)
+                                .number_of_values(1)
+                        )
+                        .arg(
+                            ClapArg::with_name("file")
+                                .short("f")
+                                .long("file")
+                                .value_name("FILE PATH")
+                                .help("File path that must be used for cleaning, can be given several times")
+                                .required(true)
+                                .takes_value(true)
+                                .multiple(true)
+                                .number_of_values(1)
+                        )
+                        .arg(
+                            ClapArg::with_name("timer")
+                                .long("timer")
+                                .value_name("CALENDAR")
+                                .help("Clean periodically, e.g. `hourly`, instead of watching the directories")
+                                .takes_value(true)
+                        )
+                        .arg(Self::service_name_arg())
+                )
+                .subcommand(
+                    ClapSubCommand::with_name("uninstall")
+                        .about("Removes the units of the service")
+                        .arg(Self::service_name_arg())
+                )
+                .subcommand(
+                    ClapSubCommand::with_name("status")
+                        .about("Tells if the service is installed and running")
+                        .arg(Self::service_name_arg())
+                )
+        )
         .get_matches()
     }
 
+    /// Return the path of the control socket given by the `--socket` argument, or the default one
+    fn socket_arg(matches: &ClapArgMatches) -> PathBuf {
+        match matches.value_of("socket") {
+            Some(x) => PathBuf::from(x),
+            None => control::socket_path(),
+        }
+    }
+
+    /// Return the argument giving the path of the control socket
+    fn socket_path_arg() -> ClapArg<'static, 'static> {
+        ClapArg::with_name("socket")
+            .long("socket")
+            .value_name("SOCKET PATH")
+            .help("Path of the control socket, in $XDG_RUNTIME_DIR by default")
+            .takes_value(true)
+    }
+
+    /// Return the arguments selecting the rules to run by name or tag
+    fn selection_args() -> [ClapArg<'static, 'static>; 2] {
+        [
+            ClapArg::with_name("only")
+                .long("only")
+                .value_name("NAME OR TAG")
+                .help("Only run the rules having this name or tag, can be given several times")
+                .takes_value(true)
+                .multiple(true)
+                .number_of_values(1),
+            ClapArg::with_name("skip")
+                .long("skip")
+                .value_name("NAME OR TAG")
+                .help("Do not run the rules having this name or tag, can be given several times")
+                .takes_value(true)
+                .multiple(true)
+                .number_of_values(1),
+        ]
+    }
+
+    /// Return the argument giving the name of the service units
+    fn service_name_arg() -> ClapArg<'static, 'static> {
+        ClapArg::with_name("name")
+            .long("name")
+            .value_name("NAME")
+            .help("Name of the service units")
+            .default_value("crabby")
+            .takes_value(true)
+    }
+
     /// Return a ClapArgMatches to retrieve arguments values in a production environment
     pub fn cli() -> ClapArgMatches<'static> {
         Self::cli_switch(false)
@@ -186,3 +1166,155 @@ impl App {
         Self::cli_switch(true)
     }
 }
+
+/// Build an `Application` without parsing the command line, such as
+/// `App::builder().target("./Downloads").config_str("mv *.pdf to ./Docs").dry_run(true).build()`
+#[derive(Clone, Debug, Default)]
+pub struct AppBuilder {
+    targets: Vec<String>,
+    config_files: Vec<String>,
+    config_strs: Vec<String>,
+    dry_run: bool,
+    interactive_mode: bool,
+    command: Option<Command>,
+    output: Option<OutputFormat>,
+    filesystem: Option<Arc<dyn Filesystem>>,
+    selection: Selection,
+}
+
+impl AppBuilder {
+    /// Add a folder to clean. The first one is the folder of the `clean` and `dupes` commands.
+    pub fn target(mut self, path: &str) -> Self {
+        if !path.is_empty() {
+            self.targets.push(path.to_string());
+        }
+        self
+    }
+
+    /// Add a `.crc` file to read the rules from
+    pub fn config_file(mut self, path: &str) -> Self {
+        if !path.is_empty() {
+            self.config_files.push(path.to_string());
+        }
+        self
+    }
+
+    /// Add rules written in the `.crc` format
+    pub fn config_str(mut self, source: &str) -> Self {
+        self.config_strs.push(source.to_string());
+        self
+    }
+
+    /// Plan the operations without applying them, `false` by default
+    pub fn dry_run(mut self, dry_run: bool) -> Self {
+        self.dry_run = dry_run;
+        self
+    }
+
+    /// Ask the user to accept or decline each operation, `false` by default
+    pub fn interactive(mut self, interactive_mode: bool) -> Self {
+        self.interactive_mode = interactive_mode;
+        self
+    }
+
+    /// Only run the rules having a name or a tag, can be given several times
+    pub fn only(mut self, label: &str) -> Self {
+        self.selection.only.push(label.to_string());
+        self
+    }
+
+    /// Do not run the rules having a name or a tag, can be given several times
+    pub fn skip(mut self, label: &str) -> Self {
+        self.selection.skip.push(label.to_string());
+        self
+    }
+
+    /// Set the command to launch, `Command::Clean` by default
+    pub fn command(mut self, command: Command) -> Self {
+        self.command = Some(command);
+        self
+    }
+
+    /// Set the format the events are written in, `OutputFormat::Text` by default
+    pub fn output(mut self, output: OutputFormat) -> Self {
+        self.output = Some(output);
+        self
+    }
+
+    /// Set the `Filesystem` the `.crc` files are read from and the rules change files in, the disk by default
+    pub fn filesystem(mut self, filesystem: Arc<dyn Filesystem>) -> Self {
+        self.filesystem = Some(filesystem);
+        self
+    }
+
+    /// Return the `Application`, after reading its `.crc` files.
+    /// Without any `.crc` file nor rules, the first `.crc` file of the current folder is read.
+    pub fn build(self) -> io::Result<App> {
+        let invalid = |message: &str| io::Error::new(io::ErrorKind::InvalidInput, message);
+        let filesystem = self.filesystem.unwrap_or_else(|| Arc::new(StdFs));
+        let command = self.command.unwrap_or(Command::Clean);
+        let needs_rules = matches!(
+            command,
+            Command::Clean
+                | Command::Tui
+                | Command::Watch { .. }
+                | Command::Schedule { .. }
+                | Command::Explain { .. }
+        );
+        let needs_target = matches!(
+            command,
+            Command::Clean | Command::Tui | Command::Dupes { .. } | Command::Init { .. }
+        );
+        if needs_target && self.targets.is_empty() {
+            return Err(invalid("no folder to clean was given"));
+        }
+
+        let command = match command {
+            Command::Check { files } if files.is_empty() => Command::Check {
+                files: App::default_conf_files_in(&*filesystem)?,
+            },
+            Command::Fmt { files, check } if files.is_empty() => Command::Fmt {
+                files: App::default_conf_files_in(&*filesystem)?,
+                check,
+            },
+            x => x,
+        };
+        let mut config_files = self.config_files;
+        if needs_rules && config_files.is_empty() && self.config_strs.is_empty() {
+            match App::default_conf_file_in(&*filesystem) {
+                Some(x) => config_files.push(x),
+                None => return Err(invalid("no configuration file in the current folder")),
+            }
+        }
+        let mut configurations: Vec<Config> = vec![];
+        for file in &config_files {
+            let config = Config::load(filesystem.clone(), file).map_err(|e| {
+                io::Error::new(e.kind(), format!("could not read `{}`: {}", file, e))
+            })?;
+            configurations.push(config);
+        }
+        for source in &self.config_strs {
+            let mut config: Config = source.parse().unwrap_or_else(|x| match x {});
+            config.set_filesystem(filesystem.clone());
+            configurations.push(config);
+        }
+        let rules: Vec<&Rule> = configurations.iter().flat_map(|x| x.get_rules()).collect();
+        if let Some(x) = self.selection.unknown_labels(&rules).first() {
+            return Err(invalid(&format!("no rule is named or tagged `{}`", x)));
+        }
+        for config in configurations.iter_mut() {
+            config.set_selection(self.selection.clone());
+        }
+
+        Ok(App {
+            configurations,
+            interactive_mode: self.interactive_mode,
+            clean_path: self.targets.first().cloned().unwrap_or_default(),
+            conf_file_path: config_files.first().cloned().unwrap_or_default(),
+            dry_run: self.dry_run,
+            command,
+            output: self.output.unwrap_or(OutputFormat::Text),
+            filesystem,
+        })
+    }
+}
@@ -0,0 +1,464 @@
+use crate::components::copy;
+use chrono::{DateTime, Local};
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use regex::{Captures, Regex};
+use std::collections::HashMap;
+use std::fmt::Write as FmtWrite;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Read, Write};
+use std::path::{Component, Path, PathBuf};
+use xz2::read::XzDecoder;
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipArchive, ZipWriter};
+
+/// Format of an archive created by a `zip` or `tar.gz` rule, or read by an `extract` rule
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ArchiveFormat {
+    Zip,
+    Tar,
+    TarGz,
+    TarXz,
+}
+
+impl ArchiveFormat {
+    /// Return the `ArchiveFormat` created by a `.crc` keyword
+    pub fn from_keyword(keyword: &str) -> Option<Self> {
+        match keyword {
+            "zip" => Some(Self::Zip),
+            "tar.gz" => Some(Self::TarGz),
+            _ => None,
+        }
+    }
+
+    /// Return the `ArchiveFormat` of a file from its first bytes, whatever its extension
+    pub fn detect(path: &Path) -> io::Result<Option<Self>> {
+        let mut header = Vec::new();
+        File::open(path)?.take(512).read_to_end(&mut header)?;
+        if header.starts_with(b"PK\x03\x04") || header.starts_with(b"PK\x05\x06") {
+            Ok(Some(Self::Zip))
+        } else if header.starts_with(&[0x1f, 0x8b]) {
+            Ok(Some(Self::TarGz))
+        } else if header.starts_with(&[0xfd, b'7', b'z', b'X', b'Z', 0x00]) {
+            Ok(Some(Self::TarXz))
+        } else if header.len() >= 262 && &header[257..262] == b"ustar" {
+            Ok(Some(Self::Tar))
+        } else {
+            Ok(None)
+        }
+    }
+}
+
+/// Options that can follow an `extract` rule
+#[derive(Clone, Debug, PartialEq)]
+pub struct ExtractOptions {
+    /// Extract the content of the folder when the archive only contains one folder
+    pub flatten: bool,
+    /// Maximum number of bytes written by the extraction of one archive
+    pub max_size: u64,
+    /// Maximum number of files and folders in one archive
+    pub max_entries: usize,
+}
+
+impl Default for ExtractOptions {
+    fn default() -> Self {
+        Self {
+            flatten: false,
+            max_size: 1024 * 1024 * 1024,
+            max_entries: 10_000,
+        }
+    }
+}
+
+/// Parse a size such as `512`, `100K`, `20M` or `2G` into a number of bytes
+pub fn parse_size(size: &str) -> Option<u64> {
+    let (number, unit) = match size.find(|c: char| !c.is_ascii_digit()) {
+        Some(i) => size.split_at(i),
+        None => (size, ""),
+    };
+    let multiplier: u64 = match unit.to_uppercase().as_str() {
+        "" | "B" => 1,
+        "K" | "KB" => 1024,
+        "M" | "MB" => 1024 * 1024,
+        "G" | "GB" => 1024 * 1024 * 1024,
+        _ => return None,
+    };
+    number.parse::<u64>().ok()?.checked_mul(multiplier)
+}
+
+/// Options that can follow a `zip` or `tar.gz` rule
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ArchiveOptions {
+    /// Delete the original files once the archive has been verified
+    pub remove: bool,
+    /// Add the files to the archive if it already exists instead of replacing it
+    pub append: bool,
+}
+
+/// Replace the `{now:<format>}` placeholders of an archive name by the formatted date
+pub fn expand_date_placeholders(name: &str, now: &DateTime<Local>) -> Result<String, String> {
+    let placeholder = Regex::new(r"\{now:([^}]*)\}").unwrap();
+    let mut error = None;
+    let expanded = placeholder.replace_all(name, |caps: &Captures| {
+        let mut formatted = String::new();
+        if write!(formatted, "{}", now.format(&caps[1])).is_err() {
+            error = Some(format!("Invalid date placeholder `{}`.", &caps[0]));
+        }
+        formatted
+    });
+    match error {
+        Some(x) => Err(x),
+        None => Ok(expanded.to_string()),
+    }
+}
+
+/// Add files to an archive, then check that every file can be read back from it
+pub fn create_archive(
+    format: ArchiveFormat,
+    archive: &Path,
+    files: &[PathBuf],
+    options: &ArchiveOptions,
+) -> io::Result<()> {
+    if let Some(parent) = archive.parent() {
+        if !parent.as_os_str().is_empty() {
+            fs::create_dir_all(parent)?;
+        }
+    }
+    let append = options.append && archive.exists();
+    let existing: Vec<String> = match append {
+        true => archived_checksums(format, archive)?.into_keys().collect(),
+        false => vec![],
+    };
+    let entries = entry_names(files, existing);
+    // The archive is written and checked in a temporary file next to it, which only replaces it once complete
+    let temporary = archive.with_file_name(format!(".{}.crabby", entry_name(archive)));
+    let written = write_archive(format, &temporary, archive, &entries, append)
+        .and_then(|_| fs::rename(&temporary, archive));
+    if written.is_err() {
+        let _ = fs::remove_file(&temporary);
+    }
+    written?;
+
+    if options.remove {
+        for file in files {
+            fs::remove_file(file)?;
+        }
+    }
+    Ok(())
+}
+
+/// Return the name a file has inside an archive
+fn entry_name(file: &Path) -> String {
+    match file.file_name() {
+        Some(x) => x.to_string_lossy().to_string(),
+        None => String::new(),
+    }
+}
+
+/// Return the files with the names of their entries, numbered like `name (n).ext` when an entry already has the name
+fn entry_names(files: &[PathBuf], mut taken: Vec<String>) -> Vec<(PathBuf, String)> {
+    let mut entries = vec![];
+    for file in files {
+        let mut name = entry_name(file);
+        let mut n = 1;
+        while taken.contains(&name) {
+            name = copy::numbered_name(file, n);
+            n += 1;
+        }
+        taken.push(name.clone());
+        entries.push((file.clone(), name));
+    }
+    entries
+}
+
+/// Write the files of an archive, and the entries of the existing one when appending, in a temporary file, then
+/// check that every file can be read back from it
+fn write_archive(
+    format: ArchiveFormat,
+    temporary: &Path,
+    archive: &Path,
+    entries: &[(PathBuf, String)],
+    append: bool,
+) -> io::Result<()> {
+    match format {
+        ArchiveFormat::Zip => write_zip_to(temporary, archive, entries, append)?,
+        ArchiveFormat::TarGz => write_tar_gz_to(temporary, archive, entries, append)?,
+        _ => {
+            return Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "only zip and tar.gz archives can be created",
+            ))
+        }
+    }
+    let archived = archived_checksums(format, temporary)?;
+    for (file, name) in entries {
+        if archived.get(name) != Some(&copy::checksum(file)?) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("{} is missing or corrupted in {}", name, archive.display()),
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Write the zip archive of `write_archive` in a temporary file, from a copy of the existing one when appending
+fn write_zip_to(
+    temporary: &Path,
+    archive: &Path,
+    entries: &[(PathBuf, String)],
+    append: bool,
+) -> io::Result<()> {
+    let mut writer = if append {
+        fs::copy(archive, temporary)?;
+        let file = OpenOptions::new().read(true).write(true).open(temporary)?;
+        ZipWriter::new_append(file).map_err(io::Error::other)?
+    } else {
+        ZipWriter::new(File::create(temporary)?)
+    };
+    let options = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+    for (file, name) in entries {
+        writer
+            .start_file(name.as_str(), options)
+            .map_err(io::Error::other)?;
+        io::copy(&mut File::open(file)?, &mut writer)?;
+    }
+    writer.finish().map_err(io::Error::other)?;
+    Ok(())
+}
+
+/// Write the gzipped tarball of `write_archive` in a temporary file, with the entries of the existing one when
+/// appending
+fn write_tar_gz_to(
+    temporary: &Path,
+    archive: &Path,
+    entries: &[(PathBuf, String)],
+    append: bool,
+) -> io::Result<()> {
+    let encoder = GzEncoder::new(File::create(temporary)?, Compression::default());
+    let mut builder = tar::Builder::new(encoder);
+
+    if append {
+        let mut existing = tar::Archive::new(GzDecoder::new(File::open(archive)?));
+        for entry in existing.entries()? {
+            let mut entry = entry?;
+            let header = entry.header().clone();
+            builder.append(&header, &mut entry)?;
+        }
+    }
+    for (file, name) in entries {
+        builder.append_path_with_name(file, name)?;
+    }
+    builder.into_inner()?.finish()?;
+    Ok(())
+}
+
+/// Return the checksums of the entries of an archive, by name
+fn archived_checksums(format: ArchiveFormat, archive: &Path) -> io::Result<HashMap<String, u64>> {
+    let mut checksums = HashMap::new();
+    match format {
+        ArchiveFormat::Zip => {
+            let mut zip = ZipArchive::new(File::open(archive)?).map_err(io::Error::other)?;
+            for i in 0..zip.len() {
+                let entry = zip.by_index(i).map_err(io::Error::other)?;
+                let name = entry.name().map_err(io::Error::other)?.to_string();
+                checksums.insert(name, copy::checksum_of(entry)?);
+            }
+        }
+        _ => {
+            let mut tar = tar::Archive::new(tar_reader(format, archive)?);
+            for entry in tar.entries()? {
+                let entry = entry?;
+                let name = entry.path()?.to_string_lossy().to_string();
+                checksums.insert(name, copy::checksum_of(entry)?);
+            }
+        }
+    }
+    Ok(checksums)
+}
+
+/// Return a reader over the uncompressed tarball of an archive
+fn tar_reader(format: ArchiveFormat, archive: &Path) -> io::Result<Box<dyn Read>> {
+    let file = File::open(archive)?;
+    Ok(match format {
+        ArchiveFormat::TarGz => Box::new(GzDecoder::new(file)),
+        ArchiveFormat::TarXz => Box::new(XzDecoder::new(file)),
+        _ => Box::new(file),
+    })
+}
+
+/// Extract an archive in a folder and return the number of extracted files.
+/// The archive is first extracted in a hidden staging folder, so nothing is left behind when an
+/// entry is unsafe or a limit is exceeded.
+pub fn extract_archive(
+    archive: &Path,
+    destination: &Path,
+    options: &ExtractOptions,
+) -> io::Result<usize> {
+    let format = match ArchiveFormat::detect(archive)? {
+        Some(x) => x,
+        None => {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("{} is not a supported archive", archive.display()),
+            ))
+        }
+    };
+    fs::create_dir_all(destination)?;
+    let staging = destination.join(format!(".{}.crabby", entry_name(archive)));
+    let _ = fs::remove_dir_all(&staging);
+    fs::create_dir(&staging)?;
+
+    let mut limits = Limits::new(options);
+    let extracted = match format {
+        ArchiveFormat::Zip => extract_zip(archive, &staging, &mut limits),
+        _ => extract_tar(format, archive, &staging, &mut limits),
+    }
+    .and_then(|_| move_extracted(&staging, destination, options.flatten));
+    let _ = fs::remove_dir_all(&staging);
+    extracted.map(|_| limits.files)
+}
+
+/// Counters used to stop the extraction of archive bombs
+struct Limits {
+    entries: usize,
+    files: usize,
+    size: u64,
+    max_entries: usize,
+    max_size: u64,
+}
+
+impl Limits {
+    fn new(options: &ExtractOptions) -> Self {
+        Self {
+            entries: 0,
+            files: 0,
+            size: 0,
+            max_entries: options.max_entries,
+            max_size: options.max_size,
+        }
+    }
+
+    /// Count a new entry
+    fn add_entry(&mut self) -> io::Result<()> {
+        self.entries += 1;
+        if self.entries > self.max_entries {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("archive has more than {} entries", self.max_entries),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Write the content of an entry in a new file, counting the bytes really written
+    fn write_file<R: Read>(&mut self, reader: &mut R, path: &Path) -> io::Result<()> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let mut file = File::create(path)?;
+        let mut buffer = [0u8; 64 * 1024];
+        loop {
+            let read = reader.read(&mut buffer)?;
+            if read == 0 {
+                break;
+            }
+            self.size += read as u64;
+            if self.size > self.max_size {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("archive is bigger than {} bytes", self.max_size),
+                ));
+            }
+            file.write_all(&buffer[..read])?;
+        }
+        self.files += 1;
+        Ok(())
+    }
+}
+
+/// Return the path of an entry inside the staging folder, refusing absolute paths and `..`
+fn safe_entry_path(staging: &Path, name: &Path) -> io::Result<PathBuf> {
+    let mut path = staging.to_path_buf();
+    for component in name.components() {
+        match component {
+            Component::Normal(x) => path.push(x),
+            Component::CurDir => continue,
+            _ => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("unsafe path `{}` in archive", name.display()),
+                ))
+            }
+        }
+    }
+    Ok(path)
+}
+
+/// Extract a zip archive in the staging folder
+fn extract_zip(archive: &Path, staging: &Path, limits: &mut Limits) -> io::Result<()> {
+    let mut zip = ZipArchive::new(File::open(archive)?).map_err(io::Error::other)?;
+    for i in 0..zip.len() {
+        limits.add_entry()?;
+        let mut entry = zip.by_index(i).map_err(io::Error::other)?;
+        let name = PathBuf::from(entry.name().map_err(io::Error::other)?.to_string());
+        let path = safe_entry_path(staging, &name)?;
+        if entry.is_dir() {
+            fs::create_dir_all(&path)?;
+        } else if !entry.is_symlink() {
+            limits.write_file(&mut entry, &path)?;
+        }
+    }
+    Ok(())
+}
+
+/// Extract a tarball in the staging folder, skipping links and special files
+fn extract_tar(
+    format: ArchiveFormat,
+    archive: &Path,
+    staging: &Path,
+    limits: &mut Limits,
+) -> io::Result<()> {
+    let mut tar = tar::Archive::new(tar_reader(format, archive)?);
+    for entry in tar.entries()? {
+        limits.add_entry()?;
+        let mut entry = entry?;
+        let path = safe_entry_path(staging, &entry.path()?)?;
+        let kind = entry.header().entry_type();
+        if kind.is_dir() {
+            fs::create_dir_all(&path)?;
+        } else if kind.is_file() {
+            limits.write_file(&mut entry, &path)?;
+        }
+    }
+    Ok(())
+}
+
+/// Move the extracted files from the staging folder to the destination
+fn move_extracted(staging: &Path, destination: &Path, flatten: bool) -> io::Result<()> {
+    let mut root = staging.to_path_buf();
+    let entries: Vec<PathBuf> = fs::read_dir(staging)?
+        .map(|x| x.map(|y| y.path()))
+        .collect::<io::Result<_>>()?;
+    if flatten && entries.len() == 1 && entries[0].is_dir() {
+        root = entries[0].clone();
+    }
+
+    let entries: Vec<PathBuf> = fs::read_dir(&root)?
+        .map(|x| x.map(|y| y.path()))
+        .collect::<io::Result<_>>()?;
+    for entry in &entries {
+        let target = destination.join(entry_name(entry));
+        if target.exists() {
+            return Err(io::Error::new(
+                io::ErrorKind::AlreadyExists,
+                format!("{} already exists", target.display()),
+            ));
+        }
+    }
+    for entry in &entries {
+        fs::rename(entry, destination.join(entry_name(entry)))?;
+    }
+    Ok(())
+}
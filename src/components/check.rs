@@ -0,0 +1,513 @@
+use crate::components::evaluation::{Evaluation, Flow, Scope};
+use crate::components::rule::{
+    FIRST_VALID_TOKENS, SECOND_VALID_TOKENS, TOKEN_PAIRS_INDEXES, UNARY_VALID_TOKENS,
+};
+use crate::components::syntax::{self, LineKind, SyntaxTree, Word};
+use crate::components::Rule;
+use crate::App;
+use regex::Regex;
+use serde::Serialize;
+
+/// Options that all rules accept, the ones followed by a value first
+const COMMON_OPTIONS: [&str; 9] = [
+    "except",
+    "older-than",
+    "every",
+    "at",
+    "allow-protected",
+    "stop",
+    "continue",
+    "ignore-case",
+    "unicode",
+];
+
+/// Options followed by a value
+const OPTIONS_WITH_VALUE: [&str; 11] = [
+    "except",
+    "older-than",
+    "every",
+    "at",
+    "conflict",
+    "max-size",
+    "max-entries",
+    "keep",
+    "prefer",
+    "spaces",
+    "max-length",
+];
+
+/// Errors about the value of an option, and the option they are about
+const VALUE_ERRORS: [(&str, &str); 13] = [
+    ("Missing exception pattern.", "except"),
+    ("Invalid age.", "older-than"),
+    ("Missing schedule.", "every"),
+    ("Invalid schedule.", "every"),
+    ("Missing time.", "at"),
+    ("Invalid time, expected HH:MM.", "at"),
+    ("Invalid conflict policy.", "conflict"),
+    ("Invalid maximum size.", "max-size"),
+    ("Invalid maximum number of entries.", "max-entries"),
+    ("Invalid keep policy.", "keep"),
+    ("Missing preferred folder.", "prefer"),
+    ("Invalid space replacement.", "spaces"),
+    ("Invalid maximum length.", "max-length"),
+];
+
+/// How serious a `Diagnostic` is
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Severity {
+    /// The rule is invalid and is never executed
+    Error,
+    /// The rule is valid but probably does not do what it is written for
+    Warning,
+}
+
+/// A problem found in a line of a `.crc` file, with the columns of the text it is about
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub file: String,
+    pub line: usize,
+    /// First column of the text the diagnostic is about, from 1
+    pub column: usize,
+    /// Number of characters of the text the diagnostic is about
+    pub length: usize,
+    pub message: String,
+    /// What to do about it, written under the text
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub help: Option<String>,
+    /// The line of the `.crc` file
+    pub source: String,
+}
+
+impl Diagnostic {
+    /// Return the diagnostic the way a compiler writes it, with the text it is about underlined
+    pub fn render(&self) -> String {
+        let severity = match self.severity {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+        };
+        let number = self.line.to_string();
+        let gutter = " ".repeat(number.len());
+        format!(
+            "{}: {}\n{}--> {}:{}:{}\n{} |\n{} | {}\n{} | {}{} {}",
+            severity,
+            self.message,
+            gutter,
+            self.file,
+            self.line,
+            self.column,
+            gutter,
+            number,
+            self.source,
+            gutter,
+            " ".repeat(self.column.saturating_sub(1)),
+            "^".repeat(self.length.max(1)),
+            self.help.as_deref().unwrap_or_default()
+        )
+        .trim_end()
+        .to_string()
+    }
+}
+
+/// Return the number of characters to change to write a word as another
+fn distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, x) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, y) in b.iter().enumerate() {
+            let cost = if x == *y { 0 } else { 1 };
+            current.push(
+                (previous[j] + cost)
+                    .min(previous[j + 1] + 1)
+                    .min(current[j] + 1),
+            );
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+/// Return the candidate a mistyped word was probably meant to be
+fn suggest<'a>(word: &str, candidates: &[&'a str]) -> Option<&'a str> {
+    candidates
+        .iter()
+        .map(|x| (distance(word, x), *x))
+        .filter(|(d, x)| *d <= 2 && *d < x.len().max(word.len()))
+        .min_by_key(|(d, _)| *d)
+        .map(|(_, x)| x)
+}
+
+/// Return the second keyword that must follow a first keyword
+pub(crate) fn second_keyword(first: &str) -> Option<&'static str> {
+    TOKEN_PAIRS_INDEXES
+        .iter()
+        .find(|x| FIRST_VALID_TOKENS[x[0] as usize] == first)
+        .map(|x| SECOND_VALID_TOKENS[x[1] as usize])
+}
+
+/// Return the options a rule accepts after its patterns, for its first keyword
+pub(crate) fn options_of(first: &str) -> Vec<&'static str> {
+    let options: &[&str] = match first {
+        "mv" | "cp" => &["conflict", "preserve", "verify", "hardlink", "reflink"],
+        "zip" | "tar.gz" => &["remove", "append"],
+        "extract" => &["flatten", "max-size", "max-entries"],
+        "dedupe" => &["keep", "prefer", "hardlink"],
+        "normalize" => &[
+            "nfc",
+            "nfd",
+            "url-decode",
+            "lowercase-ext",
+            "spaces",
+            "strip-copies",
+            "ascii",
+            "max-length",
+        ],
+        _ => &[],
+    };
+    options
+        .iter()
+        .chain(COMMON_OPTIONS.iter())
+        .copied()
+        .collect()
+}
+
+/// Return the index of the token an error is about, or `None` when something is missing at the end of the line,
+/// with the help to show under it
+fn locate(tokens: &[Word], error: &str) -> (Option<usize>, Option<String>) {
+    let first = tokens.first().map(|x| x.text.as_str()).unwrap_or_default();
+    let core_length = match UNARY_VALID_TOKENS.contains(&first) {
+        true => 2,
+        false => 4,
+    };
+    let did_you_mean = |x: Option<&str>| x.map(|y| format!("did you mean `{}`?", y));
+    match error {
+        "Invalid first keyword." => {
+            let candidates: Vec<&str> = FIRST_VALID_TOKENS
+                .iter()
+                .chain(UNARY_VALID_TOKENS.iter())
+                .chain(["ignore", "let", "include", "evaluate", "match", "in"].iter())
+                .copied()
+                .collect();
+            (Some(0), did_you_mean(suggest(first, &candidates)))
+        }
+        "Invalid second keyword." => {
+            let expected =
+                second_keyword(first).or_else(|| suggest(&tokens[2].text, &SECOND_VALID_TOKENS));
+            (Some(2), did_you_mean(expected))
+        }
+        "Invalid pattern." => (Some(1), None),
+        "Keywords' pair doesn't exists." => (
+            Some(2),
+            second_keyword(first).map(|x| format!("`{}` is followed by `{}`", first, x)),
+        ),
+        "Missing keyword(s) or pattern(s)." => {
+            let help = match second_keyword(first) {
+                Some(x) => Some(format!(
+                    "a `{}` rule is written `{} <pattern> {} <pattern>`",
+                    first, first, x
+                )),
+                None if UNARY_VALID_TOKENS.contains(&first) => Some(format!(
+                    "a `{}` rule is written `{} <pattern>`",
+                    first, first
+                )),
+                None => None,
+            };
+            (None, help)
+        }
+        "Query has too much elements." | "Invalid option." => {
+            let options = options_of(first);
+            let mut i = core_length;
+            while i < tokens.len() {
+                let text = tokens[i].text.as_str();
+                if !options.contains(&text) {
+                    return (Some(i), did_you_mean(suggest(text, &options)));
+                }
+                if OPTIONS_WITH_VALUE.contains(&text) {
+                    i += 1;
+                }
+                i += 1;
+            }
+            (Some(tokens.len() - 1), None)
+        }
+        "A time can only be given to a daily or weekly schedule." => {
+            (tokens.iter().position(|x| x.text == "at"), None)
+        }
+        x if x.starts_with("Invalid date placeholder") => (Some(3), None),
+        x => match VALUE_ERRORS.iter().find(|y| y.0 == x) {
+            Some((_, option)) => match tokens.iter().rposition(|y| y.text == *option) {
+                Some(i) if i + 1 < tokens.len() => (Some(i + 1), None),
+                Some(i) => (Some(i), None),
+                None => (Some(0), None),
+            },
+            None => (Some(0), None),
+        },
+    }
+}
+
+/// Return the message of an error of a rule, written as a compiler diagnostic
+fn message(error: &str) -> String {
+    let error = error.trim_end_matches('.');
+    let mut chars = error.chars();
+    match chars.next() {
+        Some(x) => x.to_lowercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+/// Check if every file name following a pattern follows another one
+fn covers(pattern: &[char], other: &[char]) -> bool {
+    match (pattern.first(), other.first()) {
+        (None, None) => true,
+        (None, Some(_)) => false,
+        (Some('*'), _) => {
+            covers(&pattern[1..], other) || (!other.is_empty() && covers(pattern, &other[1..]))
+        }
+        (Some(_), Some('*')) | (Some(_), None) => false,
+        (Some(x), Some(y)) => x == y && covers(&pattern[1..], &other[1..]),
+    }
+}
+
+/// Check if a file name can follow two patterns
+fn overlaps(a: &[char], b: &[char]) -> bool {
+    match (a.first(), b.first()) {
+        (None, None) => true,
+        (Some('*'), _) => overlaps(&a[1..], b) || (!b.is_empty() && overlaps(a, &b[1..])),
+        (_, Some('*')) => overlaps(a, &b[1..]) || (!a.is_empty() && overlaps(&a[1..], b)),
+        (Some(x), Some(y)) => x == y && overlaps(&a[1..], &b[1..]),
+        _ => false,
+    }
+}
+
+/// Return what a rule does to all the files it matches, if it takes them from the next rules in an evaluation mode
+fn takes_files(rule: &Rule, evaluation: Evaluation) -> Option<&'static str> {
+    if !rule.get_exceptions().is_empty() || rule.get_older_than().is_some() {
+        return None;
+    }
+    match (rule.get_flow(), evaluation) {
+        (Some(Flow::Continue), _) => return None,
+        (Some(Flow::Stop), _) | (None, Evaluation::FirstMatch) => return Some("claims"),
+        _ => (),
+    }
+    match rule.get_tokens()[0].as_str() {
+        "mv" => Some("moves"),
+        "zip" | "tar.gz" if rule.get_archive_options().remove => Some("archives"),
+        _ => None,
+    }
+}
+
+/// Return the diagnostics of the lines of a `.crc` file, without executing its rules nor reading its includes: the
+/// errors of the invalid lines, and warnings for the unknown variables, the rules named like an earlier rule and the rules
+/// that an earlier rule takes files from
+pub fn check(file: &str, content: &str) -> Vec<Diagnostic> {
+    let mut diagnostics: Vec<Diagnostic> = vec![];
+    let mut rules: Vec<Rule> = vec![];
+    let tree = SyntaxTree::parse(content);
+    let mut variables: Vec<(String, String)> = vec![];
+    let reference = Regex::new(r"\$([A-Za-z_][A-Za-z0-9_]*)").unwrap();
+    // The names of the rules, with their line
+    let mut names: Vec<(String, usize)> = vec![];
+    // The scope of the open block, with the error to report if it is never closed
+    let mut block: Option<(Scope, Diagnostic)> = None;
+    let evaluation = tree
+        .lines
+        .iter()
+        .rev()
+        .filter(|x| x.kind() == LineKind::Evaluate)
+        .find_map(|x| x.evaluation().ok())
+        .unwrap_or_default();
+    for (i, line) in content.split('\n').enumerate() {
+        let source = line.trim_end().to_string();
+        let diagnostic = |severity: Severity, token: Option<&Word>, message: String| {
+            let end = App::remove_comment(&source).trim_end().chars().count();
+            Diagnostic {
+                severity,
+                file: file.to_string(),
+                line: i + 1,
+                column: token.map_or(end + 1, |x| x.column),
+                length: token.map_or(1, |x| x.text.chars().count()),
+                message,
+                help: None,
+                source: source.clone(),
+            }
+        };
+        let tokens = tree.lines[i].words.clone();
+        let definition = match tree.lines[i].kind() {
+            LineKind::Variable => Some(tree.lines[i].variable().map(|(x, y)| {
+                let value = syntax::substitute(y, &variables);
+                variables.push((x.to_string(), value));
+            })),
+            LineKind::Include => Some(tree.lines[i].include().map(|_| ())),
+            LineKind::Evaluate => Some(tree.lines[i].evaluation().map(|_| ())),
+            LineKind::Match => Some(tree.lines[i].matching().map(|_| ())),
+            LineKind::Block => Some(match (tree.lines[i].block(), &block) {
+                (Err(e), _) => Err(e),
+                (Ok(_), Some(_)) => Err(String::from("Blocks can not be nested.")),
+                (Ok((dir, recursive)), None) => {
+                    let scope = Scope {
+                        dir: syntax::substitute(dir, &variables),
+                        recursive,
+                    };
+                    let unclosed = Diagnostic {
+                        help: Some(String::from("close it with a `}` line")),
+                        ..diagnostic(
+                            Severity::Error,
+                            tokens.first(),
+                            String::from("unclosed block"),
+                        )
+                    };
+                    block = Some((scope, unclosed));
+                    Ok(())
+                }
+            }),
+            LineKind::BlockEnd => Some(match block.take() {
+                Some(_) => Ok(()),
+                None => Err(String::from("No block to close.")),
+            }),
+            _ => None,
+        };
+        if let Some(result) = definition {
+            if let Err(e) = result {
+                diagnostics.push(diagnostic(Severity::Error, tokens.first(), message(&e)));
+            }
+            continue;
+        }
+        for token in &tokens {
+            for name in reference.captures_iter(&token.text) {
+                if !variables.iter().any(|x| x.0 == name[1]) {
+                    diagnostics.push(Diagnostic {
+                        help: Some(format!(
+                            "define it before with `let {} = <value>`",
+                            &name[1]
+                        )),
+                        ..diagnostic(
+                            Severity::Warning,
+                            Some(token),
+                            format!("unknown variable `{}`", &name[0]),
+                        )
+                    });
+                }
+            }
+        }
+        if tree.lines[i].kind() == LineKind::Ignore {
+            if tokens.len() == 1 {
+                diagnostics.push(Diagnostic {
+                    help: Some(String::from(
+                        "an `ignore` line is written `ignore <pattern>...`",
+                    )),
+                    ..diagnostic(
+                        Severity::Warning,
+                        None,
+                        String::from("no pattern to ignore"),
+                    )
+                });
+            }
+            continue;
+        }
+        let mut rule = Rule::new_from_line(&syntax::substitute(line, &variables), &i);
+        if *rule.get_line_number() == 0 {
+            continue;
+        }
+        rule.set_scope(block.as_ref().map(|x| x.0.clone()));
+        // The `@name(...)` and `@tag(...)` annotations written before the keywords of the rule
+        let annotated = tokens
+            .iter()
+            .take_while(|x| x.text.starts_with('@'))
+            .count();
+        if !rule.get_error_message().is_empty() || !*rule.is_valid() {
+            let (index, help) = match rule.get_error_message().as_str() {
+                "Only one `@name` is allowed." => (
+                    tokens
+                        .iter()
+                        .enumerate()
+                        .filter(|x| x.1.text.starts_with("@name("))
+                        .nth(1)
+                        .map(|x| x.0),
+                    None,
+                ),
+                x if x.starts_with("Invalid annotation") => (
+                    tokens[..annotated]
+                        .iter()
+                        .position(|y| Rule::annotation(&y.text).is_none()),
+                    None,
+                ),
+                x => {
+                    let (index, help) = locate(&tokens[annotated..], x);
+                    (index.map(|y| y + annotated), help)
+                }
+            };
+            let token = index.and_then(|x| tokens.get(x));
+            diagnostics.push(Diagnostic {
+                help,
+                ..diagnostic(Severity::Error, token, message(rule.get_error_message()))
+            });
+            continue;
+        }
+        if let Some(name) = rule.get_name() {
+            match names.iter().find(|x| x.0 == *name) {
+                Some((_, line)) => diagnostics.push(Diagnostic {
+                    help: Some(format!("rule line {} is already named `{}`", line, name)),
+                    ..diagnostic(
+                        Severity::Warning,
+                        tokens.iter().find(|x| x.text.starts_with("@name(")),
+                        String::from("duplicate rule name"),
+                    )
+                }),
+                None => names.push((name.clone(), i + 1)),
+            }
+        }
+        if rule.get_schedule().is_some() {
+            continue;
+        }
+        let pattern: Vec<char> = rule.get_tokens()[1].chars().collect();
+        for earlier in rules.iter().filter(|x| x.get_scope() == rule.get_scope()) {
+            let verb = match takes_files(earlier, evaluation) {
+                Some(x) => x,
+                None => continue,
+            };
+            let earlier_pattern: Vec<char> = earlier.get_tokens()[1].chars().collect();
+            let (message, help) = match (
+                covers(&earlier_pattern, &pattern),
+                overlaps(&earlier_pattern, &pattern),
+            ) {
+                (true, _) => (
+                    "unreachable rule",
+                    format!(
+                        "rule line {} {} all the files matching `{}` first",
+                        earlier.get_line_number(),
+                        verb,
+                        earlier.get_tokens()[1]
+                    ),
+                ),
+                (false, true) => (
+                    "overlapping rules",
+                    format!(
+                        "the files also matching `{}` are {} by rule line {} first",
+                        earlier.get_tokens()[1],
+                        match verb {
+                            "moves" => "moved",
+                            "claims" => "claimed",
+                            _ => "archived",
+                        },
+                        earlier.get_line_number()
+                    ),
+                ),
+                _ => continue,
+            };
+            diagnostics.push(Diagnostic {
+                help: Some(help),
+                ..diagnostic(
+                    Severity::Warning,
+                    tokens.get(annotated + 1),
+                    message.to_string(),
+                )
+            });
+            break;
+        }
+        rules.push(rule);
+    }
+    diagnostics.extend(block.map(|x| x.1));
+    diagnostics
+}
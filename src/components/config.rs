@@ -1,18 +1,238 @@
+use crate::components::confirm::{Confirmation, TerminalConfirmer};
+use crate::components::evaluation::{self, Evaluation, Flow, Scope, Selection};
+use crate::components::filesystem::{Filesystem, StdFs};
+use crate::components::matching::Matching;
+use crate::components::observer::Observer;
+use crate::components::output::{Output, OutputFormat};
+use crate::components::plan::{Action, Operation, Outcome};
+use crate::components::syntax::{self, LineKind, SyntaxTree};
 use crate::components::Rule;
 use crate::App;
+use std::convert::Infallible;
+use std::io;
+use std::path::Path;
+use std::str::FromStr;
+use std::sync::Arc;
+
+/// Executes or plans a rule in a folder on some candidate files, and returns the operations that happened
+pub(crate) type RuleRunner<'a> = dyn FnMut(&mut Rule, &str, Vec<String>) -> Vec<Operation> + 'a;
 
 /// A Configuration is created from a `.crc` file specified in the Application structure and implementation
 #[derive(Clone, Debug)]
 pub struct Config {
     filename: String,
     rules: Vec<Rule>,
+    ignored: Vec<String>,
+    evaluation: Evaluation,
+    selection: Selection,
+    filesystem: Arc<dyn Filesystem>,
 }
 
 impl Config {
-    /// Execute all the rules in the current `Configuration`
+    /// Execute all the rules in the current `Configuration`, except the scheduled and unselected ones
     pub fn execute_rules(&self, path: String, dry_run: bool, interactive_mode: bool) {
-        for mut rule in self.rules.clone() {
-            rule.execute(path.clone(), dry_run, interactive_mode);
+        let mut output = Output::new(OutputFormat::Text);
+        let mut terminal = TerminalConfirmer::new();
+        let mut confirmation = match interactive_mode {
+            true => Confirmation::new(&mut terminal),
+            false => Confirmation::none(),
+        };
+        self.execute_rules_with(path, dry_run, &mut confirmation, &mut output);
+    }
+
+    /// Execute all the rules in the current `Configuration`, except the scheduled and unselected ones, telling an
+    /// `Observer` what they do.
+    /// The rules stop when the user quits in interactive mode.
+    pub fn execute_rules_with(
+        &self,
+        path: String,
+        dry_run: bool,
+        confirmation: &mut Confirmation,
+        observer: &mut dyn Observer,
+    ) {
+        for rule in &self.rules {
+            observer.on_rule_parsed(rule.get_config_file(), rule);
+        }
+        self.evaluate(&path, None, &mut |rule, folder, candidates| {
+            if confirmation.is_quit() {
+                return vec![];
+            }
+            if rule.get_scope().is_some() && candidates.iter().any(|x| rule.concerns(x)) {
+                observer.on_rule_scoped(rule, folder);
+            }
+            rule.execute_among(candidates, dry_run, confirmation, observer)
+                .operations
+                .into_iter()
+                .filter(|x| matches!(x.1, Outcome::Applied | Outcome::Planned))
+                .map(|x| x.0)
+                .collect()
+        });
+    }
+
+    /// Return a `Configuration` holding the scheduled rules accepted by `due`, without their schedule, so that they
+    /// run with the blocks, the evaluation mode and the `stop` and `continue` options of the current one
+    pub fn scheduled(&self, due: impl Fn(&Rule) -> bool) -> Self {
+        let mut config = self.clone();
+        config.rules = self
+            .rules
+            .iter()
+            .filter(|x| x.get_schedule().is_some() && due(x))
+            .cloned()
+            .map(|mut x| {
+                x.set_schedule(None);
+                x
+            })
+            .collect();
+        config
+    }
+
+    /// Give the files of a folder to the rules, except the scheduled and unselected ones, following the evaluation mode
+    /// and the `stop` and `continue` options. The rules of the `in` blocks get the files of their folders instead.
+    /// `run` executes or plans a rule in a folder on some candidate files and returns the operations that happened,
+    /// so that the next rules see the new paths of the files. With `file`, only this file is given to the rules of
+    /// its folder.
+    pub(crate) fn evaluate(&self, dir: &str, file: Option<&str>, run: &mut RuleRunner) {
+        let folders = match file {
+            Some(x) => vec![Path::new(x)
+                .parent()
+                .map(|x| x.to_string_lossy().to_string())
+                .unwrap_or_default()],
+            None => self.folders(dir),
+        };
+        for folder in folders {
+            let rules = self.rules_for(dir, &folder);
+            self.evaluate_rules(&folder, file, rules, run);
+        }
+    }
+
+    /// Return the rules running in a folder when cleaning a target: the rules outside of the blocks if it is the
+    /// target, and the rules of the blocks covering it
+    pub(crate) fn rules_for(&self, target: &str, folder: &str) -> Vec<Rule> {
+        self.rules
+            .iter()
+            .filter(|x| match x.get_scope() {
+                Some(scope) => scope.covers(folder),
+                None => evaluation::normalize(target) == evaluation::normalize(folder),
+            })
+            .cloned()
+            .collect()
+    }
+
+    /// Return the folders the rules run in when cleaning a target: the target, then the folders of the blocks and
+    /// the subfolders of the recursive ones, except the hidden folders and the destinations of the rules
+    fn folders(&self, target: &str) -> Vec<String> {
+        let destinations: Vec<_> = self
+            .rules
+            .iter()
+            .filter_map(|x| x.get_destination_dir())
+            .map(evaluation::normalize)
+            .collect();
+        let mut folders: Vec<String> = vec![target.to_string()];
+        let add = |folders: &mut Vec<String>, folder: String| {
+            let path = evaluation::normalize(&folder);
+            if !folders.iter().any(|x| evaluation::normalize(x) == path) {
+                folders.push(folder);
+            }
+        };
+        for scope in self.rules.iter().filter_map(|x| x.get_scope().as_ref()) {
+            let mut pending = vec![scope.dir.clone()];
+            while let Some(folder) = pending.pop() {
+                if scope.recursive {
+                    let mut subfolders: Vec<String> = self
+                        .filesystem
+                        .read_dir(Path::new(&folder))
+                        .unwrap_or_default()
+                        .into_iter()
+                        .filter(|x| matches!(self.filesystem.metadata(x), Ok(x) if x.is_dir))
+                        .filter(|x| {
+                            !x.file_name()
+                                .is_some_and(|x| x.to_string_lossy().starts_with('.'))
+                        })
+                        .map(|x| x.to_string_lossy().to_string())
+                        .filter(|x| !destinations.contains(&evaluation::normalize(x)))
+                        .collect();
+                    subfolders.sort();
+                    pending.extend(subfolders.into_iter().rev());
+                }
+                add(&mut folders, folder);
+            }
+        }
+        folders
+    }
+
+    /// Give the files of a folder to some rules, as `evaluate` does
+    pub(crate) fn evaluate_rules(
+        &self,
+        dir: &str,
+        file: Option<&str>,
+        rules: Vec<Rule>,
+        run: &mut RuleRunner,
+    ) {
+        let listing = || match file {
+            Some(x) if self.filesystem.is_file(Path::new(x)) => vec![x.to_string()],
+            Some(_) => vec![],
+            None => Rule::files_matching_in(&*self.filesystem, dir, "*"),
+        };
+        // The current path of each file, and if the next rules still see it, in the `first-match` and `all-match` modes
+        let mut files: Vec<(String, bool)> = listing().into_iter().map(|x| (x, true)).collect();
+        // The files claimed by a `stop` rule, in the `independent` mode
+        let mut stopped: Vec<String> = vec![];
+        for mut rule in rules {
+            if rule.get_schedule().is_some() || !self.selection.selects(&rule) {
+                continue;
+            }
+            if self.evaluation == Evaluation::Independent {
+                let candidates: Vec<String> = listing()
+                    .into_iter()
+                    .filter(|x| !stopped.contains(x))
+                    .collect();
+                let mut claimed: Vec<String> = candidates
+                    .iter()
+                    .filter(|x| rule.concerns(x))
+                    .cloned()
+                    .collect();
+                for operation in run(&mut rule, dir, candidates) {
+                    let renamed = matches!(
+                        operation.action,
+                        Action::Move | Action::Prefix | Action::Suffix | Action::Rename
+                    );
+                    match claimed.iter_mut().find(|x| **x == operation.source) {
+                        Some(x) if renamed => *x = operation.destination,
+                        _ => (),
+                    }
+                }
+                if rule.get_flow() == &Some(Flow::Stop) {
+                    stopped.extend(claimed);
+                }
+                continue;
+            }
+            let claimed: Vec<usize> = (0..files.len())
+                .filter(|i| files[*i].1 && rule.concerns(&files[*i].0))
+                .collect();
+            let candidates: Vec<String> =
+                files.iter().filter(|x| x.1).map(|x| x.0.clone()).collect();
+            for operation in run(&mut rule, dir, candidates) {
+                let file = match files.iter_mut().find(|x| x.1 && x.0 == operation.source) {
+                    Some(x) => x,
+                    None => continue,
+                };
+                match operation.action {
+                    Action::Move | Action::Prefix | Action::Suffix | Action::Rename => {
+                        file.0 = operation.destination
+                    }
+                    Action::RemoveDuplicate => file.1 = false,
+                    Action::Archive if rule.get_archive_options().remove => file.1 = false,
+                    _ => (),
+                }
+            }
+            for i in claimed {
+                files[i].1 = match (self.evaluation, rule.get_flow()) {
+                    (_, Some(Flow::Stop)) => false,
+                    (_, Some(Flow::Continue)) => files[i].1,
+                    (Evaluation::FirstMatch, None) => false,
+                    _ => files[i].1,
+                };
+            }
         }
     }
 
@@ -31,40 +251,232 @@ impl Config {
         &self.rules
     }
 
+    /// Return how the rules share the files of a folder, from the `evaluate` line
+    pub fn get_evaluation(&self) -> &Evaluation {
+        &self.evaluation
+    }
+
+    /// Set how the rules share the files of a folder, instead of the mode of the `evaluate` line
+    pub fn set_evaluation(&mut self, evaluation: Evaluation) {
+        self.evaluation = evaluation;
+    }
+
+    /// Return the rules that run, from the `--only` and `--skip` arguments
+    pub fn get_selection(&self) -> &Selection {
+        &self.selection
+    }
+
+    /// Only run the rules of a `Selection`, instead of all of them
+    pub fn set_selection(&mut self, selection: Selection) {
+        self.selection = selection;
+    }
+
+    /// Return the patterns of the files ignored by all the rules, from the `ignore` lines
+    pub fn get_ignored(&self) -> &Vec<String> {
+        &self.ignored
+    }
+
     /// Create a new Config object from the path `test.crc` file
     pub fn new_for_test() -> Self {
         let conf_file_props: (String, String) =
             App::conf_file_lines(&String::from("./tests/test.crc"));
-        let conf_lines: Vec<&str> = conf_file_props.1.split('\n').collect();
-        let mut rules: Vec<Rule> = vec![];
+        Self::parse(Arc::new(StdFs), conf_file_props.0, &conf_file_props.1)
+    }
 
-        for i in 0..conf_lines.len() {
-            let rule = Rule::new_from_line(&conf_lines.clone()[i].to_string(), &i);
-            if rule.get_line_number() != &0 {
-                rules.push(rule);
-            }
+    /// Create a new Config object from the path of a `.crc` file
+    pub fn new(file: String) -> Self {
+        Self::new_in(Arc::new(StdFs), file)
+    }
+
+    /// Create a new Config object from the path of a `.crc` file of a `Filesystem`, that its rules change files in
+    pub fn new_in(filesystem: Arc<dyn Filesystem>, file: String) -> Self {
+        let conf_file_props: (String, String) = App::conf_file_lines_in(&*filesystem, &file);
+        Self::parse(filesystem, conf_file_props.0, &conf_file_props.1)
+    }
+
+    /// Create a new Config object from the path of a `.crc` file, without panicking if it is unreadable
+    pub fn from_file(file: &str) -> io::Result<Self> {
+        Self::load(Arc::new(StdFs), file)
+    }
+
+    /// Create a new Config object from the path of a `.crc` file of a `Filesystem`, without panicking if it is unreadable
+    pub fn load(filesystem: Arc<dyn Filesystem>, file: &str) -> io::Result<Self> {
+        let content = filesystem.read_to_string(Path::new(file))?;
+        Ok(Self::parse(filesystem, file.to_string(), &content))
+    }
+
+    /// Set the `Filesystem` the rules of the current `Configuration` change files in
+    pub fn set_filesystem(&mut self, filesystem: Arc<dyn Filesystem>) {
+        for rule in self.rules.iter_mut() {
+            rule.set_filesystem(filesystem.clone());
         }
+        self.filesystem = filesystem;
+    }
+
+    /// Create a new Config object from the content of a `.crc` file
+    fn parse(filesystem: Arc<dyn Filesystem>, filename: String, content: &str) -> Self {
+        let mut state = ParseState::default();
+        Self::parse_lines(&*filesystem, &filename, content, &mut state);
 
-        let filename = conf_file_props.0.to_string();
+        for rule in state.rules.iter_mut() {
+            rule.set_ignored(state.ignored.clone());
+            rule.set_config_files(state.parsed.clone());
+            rule.set_matching(rule.get_matching().union(&state.matching));
+            rule.set_filesystem(filesystem.clone());
+        }
 
-        Self { filename, rules }
+        Self {
+            filename,
+            rules: state.rules,
+            ignored: state.ignored,
+            evaluation: state.evaluation.unwrap_or_default(),
+            selection: Selection::default(),
+            filesystem,
+        }
     }
 
-    /// Create a new Config object from the path of a `.crc` file
-    pub fn new(file: String) -> Self {
-        let conf_file_props: (String, String) = App::conf_file_lines(&file.to_string());
-        let conf_lines: Vec<&str> = conf_file_props.1.split('\n').collect();
-        let mut rules: Vec<Rule> = vec![];
-
-        for i in 0..conf_lines.len() {
-            let rule = Rule::new_from_line(&conf_lines.clone()[i].to_string(), &i);
-            if rule.get_line_number() != &0 {
-                rules.push(rule);
+    /// Parse the lines of a `.crc` file, and the files it includes in their place
+    fn parse_lines(
+        filesystem: &dyn Filesystem,
+        filename: &str,
+        content: &str,
+        state: &mut ParseState,
+    ) {
+        state.files.push(filename.to_string());
+        state.parsed.push(filename.to_string());
+        let outer = state.block.is_some();
+        for line in SyntaxTree::parse(content).lines {
+            let text = line.to_string();
+            let text = text.trim_end_matches('\n');
+            let invalid = |message: String| Rule::invalid(text, line.number, message);
+            match line.kind() {
+                LineKind::Variable => match line.variable() {
+                    Ok((name, value)) => {
+                        let value = syntax::substitute(value, &state.variables);
+                        state.variables.push((name.to_string(), value));
+                    }
+                    Err(e) => state.rules.push(invalid(e)),
+                },
+                LineKind::Block => match (line.block(), &state.block) {
+                    (Err(e), _) => state.rules.push(invalid(e)),
+                    (Ok(_), Some(_)) => state
+                        .rules
+                        .push(invalid(String::from("Blocks can not be nested."))),
+                    (Ok((path, recursive)), None) => {
+                        let path = syntax::substitute(path, &state.variables);
+                        let scope = Scope {
+                            dir: evaluation::normalize(&Self::included_path(filename, &path))
+                                .to_string_lossy()
+                                .to_string(),
+                            recursive,
+                        };
+                        let unclosed = invalid(String::from("Block not closed by a `}` line."));
+                        state.block = Some((scope, unclosed));
+                    }
+                },
+                LineKind::BlockEnd => match state.block.take() {
+                    Some(_) => (),
+                    None => state
+                        .rules
+                        .push(invalid(String::from("No block to close."))),
+                },
+                LineKind::Match => match line.matching() {
+                    Ok(x) => state.matching = state.matching.union(&x),
+                    Err(e) => state.rules.push(invalid(e)),
+                },
+                LineKind::Evaluate => match line.evaluation() {
+                    Ok(x) => state.evaluation = Some(x),
+                    Err(e) => state.rules.push(invalid(e)),
+                },
+                LineKind::Include => {
+                    let path = match line.include() {
+                        Ok(x) => syntax::substitute(x, &state.variables),
+                        Err(e) => {
+                            state.rules.push(invalid(e));
+                            continue;
+                        }
+                    };
+                    let path = Self::included_path(filename, &path);
+                    if state.files.contains(&path) {
+                        state
+                            .rules
+                            .push(invalid(format!("`{}` includes itself.", path)));
+                        continue;
+                    }
+                    match filesystem.read_to_string(Path::new(&path)) {
+                        Ok(x) => Self::parse_lines(filesystem, &path, &x, state),
+                        Err(e) => state
+                            .rules
+                            .push(invalid(format!("Could not read `{}`: {}.", path, e))),
+                    }
+                }
+                _ => {
+                    let text = syntax::substitute(text, &state.variables);
+                    if let Some(patterns) = Self::ignore_patterns(&text) {
+                        state.ignored.extend(patterns);
+                        continue;
+                    }
+                    let mut rule = Rule::new_from_line(&text, &(line.number - 1));
+                    if rule.get_line_number() != &0 {
+                        rule.set_config_file(filename.to_string());
+                        rule.set_scope(state.block.as_ref().map(|x| x.0.clone()));
+                        state.rules.push(rule);
+                    }
+                }
+            }
+        }
+        if !outer {
+            if let Some((_, unclosed)) = state.block.take() {
+                state.rules.push(unclosed);
+            }
+        }
+        state.files.pop();
+    }
+
+    /// Return the path of a file included by a `.crc` file, relative to its folder
+    fn included_path(filename: &str, path: &str) -> String {
+        match Path::new(filename).parent() {
+            Some(x) if !x.as_os_str().is_empty() && Path::new(path).is_relative() => {
+                x.join(path).to_string_lossy().to_string()
             }
+            _ => path.to_string(),
         }
+    }
+
+    /// Return the patterns of an `ignore` line, or `None` if the line is not an `ignore` line
+    fn ignore_patterns(line: &str) -> Option<Vec<String>> {
+        let line_value = App::remove_comment(line);
+        let mut strings = line_value.split_whitespace();
+        match strings.next() {
+            Some("ignore") => Some(strings.map(|x| x.to_string()).collect()),
+            _ => None,
+        }
+    }
+}
+
+/// The rules, ignored patterns and variables read while parsing a `.crc` file and the files it includes
+#[derive(Default)]
+struct ParseState {
+    rules: Vec<Rule>,
+    ignored: Vec<String>,
+    /// The mode of the last `evaluate` line
+    evaluation: Option<Evaluation>,
+    /// The flags of the `match` lines
+    matching: Matching,
+    /// The scope of the open block, with the line opening it
+    block: Option<(Scope, Rule)>,
+    variables: Vec<(String, String)>,
+    /// The files being parsed, to refuse circular includes
+    files: Vec<String>,
+    /// Every file parsed, the included ones after the file including them
+    parsed: Vec<String>,
+}
 
-        let filename = conf_file_props.0.to_string();
+/// Read rules written in the `.crc` format, from memory. Invalid rules are kept with their error.
+impl FromStr for Config {
+    type Err = Infallible;
 
-        Self { filename, rules }
+    fn from_str(content: &str) -> Result<Self, Self::Err> {
+        Ok(Self::parse(Arc::new(StdFs), String::new(), content))
     }
 }
@@ -0,0 +1,202 @@
+use crate::components::plan::Operation;
+use crate::components::Rule;
+use std::collections::VecDeque;
+use std::io::{self, BufRead, BufReader, Write};
+
+/// The answer of the user to an operation in interactive mode
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Answer {
+    Yes,
+    No,
+    /// Apply this operation and all the next ones, without asking again
+    YesToAll,
+    /// Skip this operation and all the next ones, without asking again
+    NoToAll,
+    /// Apply this operation and the next ones of the same rule
+    YesToRule,
+    /// Skip this operation and stop executing the rules
+    Quit,
+}
+
+impl Answer {
+    /// Return the `Answer` of an input, a letter such as `y` or a name such as `yes-to-all`
+    pub fn from_input(input: &str) -> Option<Self> {
+        match input {
+            "y" | "yes" => Some(Self::Yes),
+            "n" | "no" => Some(Self::No),
+            "Y" | "yes-to-all" => Some(Self::YesToAll),
+            "N" | "no-to-all" => Some(Self::NoToAll),
+            "r" | "yes-to-rule" => Some(Self::YesToRule),
+            "q" | "quit" => Some(Self::Quit),
+            _ => None,
+        }
+    }
+}
+
+/// A Confirmer asks the user if an operation must be applied, in interactive mode
+pub trait Confirmer {
+    /// Return the answer of the user to an operation of a rule
+    fn confirm(&mut self, rule: &Rule, operation: &Operation) -> Answer;
+}
+
+/// Ask the user on a terminal, showing the source and destination of each operation
+pub struct TerminalConfirmer {
+    input: Box<dyn BufRead>,
+    output: Box<dyn Write>,
+}
+
+impl TerminalConfirmer {
+    /// Create a new `TerminalConfirmer` reading the standard input and writing on the standard output
+    pub fn new() -> Self {
+        Self::with_io(
+            Box::new(BufReader::new(io::stdin())),
+            Box::new(io::stdout()),
+        )
+    }
+
+    /// Create a new `TerminalConfirmer` reading and writing somewhere else, such as the standard error
+    pub fn with_io(input: Box<dyn BufRead>, output: Box<dyn Write>) -> Self {
+        Self { input, output }
+    }
+}
+
+impl Default for TerminalConfirmer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Confirmer for TerminalConfirmer {
+    /// Ask again until the input is a valid answer. The end of the input quits.
+    fn confirm(&mut self, rule: &Rule, operation: &Operation) -> Answer {
+        let _ = writeln!(
+            self.output,
+            "[line {}] {}\n    {:?}: {} -> {}",
+            rule.get_line_number(),
+            rule.get_line_value(),
+            operation.action,
+            operation.source,
+            operation.destination
+        );
+        loop {
+            let _ = write!(
+                self.output,
+                "Apply ? [y]es, [n]o, [Y]es to all, [N]o to all, yes to the [r]ule, [q]uit: "
+            );
+            let _ = self.output.flush();
+            let mut input = String::new();
+            match self.input.read_line(&mut input) {
+                Ok(0) | Err(_) => return Answer::Quit,
+                Ok(_) => {
+                    if let Some(x) = Answer::from_input(input.trim()) {
+                        return x;
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Give answers known in advance, then `No` once they are all given. The operations asked are kept.
+#[derive(Clone, Debug, Default)]
+pub struct ScriptedConfirmer {
+    answers: VecDeque<Answer>,
+    asked: Vec<Operation>,
+}
+
+impl ScriptedConfirmer {
+    /// Create a new `ScriptedConfirmer` giving some answers in order
+    pub fn new(answers: Vec<Answer>) -> Self {
+        Self {
+            answers: answers.into(),
+            asked: vec![],
+        }
+    }
+
+    /// Return the operations that were asked, in order
+    pub fn get_asked(&self) -> &Vec<Operation> {
+        &self.asked
+    }
+}
+
+impl Confirmer for ScriptedConfirmer {
+    fn confirm(&mut self, _rule: &Rule, operation: &Operation) -> Answer {
+        self.asked.push(operation.clone());
+        self.answers.pop_front().unwrap_or(Answer::No)
+    }
+}
+
+/// The answers given during an execution, so that `yes-to-all`, `no-to-all` and `quit` apply to the next rules
+pub struct Confirmation<'a> {
+    confirmer: Option<&'a mut dyn Confirmer>,
+    all: Option<bool>,
+    rule: bool,
+    quit: bool,
+}
+
+impl<'a> Confirmation<'a> {
+    /// Create a new `Confirmation` accepting all the operations without asking
+    pub fn none() -> Self {
+        Self {
+            confirmer: None,
+            all: None,
+            rule: false,
+            quit: false,
+        }
+    }
+
+    /// Create a new `Confirmation` asking a `Confirmer` about each operation
+    pub fn new(confirmer: &'a mut dyn Confirmer) -> Self {
+        Self {
+            confirmer: Some(confirmer),
+            ..Self::none()
+        }
+    }
+
+    /// Check if the user chose to stop executing the rules
+    pub fn is_quit(&self) -> bool {
+        self.quit
+    }
+
+    /// Forget a `yes-to-rule` answer, before asking about the operations of another rule
+    pub fn start_rule(&mut self) {
+        self.rule = false;
+    }
+
+    /// Check if an operation of a rule must be applied, asking the user if needed
+    pub fn accepts(&mut self, rule: &Rule, operation: &Operation) -> bool {
+        let confirmer = match &mut self.confirmer {
+            Some(x) => x,
+            None => return true,
+        };
+        if self.quit {
+            return false;
+        }
+        if let Some(x) = self.all {
+            return x;
+        }
+        if self.rule {
+            return true;
+        }
+        match confirmer.confirm(rule, operation) {
+            Answer::Yes => true,
+            Answer::No => false,
+            Answer::YesToAll => {
+                self.all = Some(true);
+                true
+            }
+            Answer::NoToAll => {
+                self.all = Some(false);
+                false
+            }
+            Answer::YesToRule => {
+                self.rule = true;
+                true
+            }
+            Answer::Quit => {
+                self.quit = true;
+                false
+            }
+        }
+    }
+}
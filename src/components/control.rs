@@ -0,0 +1,217 @@
+use serde::{Deserialize, Serialize};
+use std::env;
+use std::io;
+use std::path::PathBuf;
+#[cfg(unix)]
+use std::{
+    fs,
+    io::{BufRead, BufReader, Write},
+    os::unix::fs::FileTypeExt,
+    os::unix::net::{UnixListener, UnixStream},
+    path::Path,
+    time::Duration,
+};
+
+/// A command sent to a running daemon, as one line of JSON such as `{"command":"run-now","target":"/home/user/Downloads"}`
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "command", rename_all = "kebab-case")]
+pub enum Request {
+    /// Return the `Status` of the daemon
+    Status,
+    /// Stop handling the new files, that are queued until the daemon is resumed
+    Pause,
+    /// Handle the new files again
+    Resume,
+    /// Read the `.crc` file again
+    Reload,
+    /// Execute the rules on all the files of a watched folder
+    RunNow { target: String },
+    /// Return the `Report` of the last files handled
+    LastReport,
+}
+
+/// The answer of a daemon to a `Request`, as one line of JSON
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Response {
+    pub ok: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub status: Option<Status>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub report: Option<Report>,
+}
+
+impl Response {
+    /// Return a successful `Response` without content
+    pub fn ok() -> Self {
+        Self {
+            ok: true,
+            ..Self::default()
+        }
+    }
+
+    /// Return a failed `Response`
+    pub fn error(message: String) -> Self {
+        Self {
+            ok: false,
+            error: Some(message),
+            ..Self::default()
+        }
+    }
+}
+
+/// The state of a running daemon
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Status {
+    pub paused: bool,
+    /// Number of new files waiting to be handled
+    pub queue_depth: usize,
+    pub targets: Vec<TargetStats>,
+    /// The last errors, oldest first
+    pub recent_errors: Vec<String>,
+}
+
+/// The statistics of the rules applied to a watched folder
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct TargetStats {
+    pub target: String,
+    pub rules: Vec<RuleStats>,
+}
+
+/// How many times a rule was applied to the files of a folder
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct RuleStats {
+    pub line: usize,
+    /// The name of the rule, from its `@name(...)` annotation
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    pub rule: String,
+    pub runs: u64,
+    pub files: u64,
+    pub errors: u64,
+}
+
+/// What the daemon did the last time it applied rules
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Report {
+    /// When the rules were applied, in RFC 3339 format
+    pub time: String,
+    /// `new-file` or `run-now`
+    pub trigger: String,
+    pub entries: Vec<ReportEntry>,
+}
+
+/// A rule applied to some files of a folder
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct ReportEntry {
+    pub target: String,
+    pub line: usize,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    pub rule: String,
+    pub files: usize,
+    pub succeeded: bool,
+}
+
+/// Return the path of the control socket, in the runtime folder of the current user
+pub fn socket_path() -> PathBuf {
+    match env::var_os("XDG_RUNTIME_DIR") {
+        Some(x) if !x.is_empty() => PathBuf::from(x).join("crabby.sock"),
+        _ => env::temp_dir().join(format!(
+            "crabby-{}.sock",
+            env::var("USER").unwrap_or_default()
+        )),
+    }
+}
+
+/// Send a `Request` to the daemon listening on a socket, and return its `Response`
+#[cfg(unix)]
+pub fn send(socket: &Path, request: &Request) -> io::Result<Response> {
+    let mut stream = UnixStream::connect(socket)?;
+    stream.set_read_timeout(Some(Duration::from_secs(30)))?;
+    writeln!(stream, "{}", serde_json::to_string(request)?)?;
+    let mut line = String::new();
+    BufReader::new(stream).read_line(&mut line)?;
+    Ok(serde_json::from_str(&line)?)
+}
+
+/// Send a `Request` to the daemon listening on a socket, and return its `Response`
+#[cfg(not(unix))]
+pub fn send(_socket: &std::path::Path, _request: &Request) -> io::Result<Response> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "the control socket is only available on Unix",
+    ))
+}
+
+/// The socket a daemon answers requests on. The socket file is removed when it is dropped.
+#[cfg(unix)]
+pub struct ControlServer {
+    listener: UnixListener,
+    path: PathBuf,
+}
+
+#[cfg(unix)]
+impl ControlServer {
+    /// Listen on a socket, replacing the socket file left by a daemon that stopped.
+    /// Any other file at the path is left untouched.
+    pub fn bind(path: &Path) -> io::Result<Self> {
+        if let Ok(metadata) = fs::symlink_metadata(path) {
+            if !metadata.file_type().is_socket() {
+                return Err(io::Error::new(
+                    io::ErrorKind::AlreadyExists,
+                    format!("`{}` exists and is not a socket", path.display()),
+                ));
+            }
+            if UnixStream::connect(path).is_ok() {
+                return Err(io::Error::new(
+                    io::ErrorKind::AddrInUse,
+                    format!("another daemon listens on `{}`", path.display()),
+                ));
+            }
+            fs::remove_file(path)?;
+        }
+        let listener = UnixListener::bind(path)?;
+        listener.set_nonblocking(true)?;
+        Ok(Self {
+            listener,
+            path: path.to_path_buf(),
+        })
+    }
+
+    /// Answer the pending connections, without waiting for new ones, and return the errors of the connections that
+    /// could not be answered, for the caller to report them with its output
+    pub fn serve<F: FnMut(Request) -> Response>(&self, mut handler: F) -> Vec<io::Error> {
+        let mut errors = vec![];
+        while let Ok((stream, _)) = self.listener.accept() {
+            if let Err(e) = Self::answer(stream, &mut handler) {
+                errors.push(e);
+            }
+        }
+        errors
+    }
+
+    /// Read one request from a connection and write the response
+    fn answer<F: FnMut(Request) -> Response>(
+        stream: UnixStream,
+        handler: &mut F,
+    ) -> io::Result<()> {
+        stream.set_nonblocking(false)?;
+        stream.set_read_timeout(Some(Duration::from_secs(5)))?;
+        let mut line = String::new();
+        BufReader::new(&stream).read_line(&mut line)?;
+        let response = match serde_json::from_str::<Request>(&line) {
+            Ok(request) => handler(request),
+            Err(e) => Response::error(format!("invalid request: {}", e)),
+        };
+        writeln!(&stream, "{}", serde_json::to_string(&response)?)
+    }
+}
+
+#[cfg(unix)]
+impl Drop for ControlServer {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
@@ -0,0 +1,286 @@
+use crate::components::filesystem::{Filesystem, StdFs};
+use std::collections::hash_map::DefaultHasher;
+use std::fs::{self, File, FileTimes};
+use std::hash::Hasher;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+
+/// Policy applied when the destination of a copy already exists
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ConflictPolicy {
+    /// Replace the existing file
+    Overwrite,
+    /// Keep the existing file and skip the copy
+    Skip,
+    /// Copy next to the existing file with a numbered name (`file (1).pdf`)
+    Rename,
+    /// Refuse to copy and report an error
+    Fail,
+}
+
+impl ConflictPolicy {
+    /// Return the `ConflictPolicy` matching a `.crc` token
+    pub fn from_token(token: &str) -> Option<Self> {
+        match token {
+            "overwrite" => Some(Self::Overwrite),
+            "skip" => Some(Self::Skip),
+            "rename" => Some(Self::Rename),
+            "fail" => Some(Self::Fail),
+            _ => None,
+        }
+    }
+}
+
+/// How the content of a file ends up at its destination
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum LinkMode {
+    /// Write a full copy of the content
+    Copy,
+    /// Create a hardlink when both paths are on the same filesystem
+    Hardlink,
+    /// Clone the file extents (copy-on-write) when both paths are on the same filesystem
+    Reflink,
+}
+
+/// Options used by `copy_file`, shared by the `mv` and `cp` rules
+#[derive(Clone, Debug, PartialEq)]
+pub struct CopyOptions {
+    pub conflict: ConflictPolicy,
+    pub preserve: bool,
+    pub verify: bool,
+    pub link: LinkMode,
+}
+
+impl Default for CopyOptions {
+    fn default() -> Self {
+        Self {
+            conflict: ConflictPolicy::Overwrite,
+            preserve: false,
+            verify: false,
+            link: LinkMode::Copy,
+        }
+    }
+}
+
+/// What `copy_file` did with a file
+#[derive(Clone, Debug, PartialEq)]
+pub enum CopyOutcome {
+    /// The content was copied to the path
+    Copied(PathBuf),
+    /// The path was linked (hardlink or reflink) to the source
+    Linked(PathBuf),
+    /// The destination already existed and the policy is `skip`
+    Skipped,
+}
+
+/// Make a copy of a file from the source full path towards the destination full path
+pub fn copy_file(src: &Path, dest: &Path, options: &CopyOptions) -> io::Result<CopyOutcome> {
+    copy_file_in(&StdFs, src, dest, options)
+}
+
+/// Make a copy of a file in a `Filesystem`. Reflinks and the `preserve` option only work on the disk.
+pub fn copy_file_in(
+    fs: &dyn Filesystem,
+    src: &Path,
+    dest: &Path,
+    options: &CopyOptions,
+) -> io::Result<CopyOutcome> {
+    // A file copied or moved to its own path is left as it is
+    if is_same_file(fs, src, dest) {
+        return Ok(CopyOutcome::Skipped);
+    }
+    let dest = match resolve_conflict(fs, dest, options.conflict)? {
+        Some(x) => x,
+        None => return Ok(CopyOutcome::Skipped),
+    };
+
+    if options.link != LinkMode::Copy && (!fs.is_native() || same_filesystem(src, &dest)) {
+        let linked = match options.link {
+            LinkMode::Hardlink => hardlink(fs, src, &dest),
+            _ if fs.is_native() => reflink(src, &dest),
+            _ => Err(io::Error::other("reflinks are only supported on the disk")),
+        };
+        if linked.is_ok() {
+            return Ok(CopyOutcome::Linked(dest));
+        }
+    }
+
+    // The copy is written to a temporary file next to the destination, which only replaces an existing
+    // destination once it is complete
+    let temporary = dest.with_file_name(format!(
+        ".{}.crabby",
+        dest.file_name().unwrap_or_default().to_string_lossy()
+    ));
+    let copied =
+        write_copy(fs, src, &temporary, options).and_then(|_| fs.rename(&temporary, &dest));
+    if copied.is_err() {
+        let _ = fs.remove_file(&temporary);
+    }
+    copied.map(|_| CopyOutcome::Copied(dest))
+}
+
+/// Write the copy of `copy_file_in` to a path, and check it if the options ask for it
+fn write_copy(
+    fs: &dyn Filesystem,
+    src: &Path,
+    dest: &Path,
+    options: &CopyOptions,
+) -> io::Result<()> {
+    fs.copy(src, dest)?;
+    if options.preserve && fs.is_native() {
+        preserve_metadata(src, dest)?;
+    }
+    if options.verify && checksum_in(fs, src)? != checksum_in(fs, dest)? {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("checksum mismatch after copying {}", src.display()),
+        ));
+    }
+    Ok(())
+}
+
+/// Check if two paths are the same file of a `Filesystem`
+fn is_same_file(fs: &dyn Filesystem, src: &Path, dest: &Path) -> bool {
+    matches!((fs.canonicalize(src), fs.canonicalize(dest)), (Ok(x), Ok(y)) if x == y)
+}
+
+/// Return a checksum of the content of a file
+pub fn checksum(path: &Path) -> io::Result<u64> {
+    checksum_of(File::open(path)?)
+}
+
+/// Return a checksum of the content of a file of a `Filesystem`
+pub fn checksum_in(fs: &dyn Filesystem, path: &Path) -> io::Result<u64> {
+    match fs.is_native() {
+        true => checksum(path),
+        false => checksum_of(&fs.read(path)?[..]),
+    }
+}
+
+/// Return a checksum of everything that can be read from a reader
+pub fn checksum_of<R: Read>(mut reader: R) -> io::Result<u64> {
+    let mut hasher = DefaultHasher::new();
+    let mut buffer = [0u8; 64 * 1024];
+    loop {
+        let read = reader.read(&mut buffer)?;
+        if read == 0 {
+            break;
+        }
+        hasher.write(&buffer[..read]);
+    }
+    Ok(hasher.finish())
+}
+
+/// Return the path the copy must be written to, or `None` if it must be skipped
+fn resolve_conflict(
+    fs: &dyn Filesystem,
+    dest: &Path,
+    policy: ConflictPolicy,
+) -> io::Result<Option<PathBuf>> {
+    if !fs.exists(dest) {
+        return Ok(Some(dest.to_path_buf()));
+    }
+    match policy {
+        ConflictPolicy::Overwrite => Ok(Some(dest.to_path_buf())),
+        ConflictPolicy::Skip => Ok(None),
+        ConflictPolicy::Rename => Ok(Some(numbered_path_in(fs, dest))),
+        ConflictPolicy::Fail => Err(io::Error::new(
+            io::ErrorKind::AlreadyExists,
+            format!("{} already exists", dest.display()),
+        )),
+    }
+}
+
+/// Return the first free path of the form `name (n).ext` next to `path`
+pub fn numbered_path(path: &Path) -> PathBuf {
+    numbered_path_in(&StdFs, path)
+}
+
+/// Return the first free path of the form `name (n).ext` next to `path` in a `Filesystem`
+pub fn numbered_path_in(fs: &dyn Filesystem, path: &Path) -> PathBuf {
+    let mut n = 1;
+    loop {
+        let candidate = path.with_file_name(numbered_name(path, n));
+        if !fs.exists(&candidate) {
+            return candidate;
+        }
+        n += 1;
+    }
+}
+
+/// Return the name of a path numbered like `name (n).ext`
+pub(crate) fn numbered_name(path: &Path, n: usize) -> String {
+    let stem = path
+        .file_stem()
+        .map(|x| x.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let extension = path
+        .extension()
+        .map(|x| format!(".{}", x.to_string_lossy()))
+        .unwrap_or_default();
+    format!("{} ({}){}", stem, n, extension)
+}
+
+/// Copy permissions and timestamps of the source file to the destination file
+fn preserve_metadata(src: &Path, dest: &Path) -> io::Result<()> {
+    let metadata = fs::metadata(src)?;
+    fs::set_permissions(dest, metadata.permissions())?;
+    let times = FileTimes::new()
+        .set_accessed(metadata.accessed()?)
+        .set_modified(metadata.modified()?);
+    File::options().write(true).open(dest)?.set_times(times)
+}
+
+/// Replace the destination by a hardlink to the source
+fn hardlink(fs: &dyn Filesystem, src: &Path, dest: &Path) -> io::Result<()> {
+    if fs.exists(dest) {
+        fs.remove_file(dest)?;
+    }
+    fs.hard_link(src, dest)
+}
+
+/// Replace the destination by a copy-on-write clone of the source
+#[cfg(target_os = "linux")]
+fn reflink(src: &Path, dest: &Path) -> io::Result<()> {
+    use std::os::unix::io::AsRawFd;
+
+    const FICLONE: libc::c_ulong = 0x4004_9409;
+
+    let source = File::open(src)?;
+    let destination = File::create(dest)?;
+    let result = unsafe { libc::ioctl(destination.as_raw_fd(), FICLONE as _, source.as_raw_fd()) };
+    if result == -1 {
+        let error = io::Error::last_os_error();
+        let _ = fs::remove_file(dest);
+        return Err(error);
+    }
+    Ok(())
+}
+
+#[cfg(not(target_os = "linux"))]
+fn reflink(_src: &Path, _dest: &Path) -> io::Result<()> {
+    Err(io::Error::new(
+        io::ErrorKind::Other,
+        "reflinks are not supported on this platform",
+    ))
+}
+
+/// Check if the source file and the destination folder are on the same filesystem
+#[cfg(unix)]
+fn same_filesystem(src: &Path, dest: &Path) -> bool {
+    use std::os::unix::fs::MetadataExt;
+
+    let dest_dir = match dest.parent() {
+        Some(x) if !x.as_os_str().is_empty() => x,
+        _ => Path::new("."),
+    };
+    match (fs::metadata(src), fs::metadata(dest_dir)) {
+        (Ok(a), Ok(b)) => a.dev() == b.dev(),
+        _ => false,
+    }
+}
+
+#[cfg(not(unix))]
+fn same_filesystem(_src: &Path, _dest: &Path) -> bool {
+    false
+}
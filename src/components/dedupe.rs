@@ -0,0 +1,192 @@
+use crate::components::filesystem::{Filesystem, StdFs};
+use crate::components::{copy, trash};
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{self, BufReader, Read};
+use std::path::{Path, PathBuf};
+
+/// Which file of a group of duplicates is kept
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum KeepPolicy {
+    /// The file with the oldest modification date
+    Oldest,
+    /// The file with the newest modification date
+    Newest,
+    /// The file with the shortest name, e.g. `file.pdf` rather than `file (1).pdf`
+    Shortest,
+}
+
+impl KeepPolicy {
+    /// Return the `KeepPolicy` matching a `.crc` token
+    pub fn from_token(token: &str) -> Option<Self> {
+        match token {
+            "oldest" => Some(Self::Oldest),
+            "newest" => Some(Self::Newest),
+            "shortest" => Some(Self::Shortest),
+            _ => None,
+        }
+    }
+}
+
+/// Options that can follow a `dedupe` rule
+#[derive(Clone, Debug, PartialEq)]
+pub struct DedupeOptions {
+    pub keep: KeepPolicy,
+    /// Keep a file of this folder when a group has one
+    pub prefer: Option<PathBuf>,
+    /// Replace the duplicates by hardlinks to the kept file instead of moving them to the trash
+    pub hardlink: bool,
+}
+
+impl Default for DedupeOptions {
+    fn default() -> Self {
+        Self {
+            keep: KeepPolicy::Oldest,
+            prefer: None,
+            hardlink: false,
+        }
+    }
+}
+
+/// Return the groups of files having the same content.
+/// Files are grouped by size, then by checksum, then compared byte by byte.
+pub fn find_duplicates(files: &[PathBuf]) -> io::Result<Vec<Vec<PathBuf>>> {
+    find_duplicates_in(&StdFs, files)
+}
+
+/// Return the groups of files of a `Filesystem` having the same content
+pub fn find_duplicates_in(fs: &dyn Filesystem, files: &[PathBuf]) -> io::Result<Vec<Vec<PathBuf>>> {
+    let mut by_size: HashMap<u64, Vec<PathBuf>> = HashMap::new();
+    for file in files {
+        let size = fs.metadata(file)?.len;
+        if size > 0 {
+            by_size.entry(size).or_default().push(file.clone());
+        }
+    }
+
+    let mut groups: Vec<Vec<PathBuf>> = vec![];
+    for (_, same_size) in by_size.into_iter().filter(|x| x.1.len() > 1) {
+        let mut by_checksum: HashMap<u64, Vec<PathBuf>> = HashMap::new();
+        for file in same_size {
+            by_checksum
+                .entry(copy::checksum_in(fs, &file)?)
+                .or_default()
+                .push(file);
+        }
+        for (_, same_checksum) in by_checksum.into_iter().filter(|x| x.1.len() > 1) {
+            groups.extend(split_identical(fs, same_checksum)?);
+        }
+    }
+    for group in groups.iter_mut() {
+        group.sort();
+    }
+    groups.sort();
+    Ok(groups)
+}
+
+/// Split files having the same checksum into groups of files having exactly the same content
+fn split_identical(fs: &dyn Filesystem, mut files: Vec<PathBuf>) -> io::Result<Vec<Vec<PathBuf>>> {
+    let mut groups: Vec<Vec<PathBuf>> = vec![];
+    while let Some(first) = files.pop() {
+        let mut group = vec![first];
+        let mut different = vec![];
+        for file in files {
+            if same_content(fs, &group[0], &file)? {
+                group.push(file);
+            } else {
+                different.push(file);
+            }
+        }
+        if group.len() > 1 {
+            groups.push(group);
+        }
+        files = different;
+    }
+    Ok(groups)
+}
+
+/// Compare the content of two files byte by byte
+fn same_content(fs: &dyn Filesystem, a: &Path, b: &Path) -> io::Result<bool> {
+    if !fs.is_native() {
+        return Ok(fs.read(a)? == fs.read(b)?);
+    }
+    let mut a = BufReader::new(File::open(a)?).bytes();
+    let mut b = BufReader::new(File::open(b)?).bytes();
+    loop {
+        match (a.next(), b.next()) {
+            (None, None) => return Ok(true),
+            (Some(x), Some(y)) => {
+                if x? != y? {
+                    return Ok(false);
+                }
+            }
+            _ => return Ok(false),
+        }
+    }
+}
+
+/// Return the file of a group of duplicates that must be kept
+pub fn choose_kept(group: &[PathBuf], options: &DedupeOptions) -> PathBuf {
+    choose_kept_in(&StdFs, group, options)
+}
+
+/// Return the file of a group of duplicates of a `Filesystem` that must be kept
+pub fn choose_kept_in(fs: &dyn Filesystem, group: &[PathBuf], options: &DedupeOptions) -> PathBuf {
+    let preferred: Vec<&PathBuf> = match &options.prefer {
+        Some(dir) => {
+            let dir = fs.canonicalize(dir).unwrap_or_else(|_| dir.clone());
+            group
+                .iter()
+                .filter(|x| match fs.canonicalize(x) {
+                    Ok(path) => path.starts_with(&dir),
+                    Err(_) => x.starts_with(&dir),
+                })
+                .collect()
+        }
+        None => vec![],
+    };
+    let candidates: Vec<&PathBuf> = match preferred.is_empty() {
+        true => group.iter().collect(),
+        false => preferred,
+    };
+
+    let modified = |x: &PathBuf| fs.metadata(x).map(|y| y.modified).ok();
+    let name_length = |x: &PathBuf| match x.file_name() {
+        Some(y) => y.to_string_lossy().chars().count(),
+        None => 0,
+    };
+    let kept = match options.keep {
+        KeepPolicy::Oldest => candidates.iter().min_by_key(|x| modified(x)),
+        KeepPolicy::Newest => candidates.iter().max_by_key(|x| modified(x)),
+        KeepPolicy::Shortest => candidates
+            .iter()
+            .min_by_key(|x| (name_length(x), x.to_owned())),
+    };
+    kept.map(|x| x.to_path_buf()).unwrap_or_default()
+}
+
+/// Remove a duplicate of the kept file, by moving it to the trash or replacing it by a hardlink
+pub fn remove_duplicate(duplicate: &Path, kept: &Path, options: &DedupeOptions) -> io::Result<()> {
+    remove_duplicate_in(&StdFs, duplicate, kept, options)
+}
+
+/// Remove a duplicate of the kept file of a `Filesystem`. The trash is only available on the disk.
+pub fn remove_duplicate_in(
+    fs: &dyn Filesystem,
+    duplicate: &Path,
+    kept: &Path,
+    options: &DedupeOptions,
+) -> io::Result<()> {
+    if options.hardlink {
+        let temporary = duplicate.with_file_name(format!(
+            ".{}.crabby",
+            duplicate.file_name().unwrap_or_default().to_string_lossy()
+        ));
+        fs.hard_link(kept, &temporary)?;
+        fs.rename(&temporary, duplicate)
+    } else if !fs.is_native() {
+        Err(io::Error::other("the trash is only available on the disk"))
+    } else {
+        trash::trash(duplicate).map(|_| ())
+    }
+}
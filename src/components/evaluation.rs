@@ -0,0 +1,96 @@
+use crate::components::Rule;
+use serde::Serialize;
+use std::path::{Component, Path, PathBuf};
+
+/// How the rules of a `.crc` file share the files of a folder, from its `evaluate` line
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Evaluation {
+    /// Every rule runs on the files of the folder as the previous rules left it
+    #[default]
+    Independent,
+    /// Each file is only handled by the first rule matching it, like mail filters
+    FirstMatch,
+    /// Each file goes through all the rules in order, the next rules seeing its new name
+    AllMatch,
+}
+
+impl Evaluation {
+    /// Names of the modes, as written after `evaluate`
+    pub const NAMES: [&'static str; 3] = ["independent", "first-match", "all-match"];
+
+    /// Return the `Evaluation` of a name written after `evaluate`
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "independent" => Some(Self::Independent),
+            "first-match" => Some(Self::FirstMatch),
+            "all-match" => Some(Self::AllMatch),
+            _ => None,
+        }
+    }
+}
+
+/// What happens to the files a rule claims, from its `stop` or `continue` option
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Flow {
+    /// The next rules do not see the files, whatever the evaluation mode
+    Stop,
+    /// The next rules still see the files, even in `first-match` mode
+    Continue,
+}
+
+/// The folder a block of rules is scoped to, from its `in <path> [recursive] {` line
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct Scope {
+    /// The folder, relative to the `.crc` file of the block
+    pub dir: String,
+    /// The rules also run in the subfolders of the folder
+    pub recursive: bool,
+}
+
+impl Scope {
+    /// Check if the rules of the scope run in a folder
+    pub fn covers(&self, folder: &str) -> bool {
+        let (dir, folder) = (normalize(&self.dir), normalize(folder));
+        match self.recursive {
+            true => folder.starts_with(dir),
+            false => folder == dir,
+        }
+    }
+}
+
+/// The rules to run, from the names and tags given to `--only` and `--skip`
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub struct Selection {
+    /// Only the rules having one of these names or tags run, all of them when it is empty
+    pub only: Vec<String>,
+    /// The rules having one of these names or tags do not run
+    pub skip: Vec<String>,
+}
+
+impl Selection {
+    /// Check if a rule runs
+    pub fn selects(&self, rule: &Rule) -> bool {
+        (self.only.is_empty() || self.only.iter().any(|x| rule.is_labeled(x)))
+            && !self.skip.iter().any(|x| rule.is_labeled(x))
+    }
+
+    /// Return the names and tags of the selection that no rule has
+    pub fn unknown_labels(&self, rules: &[&Rule]) -> Vec<String> {
+        self.only
+            .iter()
+            .chain(self.skip.iter())
+            .filter(|x| !rules.iter().any(|y| y.is_labeled(x)))
+            .cloned()
+            .collect()
+    }
+}
+
+/// Return a path without its `.` components, to compare the paths of folders written differently
+pub(crate) fn normalize(path: &str) -> PathBuf {
+    Path::new(path)
+        .components()
+        .filter(|x| *x != Component::CurDir)
+        .collect()
+}
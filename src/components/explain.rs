@@ -0,0 +1,203 @@
+use crate::components::evaluation::{self, Scope};
+use crate::components::plan::{Action, Operation};
+use crate::components::Config;
+use serde::Serialize;
+use std::path::Path;
+
+/// Which part of a `Rule` decided if it touches a file
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(tag = "decision", rename_all = "kebab-case")]
+pub enum Decision {
+    /// The rule touches the file
+    Matched,
+    /// The rule can not run
+    Invalid { error: String },
+    /// The name of the file does not follow the pattern of the rule
+    Pattern { pattern: String },
+    /// The file follows a pattern of an `except` clause of the rule
+    Exception { pattern: String },
+    /// The file follows a pattern of an `ignore` line of the `.crc` file
+    Ignored { pattern: String },
+    /// The file was modified after the age of the `older-than` clause, in seconds
+    TooRecent { older_than: u64 },
+    /// The rule does not move nor delete protected files
+    Protected { reason: String },
+    /// The rule would touch the file, but it only runs with `crabby schedule`
+    Scheduled,
+    /// The rule is in an `in` block whose folder does not contain the file, or outside of the blocks and the file is
+    /// not in the target
+    OutOfScope { dir: String, recursive: bool },
+    /// The rule is not selected by the `--only` and `--skip` arguments
+    Deselected,
+    /// An earlier rule claimed the file, or moved, renamed or removed it before this rule runs
+    Taken { line: usize },
+}
+
+impl Decision {
+    /// Return a sentence telling why the rule touches the file or not
+    pub fn describe(&self) -> String {
+        match self {
+            Self::Matched => String::from("matched"),
+            Self::Invalid { error } => format!("not run, the rule is invalid: {}", error),
+            Self::Pattern { pattern } => {
+                format!("not matched, the name does not follow `{}`", pattern)
+            }
+            Self::Exception { pattern } => format!("not matched, excluded by `except {}`", pattern),
+            Self::Ignored { pattern } => format!("not matched, excluded by `ignore {}`", pattern),
+            Self::TooRecent { older_than } => format!(
+                "not matched, modified less than {} ago (`older-than`)",
+                Self::age(*older_than)
+            ),
+            Self::Protected { reason } => {
+                format!("not matched, the file is protected ({})", reason)
+            }
+            Self::Scheduled => String::from("matched, but only run by `crabby schedule`"),
+            Self::Deselected => String::from("not run, deselected by `--only` or `--skip`"),
+            Self::OutOfScope { dir, recursive } => format!(
+                "not run, the rule only runs in `{}`{}",
+                dir,
+                match recursive {
+                    true => " and its subfolders",
+                    false => "",
+                }
+            ),
+            Self::Taken { line } => {
+                format!(
+                    "not reached, the file was claimed by the rule line {}",
+                    line
+                )
+            }
+        }
+    }
+
+    /// Return an age in seconds with its largest whole unit, such as `7d`
+    fn age(seconds: u64) -> String {
+        let units = [(604_800, "w"), (86_400, "d"), (3_600, "h"), (60, "m")];
+        match units
+            .iter()
+            .find(|x| seconds >= x.0 && seconds.is_multiple_of(x.0))
+        {
+            Some((x, y)) => format!("{}{}", seconds / x, y),
+            None => format!("{}s", seconds),
+        }
+    }
+}
+
+/// What a rule of a `.crc` file would do to a file
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct RuleExplanation {
+    /// The `.crc` file of the rule
+    pub config: String,
+    pub line: usize,
+    pub rule: String,
+    /// The folder of the `in` block of the rule, if it is in one
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub scope: Option<Scope>,
+    #[serde(flatten)]
+    pub decision: Decision,
+    /// The operations the rule would run on the file, when it matches
+    pub operations: Vec<Operation>,
+}
+
+/// Evaluate the rules of some configurations against a file when cleaning a target, the folder of the file if it is
+/// empty, in the order and with the evaluation mode they run with on the folder of the file. The rules outside of
+/// the blocks only run in the target, and the rules of the `in` blocks in the folders of their blocks. The next
+/// rules see the new path of a file a rule moves or renames, or nothing once a rule claimed it in the
+/// `first-match` mode, or removed it.
+pub fn explain(configurations: &[Config], target: &str, file: &str) -> Vec<RuleExplanation> {
+    let path = Path::new(file);
+    let dir = match path.parent() {
+        Some(x) if !x.as_os_str().is_empty() => x.to_string_lossy().to_string(),
+        _ => String::from("."),
+    };
+    let target = match target.is_empty() {
+        true => dir.clone(),
+        false => target.to_string(),
+    };
+    let file = Path::new(&dir)
+        .join(path.file_name().unwrap_or_default())
+        .to_string_lossy()
+        .to_string();
+    // The path of the file for the next rule, and the line of the last rule that claimed it
+    let mut current: Option<String> = Some(file.clone());
+    let mut claimer: usize = 0;
+    let mut explanations: Vec<RuleExplanation> = vec![];
+    for config in configurations {
+        let mut evaluated: Vec<RuleExplanation> = vec![];
+        let rules = config.rules_for(&target, &dir);
+        config.evaluate_rules(&dir, None, rules, &mut |rule, _, candidates| {
+            let decision = match &current {
+                Some(x) if candidates.contains(x) => rule.decide(x),
+                _ => Decision::Taken { line: claimer },
+            };
+            let files: Vec<String> = candidates
+                .into_iter()
+                .filter(|x| rule.concerns(x))
+                .collect();
+            let planned = match files.is_empty() {
+                true => vec![],
+                false => rule.plan(&files).unwrap_or_default(),
+            };
+            let mut operations: Vec<Operation> = vec![];
+            if let (Decision::Matched, Some(x)) = (&decision, current.clone()) {
+                claimer = *rule.get_line_number();
+                for operation in planned
+                    .iter()
+                    .filter(|y| Path::new(&y.source) == Path::new(&x))
+                {
+                    current = match operation.action {
+                        Action::Move | Action::Prefix | Action::Suffix | Action::Rename => {
+                            Some(operation.destination.clone())
+                        }
+                        Action::RemoveDuplicate => None,
+                        Action::Archive if rule.get_archive_options().remove => None,
+                        _ => current.clone(),
+                    };
+                    operations.push(operation.clone());
+                }
+            }
+            evaluated.push(RuleExplanation {
+                config: rule.get_config_file().clone(),
+                line: *rule.get_line_number(),
+                rule: rule.get_line_value().clone(),
+                scope: rule.get_scope().clone(),
+                decision,
+                operations,
+            });
+            planned
+        });
+        // The scheduled and unselected rules, the rules of the blocks of other folders and the rules outside of the
+        // blocks when the file is not in the target are not evaluated, but they are explained in their place
+        let mut evaluated = evaluated.into_iter();
+        for rule in config.get_rules() {
+            let skipped = |decision: Decision| RuleExplanation {
+                config: rule.get_config_file().clone(),
+                line: *rule.get_line_number(),
+                rule: rule.get_line_value().clone(),
+                scope: rule.get_scope().clone(),
+                decision,
+                operations: vec![],
+            };
+            let explanation = match (rule.get_scope(), rule.get_schedule()) {
+                (Some(x), _) if !x.covers(&dir) => skipped(Decision::OutOfScope {
+                    dir: x.dir.clone(),
+                    recursive: x.recursive,
+                }),
+                (None, _) if evaluation::normalize(&target) != evaluation::normalize(&dir) => {
+                    skipped(Decision::OutOfScope {
+                        dir: target.clone(),
+                        recursive: false,
+                    })
+                }
+                _ if !config.get_selection().selects(rule) => skipped(Decision::Deselected),
+                (_, Some(_)) => skipped(rule.decide(&file)),
+                _ => match evaluated.next() {
+                    Some(x) => x,
+                    None => continue,
+                },
+            };
+            explanations.push(explanation);
+        }
+    }
+    explanations
+}
@@ -0,0 +1,341 @@
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Component, Path, PathBuf};
+use std::sync::Mutex;
+use std::time::SystemTime;
+
+/// What a `Filesystem` knows about a path
+#[derive(Clone, Debug, PartialEq)]
+pub struct FileInfo {
+    pub is_dir: bool,
+    pub len: u64,
+    pub modified: SystemTime,
+}
+
+/// A Filesystem is where the rules read and change files: the disk, or the memory in tests
+pub trait Filesystem: fmt::Debug + Send + Sync {
+    /// Return the content of a file
+    fn read(&self, path: &Path) -> io::Result<Vec<u8>>;
+
+    /// Return the content of a text file
+    fn read_to_string(&self, path: &Path) -> io::Result<String> {
+        String::from_utf8(self.read(path)?)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+
+    /// Create or replace a file
+    fn write(&self, path: &Path, content: &[u8]) -> io::Result<()>;
+
+    /// Return the paths of the files and folders of a folder
+    fn read_dir(&self, path: &Path) -> io::Result<Vec<PathBuf>>;
+
+    fn metadata(&self, path: &Path) -> io::Result<FileInfo>;
+
+    fn exists(&self, path: &Path) -> bool {
+        self.metadata(path).is_ok()
+    }
+
+    fn is_file(&self, path: &Path) -> bool {
+        matches!(self.metadata(path), Ok(x) if !x.is_dir)
+    }
+
+    fn create_dir_all(&self, path: &Path) -> io::Result<()>;
+
+    fn rename(&self, from: &Path, to: &Path) -> io::Result<()>;
+
+    /// Copy the content of a file, and return the number of bytes copied
+    fn copy(&self, from: &Path, to: &Path) -> io::Result<u64>;
+
+    fn remove_file(&self, path: &Path) -> io::Result<()>;
+
+    fn hard_link(&self, from: &Path, to: &Path) -> io::Result<()>;
+
+    /// Return the absolute path of an existing file or folder, without `.` and `..` components nor links
+    fn canonicalize(&self, path: &Path) -> io::Result<PathBuf>;
+
+    /// Check if the paths are on the disk, which archives, the trash, reflinks and the `preserve` option need
+    fn is_native(&self) -> bool {
+        false
+    }
+}
+
+/// The disk, through `std::fs`
+#[derive(Clone, Copy, Debug, Default)]
+pub struct StdFs;
+
+impl Filesystem for StdFs {
+    fn read(&self, path: &Path) -> io::Result<Vec<u8>> {
+        fs::read(path)
+    }
+
+    fn read_to_string(&self, path: &Path) -> io::Result<String> {
+        fs::read_to_string(path)
+    }
+
+    fn write(&self, path: &Path, content: &[u8]) -> io::Result<()> {
+        fs::write(path, content)
+    }
+
+    fn read_dir(&self, path: &Path) -> io::Result<Vec<PathBuf>> {
+        fs::read_dir(path)?.map(|x| x.map(|y| y.path())).collect()
+    }
+
+    fn metadata(&self, path: &Path) -> io::Result<FileInfo> {
+        let metadata = fs::metadata(path)?;
+        Ok(FileInfo {
+            is_dir: metadata.is_dir(),
+            len: metadata.len(),
+            modified: metadata.modified()?,
+        })
+    }
+
+    fn create_dir_all(&self, path: &Path) -> io::Result<()> {
+        fs::create_dir_all(path)
+    }
+
+    fn rename(&self, from: &Path, to: &Path) -> io::Result<()> {
+        fs::rename(from, to)
+    }
+
+    fn copy(&self, from: &Path, to: &Path) -> io::Result<u64> {
+        fs::copy(from, to)
+    }
+
+    fn remove_file(&self, path: &Path) -> io::Result<()> {
+        fs::remove_file(path)
+    }
+
+    fn hard_link(&self, from: &Path, to: &Path) -> io::Result<()> {
+        fs::hard_link(from, to)
+    }
+
+    fn canonicalize(&self, path: &Path) -> io::Result<PathBuf> {
+        fs::canonicalize(path)
+    }
+
+    fn is_native(&self) -> bool {
+        true
+    }
+}
+
+/// The files and folders of a `MemFs`
+#[derive(Debug, Default)]
+struct MemState {
+    files: BTreeMap<PathBuf, (Vec<u8>, SystemTime)>,
+    dirs: BTreeSet<PathBuf>,
+    failing: HashSet<PathBuf>,
+}
+
+/// A filesystem in memory, to test the rules without touching the disk.
+/// The paths are compared without their `.` components, and an operation on a path given to `fail_on` fails.
+#[derive(Debug, Default)]
+pub struct MemFs {
+    state: Mutex<MemState>,
+}
+
+impl MemFs {
+    /// Create an empty `MemFs`
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a file and its folders
+    pub fn add_file(&self, path: &str, content: &str) {
+        let path = Self::normalize(Path::new(path));
+        let mut state = self.state.lock().unwrap();
+        Self::add_parents(&mut state, &path);
+        state
+            .files
+            .insert(path, (content.as_bytes().to_vec(), SystemTime::now()));
+    }
+
+    /// Add a folder and its parents
+    pub fn add_dir(&self, path: &str) {
+        let path = Self::normalize(Path::new(path));
+        let mut state = self.state.lock().unwrap();
+        Self::add_parents(&mut state, &path);
+        state.dirs.insert(path);
+    }
+
+    /// Change the modification time of a file
+    pub fn set_modified(&self, path: &str, modified: SystemTime) {
+        let path = Self::normalize(Path::new(path));
+        if let Some(x) = self.state.lock().unwrap().files.get_mut(&path) {
+            x.1 = modified;
+        }
+    }
+
+    /// Make every operation reading or changing a path fail from now on
+    pub fn fail_on(&self, path: &str) {
+        let path = Self::normalize(Path::new(path));
+        self.state.lock().unwrap().failing.insert(path);
+    }
+
+    /// Return the paths of all the files, sorted
+    pub fn files(&self) -> Vec<PathBuf> {
+        self.state.lock().unwrap().files.keys().cloned().collect()
+    }
+
+    /// Return a path without its `.` components
+    fn normalize(path: &Path) -> PathBuf {
+        path.components()
+            .filter(|x| *x != Component::CurDir)
+            .collect()
+    }
+
+    fn add_parents(state: &mut MemState, path: &Path) {
+        for parent in path.ancestors().skip(1) {
+            if !parent.as_os_str().is_empty() {
+                state.dirs.insert(parent.to_path_buf());
+            }
+        }
+    }
+
+    /// Return the normalized path, or an error if it was given to `fail_on`
+    fn checked(state: &MemState, path: &Path) -> io::Result<PathBuf> {
+        let path = Self::normalize(path);
+        match state.failing.contains(&path) {
+            true => Err(io::Error::other(format!(
+                "injected failure on {}",
+                path.display()
+            ))),
+            false => Ok(path),
+        }
+    }
+
+    fn is_dir(state: &MemState, path: &Path) -> bool {
+        path.as_os_str().is_empty() || path.parent().is_none() || state.dirs.contains(path)
+    }
+
+    fn not_found(path: &Path) -> io::Error {
+        io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("{} does not exist", path.display()),
+        )
+    }
+
+    /// Check that the folder of a file exists
+    fn check_parent(state: &MemState, path: &Path) -> io::Result<()> {
+        match path.parent() {
+            Some(x) if !Self::is_dir(state, x) => Err(Self::not_found(x)),
+            _ => Ok(()),
+        }
+    }
+}
+
+impl Filesystem for MemFs {
+    fn read(&self, path: &Path) -> io::Result<Vec<u8>> {
+        let state = self.state.lock().unwrap();
+        let path = Self::checked(&state, path)?;
+        match state.files.get(&path) {
+            Some(x) => Ok(x.0.clone()),
+            None => Err(Self::not_found(&path)),
+        }
+    }
+
+    fn write(&self, path: &Path, content: &[u8]) -> io::Result<()> {
+        let mut state = self.state.lock().unwrap();
+        let path = Self::checked(&state, path)?;
+        Self::check_parent(&state, &path)?;
+        state
+            .files
+            .insert(path, (content.to_vec(), SystemTime::now()));
+        Ok(())
+    }
+
+    fn read_dir(&self, path: &Path) -> io::Result<Vec<PathBuf>> {
+        let state = self.state.lock().unwrap();
+        let dir = Self::checked(&state, path)?;
+        if !Self::is_dir(&state, &dir) {
+            return Err(Self::not_found(&dir));
+        }
+        let names = state
+            .files
+            .keys()
+            .chain(state.dirs.iter())
+            .filter(|x| x.parent() == Some(&dir))
+            .filter_map(|x| x.file_name());
+        Ok(names.map(|x| path.join(x)).collect())
+    }
+
+    fn metadata(&self, path: &Path) -> io::Result<FileInfo> {
+        let state = self.state.lock().unwrap();
+        let path = Self::checked(&state, path)?;
+        match state.files.get(&path) {
+            Some(x) => Ok(FileInfo {
+                is_dir: false,
+                len: x.0.len() as u64,
+                modified: x.1,
+            }),
+            None if Self::is_dir(&state, &path) => Ok(FileInfo {
+                is_dir: true,
+                len: 0,
+                modified: SystemTime::UNIX_EPOCH,
+            }),
+            None => Err(Self::not_found(&path)),
+        }
+    }
+
+    fn create_dir_all(&self, path: &Path) -> io::Result<()> {
+        let mut state = self.state.lock().unwrap();
+        let path = Self::checked(&state, path)?;
+        Self::add_parents(&mut state, &path);
+        state.dirs.insert(path);
+        Ok(())
+    }
+
+    fn rename(&self, from: &Path, to: &Path) -> io::Result<()> {
+        let mut state = self.state.lock().unwrap();
+        let from = Self::checked(&state, from)?;
+        let to = Self::checked(&state, to)?;
+        Self::check_parent(&state, &to)?;
+        match state.files.remove(&from) {
+            Some(x) => {
+                state.files.insert(to, x);
+                Ok(())
+            }
+            None => Err(Self::not_found(&from)),
+        }
+    }
+
+    fn copy(&self, from: &Path, to: &Path) -> io::Result<u64> {
+        let mut state = self.state.lock().unwrap();
+        let from = Self::checked(&state, from)?;
+        let to = Self::checked(&state, to)?;
+        Self::check_parent(&state, &to)?;
+        let content = match state.files.get(&from) {
+            Some(x) => x.0.clone(),
+            None => return Err(Self::not_found(&from)),
+        };
+        let len = content.len() as u64;
+        state.files.insert(to, (content, SystemTime::now()));
+        Ok(len)
+    }
+
+    fn remove_file(&self, path: &Path) -> io::Result<()> {
+        let mut state = self.state.lock().unwrap();
+        let path = Self::checked(&state, path)?;
+        match state.files.remove(&path) {
+            Some(_) => Ok(()),
+            None => Err(Self::not_found(&path)),
+        }
+    }
+
+    /// The files of a `MemFs` do not share their content, so a hardlink is a copy
+    fn hard_link(&self, from: &Path, to: &Path) -> io::Result<()> {
+        if self.exists(to) {
+            return Err(io::Error::new(
+                io::ErrorKind::AlreadyExists,
+                format!("{} already exists", to.display()),
+            ));
+        }
+        self.copy(from, to).map(|_| ())
+    }
+    /// The paths of a `MemFs` have no links, so the canonical path of an existing one is its path without `.`
+    fn canonicalize(&self, path: &Path) -> io::Result<PathBuf> {
+        self.metadata(path)?;
+        Ok(Self::normalize(path))
+    }
+}
@@ -0,0 +1,107 @@
+use crate::components::evaluation::Scope;
+use crate::components::matching::Matching;
+use crate::components::syntax::{LineKind, SyntaxTree};
+use crate::components::Config;
+
+/// Number of spaces between the longest rule of a file and the trailing comments
+const COMMENT_GAP: usize = 4;
+
+/// Number of spaces before the lines of an `in` block
+const BLOCK_INDENT: usize = 4;
+
+/// Length of the longest rule the trailing comments are aligned after, the longer ones are followed by the gap only
+const MAX_ALIGNED_WIDTH: usize = 50;
+
+/// Return the canonical form of a `.crc` file: one space between words, no trailing spaces, one blank line at most
+/// between lines, the lines of the `in` blocks indented, and the trailing comments aligned after the longest rule
+/// having one, up to `MAX_ALIGNED_WIDTH`.
+/// It is an error if the rules of the formatted file are not the same as the ones of the original file.
+pub fn format(content: &str) -> Result<String, String> {
+    let tree = SyntaxTree::parse(content);
+    let mut depth: usize = 0;
+    let mut lines: Vec<(String, Option<String>)> = vec![];
+    for line in &tree.lines {
+        if line.kind() == LineKind::BlockEnd {
+            depth = depth.saturating_sub(1);
+        }
+        let indent = " ".repeat(depth * BLOCK_INDENT);
+        let code = match line.code().as_str() {
+            "" => String::new(),
+            x => format!("{}{}", indent, x),
+        };
+        let comment = line
+            .comment
+            .as_ref()
+            .map(|y| format!("{}{}", indent, y.trim_end()));
+        lines.push((code, comment));
+        if line.kind() == LineKind::Block {
+            depth += 1;
+        }
+    }
+    let width = lines
+        .iter()
+        .filter(|x| x.1.is_some() && !x.0.is_empty())
+        .map(|x| x.0.chars().count())
+        .filter(|x| *x <= MAX_ALIGNED_WIDTH)
+        .max()
+        .unwrap_or(0);
+
+    let mut formatted: Vec<String> = vec![];
+    for (code, comment) in lines {
+        let line = match (code.is_empty(), comment) {
+            (true, None) => String::new(),
+            (true, Some(x)) => x,
+            (false, None) => code,
+            (false, Some(x)) => {
+                let x = x.trim_start();
+                let padding = (width + COMMENT_GAP)
+                    .saturating_sub(code.chars().count())
+                    .max(COMMENT_GAP);
+                format!("{}{}{}", code, " ".repeat(padding), x)
+            }
+        };
+        if line.is_empty() && formatted.last().is_none_or(String::is_empty) {
+            continue;
+        }
+        formatted.push(line);
+    }
+    while formatted.last().is_some_and(String::is_empty) {
+        formatted.pop();
+    }
+    let result = match formatted.is_empty() {
+        true => String::new(),
+        false => format!("{}\n", formatted.join("\n")),
+    };
+    match same_rules(content, &result) {
+        true => Ok(result),
+        false => Err(String::from(
+            "Formatting would change the rules of the file.",
+        )),
+    }
+}
+
+/// Check if two `.crc` files have the same rules in the same blocks, ignore the same files and have the same
+/// evaluation mode, whatever their lines
+fn same_rules(a: &str, b: &str) -> bool {
+    let rules = |content: &str| {
+        let config: Config = content.parse().unwrap_or_else(|x| match x {});
+        let rules: Vec<(Vec<String>, String, Option<Scope>, Matching)> = config
+            .get_rules()
+            .iter()
+            .map(|x| {
+                (
+                    x.get_tokens().clone(),
+                    x.get_error_message().clone(),
+                    x.get_scope().clone(),
+                    *x.get_matching(),
+                )
+            })
+            .collect();
+        (
+            rules,
+            config.get_ignored().clone(),
+            *config.get_evaluation(),
+        )
+    };
+    rules(a) == rules(b)
+}
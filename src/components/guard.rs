@@ -0,0 +1,28 @@
+use crate::components::trash;
+use std::fs;
+use std::path::{Component, Path};
+
+/// Return why a path is protected against being moved or deleted, or `None` if it is not.
+/// Protected paths are the `.crc` file being executed, dotfiles, `.git` directories and the
+/// home directory itself.
+pub fn protection(path: &Path, config_file: Option<&Path>) -> Option<&'static str> {
+    let canonical = fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+    if let Some(config_file) = config_file {
+        if fs::canonicalize(config_file).ok().as_ref() == Some(&canonical) {
+            return Some("configuration file");
+        }
+    }
+    if fs::canonicalize(trash::home_dir()).ok().as_ref() == Some(&canonical) {
+        return Some("home directory");
+    }
+    if canonical
+        .components()
+        .any(|x| x == Component::Normal(".git".as_ref()))
+    {
+        return Some("git directory");
+    }
+    match path.file_name() {
+        Some(x) if x.to_string_lossy().starts_with('.') => Some("dotfile"),
+        _ => None,
+    }
+}
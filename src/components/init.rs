@@ -0,0 +1,226 @@
+use crate::components::confirm::Confirmation;
+use crate::components::filesystem::Filesystem;
+use crate::components::format;
+use crate::components::plan::{Action, Operation};
+use crate::components::trash;
+use crate::components::Rule;
+use regex::Regex;
+use std::collections::BTreeMap;
+use std::env;
+use std::path::{Path, PathBuf};
+
+/// The user directories rules are proposed for, with their key in `user-dirs.dirs` and the lowercase extensions they
+/// receive
+const CATEGORIES: [(&str, &str, &[&str]); 4] = [
+    (
+        "Pictures",
+        "PICTURES",
+        &[
+            "png", "jpg", "jpeg", "gif", "webp", "svg", "bmp", "tiff", "heic",
+        ],
+    ),
+    (
+        "Music",
+        "MUSIC",
+        &["mp3", "flac", "ogg", "wav", "m4a", "aac", "opus"],
+    ),
+    ("Videos", "VIDEOS", &["mp4", "mkv", "avi", "mov", "webm"]),
+    (
+        "Documents",
+        "DOCUMENTS",
+        &[
+            "pdf", "doc", "docx", "odt", "rtf", "txt", "md", "xls", "xlsx", "ods", "csv", "ppt",
+            "pptx", "odp", "epub",
+        ],
+    ),
+];
+
+/// A rule proposed by `crabby init`, moving the files of an extension to a user directory
+#[derive(Clone, Debug, PartialEq)]
+pub struct Proposal {
+    /// Name of the user directory, such as `Pictures`
+    pub category: String,
+    /// The extension, in lowercase
+    pub extension: String,
+    /// The files of the scanned folder having the extension, whatever its case
+    pub files: Vec<String>,
+    pub destination: PathBuf,
+}
+
+impl Proposal {
+    /// Return the line of the proposed rule, ignoring the case when some files have the extension in another case
+    pub fn rule(&self) -> String {
+        let ignore_case = self.files.iter().any(|x| {
+            Path::new(x)
+                .extension()
+                .is_some_and(|y| y.to_string_lossy() != self.extension)
+        });
+        format!(
+            "mv *.{} to {}{}",
+            self.extension,
+            self.destination.to_string_lossy(),
+            match ignore_case {
+                true => " ignore-case",
+                false => "",
+            }
+        )
+    }
+}
+
+/// What `crabby init` found in a folder: the proposed rules, and the extensions without a destination with their
+/// number of files
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Scaffold {
+    pub proposals: Vec<Proposal>,
+    pub unsorted: Vec<(String, usize)>,
+}
+
+/// Return the user directories of the current user, from the `user-dirs.dirs` file of `xdg-user-dirs`
+pub fn user_dirs(fs: &dyn Filesystem) -> Vec<(String, PathBuf)> {
+    let config_home = match env::var_os("XDG_CONFIG_HOME") {
+        Some(x) if !x.is_empty() => PathBuf::from(x),
+        _ => trash::home_dir().join(".config"),
+    };
+    user_dirs_in(fs, &trash::home_dir(), &config_home)
+}
+
+/// Return the user directories of a home directory, as written in the `user-dirs.dirs` file of a configuration
+/// folder. The ones it does not define are the folders of the home directory named after them.
+pub fn user_dirs_in(
+    fs: &dyn Filesystem,
+    home: &Path,
+    config_home: &Path,
+) -> Vec<(String, PathBuf)> {
+    let content = fs
+        .read_to_string(&config_home.join("user-dirs.dirs"))
+        .unwrap_or_default();
+    let line_regex = Regex::new(r#"^XDG_([A-Z]+)_DIR="(.*)"$"#).unwrap();
+    let defined: Vec<(String, String)> = content
+        .lines()
+        .filter_map(|x| line_regex.captures(x.trim()))
+        .map(|x| (x[1].to_string(), x[2].to_string()))
+        .collect();
+    CATEGORIES
+        .iter()
+        .map(|(name, key, _)| {
+            let path = match defined.iter().find(|x| x.0 == *key) {
+                Some((_, x)) if x.starts_with("$HOME") => {
+                    home.join(x["$HOME".len()..].trim_start_matches('/'))
+                }
+                Some((_, x)) => PathBuf::from(x),
+                None => home.join(name),
+            };
+            (name.to_string(), path)
+        })
+        .collect()
+}
+
+/// Group the files of a folder by extension, whatever its case, and propose to move the ones of a known type to its
+/// user directory. The destinations with spaces are not proposed, since the rules of a `.crc` file have no quoting.
+pub fn scaffold(fs: &dyn Filesystem, dir: &str, user_dirs: &[(String, PathBuf)]) -> Scaffold {
+    let mut extensions: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    for path in fs.read_dir(Path::new(dir)).unwrap_or_default() {
+        let extension = path.extension().map(|x| x.to_string_lossy().to_lowercase());
+        let hidden = path
+            .file_name()
+            .is_some_and(|x| x.to_string_lossy().starts_with('.'));
+        match extension {
+            Some(x) if fs.is_file(&path) && !hidden && !x.contains(' ') && x != "crc" => extensions
+                .entry(x)
+                .or_default()
+                .push(path.to_string_lossy().to_string()),
+            _ => (),
+        }
+    }
+    let mut scaffold = Scaffold::default();
+    for (name, _, known) in CATEGORIES.iter() {
+        let destination = user_dirs.iter().find(|x| x.0 == *name).map(|x| &x.1);
+        for extension in known.iter() {
+            let files = match extensions.remove(*extension) {
+                Some(x) => x,
+                None => continue,
+            };
+            match destination {
+                Some(x) if !x.to_string_lossy().contains(' ') => {
+                    scaffold.proposals.push(Proposal {
+                        category: name.to_string(),
+                        extension: extension.to_string(),
+                        files,
+                        destination: x.clone(),
+                    })
+                }
+                _ => scaffold.unsorted.push((extension.to_string(), files.len())),
+            }
+        }
+    }
+    scaffold
+        .unsorted
+        .extend(extensions.into_iter().map(|(x, y)| (x, y.len())));
+    scaffold
+}
+
+/// Keep the proposals the user accepts, asking about each of them with an example of the files it would move
+pub fn select_proposals(
+    proposals: Vec<Proposal>,
+    confirmation: &mut Confirmation,
+) -> Vec<Proposal> {
+    let mut selected: Vec<Proposal> = vec![];
+    for (i, proposal) in proposals.into_iter().enumerate() {
+        if confirmation.is_quit() {
+            break;
+        }
+        confirmation.start_rule();
+        let rule = Rule::new_from_line(&proposal.rule(), &i);
+        let source = &proposal.files[0];
+        let operation = Operation {
+            line: i + 1,
+            action: Action::Move,
+            source: source.clone(),
+            destination: proposal
+                .destination
+                .join(Path::new(source).file_name().unwrap_or_default())
+                .to_string_lossy()
+                .to_string(),
+        };
+        if confirmation.accepts(&rule, &operation) {
+            selected.push(proposal);
+        }
+    }
+    selected
+}
+
+/// Return the `.crc` file written to `file`, with the proposed rules grouped by user directory, in its canonical form
+pub fn render_scaffold(dir: &str, file: &str, scaffold: &Scaffold) -> String {
+    let header = |title: &str| format!("\n// ===============\n// {}\n// ===============\n", title);
+    let mut lines: Vec<String> = vec![
+        format!(
+            "// Rules proposed by `crabby init` for the files of `{}`.",
+            dir
+        ),
+        format!(
+            "// Review them, then run `crabby -c {} -f {} --dry-run` to see what they do.",
+            dir, file
+        ),
+        String::new(),
+    ];
+    let mut category = "";
+    for proposal in &scaffold.proposals {
+        if proposal.category != category {
+            category = &proposal.category;
+            lines.push(header(category));
+        }
+        lines.push(format!(
+            "{}    // {} file(s)",
+            proposal.rule(),
+            proposal.files.len()
+        ));
+    }
+    if !scaffold.unsorted.is_empty() {
+        lines.push(header("Without destination"));
+        for (extension, count) in &scaffold.unsorted {
+            lines.push(format!("// {} `.{}` file(s)", count, extension));
+        }
+    }
+    let content = format!("{}\n", lines.join("\n"));
+    format::format(&content).unwrap_or(content)
+}
@@ -0,0 +1,628 @@
+use crate::components::check::{self, Severity};
+use crate::components::evaluation::Evaluation;
+use crate::components::filesystem::{Filesystem, StdFs};
+use crate::components::matching::Matching;
+use crate::components::rule::{FIRST_VALID_TOKENS, UNARY_VALID_TOKENS};
+use crate::components::syntax::{self, LineKind, SyntaxTree};
+use crate::components::Rule;
+use percent_encoding::{percent_decode_str, utf8_percent_encode, AsciiSet, CONTROLS};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::io::{self, BufRead, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// Kinds of completion items of the protocol
+const KEYWORD_KIND: u32 = 14;
+const VARIABLE_KIND: u32 = 6;
+
+/// Read a message of the protocol, written after a `Content-Length` header. Return `None` at the end of the input.
+pub fn read_message<R: BufRead>(input: &mut R) -> io::Result<Option<Value>> {
+    let mut length: Option<usize> = None;
+    loop {
+        let mut header = String::new();
+        if input.read_line(&mut header)? == 0 {
+            return Ok(None);
+        }
+        let header = header.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        if let Some(x) = header.strip_prefix("Content-Length:") {
+            length = x.trim().parse().ok();
+        }
+    }
+    let length = length.ok_or_else(|| {
+        io::Error::new(io::ErrorKind::InvalidData, "missing Content-Length header")
+    })?;
+    let mut content = vec![0; length];
+    input.read_exact(&mut content)?;
+    serde_json::from_slice(&content)
+        .map(Some)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+/// Write a message of the protocol, after its `Content-Length` header
+pub fn write_message<W: Write>(output: &mut W, message: &Value) -> io::Result<()> {
+    let content = message.to_string();
+    write!(
+        output,
+        "Content-Length: {}\r\n\r\n{}",
+        content.len(),
+        content
+    )?;
+    output.flush()
+}
+
+/// The characters of a path that are percent-encoded in a `file://` URI
+const URI_ESCAPED: &AsciiSet = &CONTROLS
+    .add(b' ')
+    .add(b'"')
+    .add(b'#')
+    .add(b'%')
+    .add(b'<')
+    .add(b'>')
+    .add(b'?')
+    .add(b'[')
+    .add(b']')
+    .add(b'`')
+    .add(b'{')
+    .add(b'}');
+
+/// Return the path of a `file://` URI
+fn uri_to_path(uri: &str) -> PathBuf {
+    let path = uri.strip_prefix("file://").unwrap_or(uri);
+    PathBuf::from(percent_decode_str(path).decode_utf8_lossy().to_string())
+}
+
+/// Return the `file://` URI of a path
+fn path_to_uri(path: &Path) -> String {
+    let path = path.to_string_lossy();
+    format!("file://{}", utf8_percent_encode(&path, URI_ESCAPED))
+}
+
+/// A variable defined by a `let` line, in a `.crc` file or a file it includes
+#[derive(Clone, Debug)]
+struct Definition {
+    name: String,
+    value: String,
+    path: PathBuf,
+    /// Line of the definition, from 0
+    line: usize,
+}
+
+/// A language server for `.crc` files, speaking the Language Server Protocol over an input and an output:
+/// diagnostics, completion of the keywords, hover and go-to-definition for variables and includes
+pub struct LanguageServer<R: BufRead, W: Write> {
+    input: R,
+    output: W,
+    documents: HashMap<String, String>,
+    /// The folder whose files the rules are matched with in the hovers, the folder of the document by default
+    root: Option<PathBuf>,
+    filesystem: Arc<dyn Filesystem>,
+}
+
+impl<R: BufRead, W: Write> LanguageServer<R, W> {
+    /// Create a new `LanguageServer` reading the disk
+    pub fn new(input: R, output: W) -> Self {
+        Self {
+            input,
+            output,
+            documents: HashMap::new(),
+            root: None,
+            filesystem: Arc::new(StdFs),
+        }
+    }
+
+    /// Set the `Filesystem` the included files and the files matched by the rules are read from
+    pub fn set_filesystem(&mut self, filesystem: Arc<dyn Filesystem>) {
+        self.filesystem = filesystem;
+    }
+
+    /// Return the output, to read what a server in a test wrote
+    pub fn into_output(self) -> W {
+        self.output
+    }
+
+    /// Answer the messages until the `exit` notification or the end of the input
+    pub fn run(&mut self) -> io::Result<()> {
+        while let Some(message) = read_message(&mut self.input)? {
+            if !self.handle(&message)? {
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    /// Answer a message, and return false if it is the `exit` notification
+    fn handle(&mut self, message: &Value) -> io::Result<bool> {
+        let method = message["method"].as_str().unwrap_or_default();
+        let params = &message["params"];
+        let result = match method {
+            "initialize" => {
+                self.root = params["rootUri"]
+                    .as_str()
+                    .map(uri_to_path)
+                    .or_else(|| params["rootPath"].as_str().map(PathBuf::from));
+                Some(json!({
+                    "capabilities": {
+                        "textDocumentSync": 1,
+                        "completionProvider": { "triggerCharacters": ["$"] },
+                        "hoverProvider": true,
+                        "definitionProvider": true,
+                    },
+                    "serverInfo": { "name": "crabby" },
+                }))
+            }
+            "textDocument/didOpen" => {
+                let document = &params["textDocument"];
+                self.update(&document["uri"], document["text"].as_str())?;
+                None
+            }
+            "textDocument/didChange" => {
+                let text = params["contentChanges"]
+                    .as_array()
+                    .and_then(|x| x.last())
+                    .and_then(|x| x["text"].as_str());
+                self.update(&params["textDocument"]["uri"], text)?;
+                None
+            }
+            "textDocument/didClose" => {
+                let uri = params["textDocument"]["uri"].as_str().unwrap_or_default();
+                self.documents.remove(uri);
+                None
+            }
+            "textDocument/completion" => Some(self.at_position(params, Self::completion)),
+            "textDocument/hover" => Some(self.at_position(params, Self::hover)),
+            "textDocument/definition" => Some(self.at_position(params, Self::definition)),
+            "shutdown" => Some(Value::Null),
+            "exit" => return Ok(false),
+            _ => None,
+        };
+        let id = match message.get("id") {
+            Some(x) => x.clone(),
+            None => return Ok(true),
+        };
+        let response = match result {
+            Some(x) => json!({ "jsonrpc": "2.0", "id": id, "result": x }),
+            None => json!({
+                "jsonrpc": "2.0",
+                "id": id,
+                "error": { "code": -32601, "message": format!("unknown method `{}`", method) },
+            }),
+        };
+        write_message(&mut self.output, &response)?;
+        Ok(true)
+    }
+
+    /// Keep the new text of a document, and publish its diagnostics
+    fn update(&mut self, uri: &Value, text: Option<&str>) -> io::Result<()> {
+        let (uri, text) = match (uri.as_str(), text) {
+            (Some(x), Some(y)) => (x.to_string(), y.to_string()),
+            _ => return Ok(()),
+        };
+        let diagnostics = self.diagnostics(&uri, &text);
+        self.documents.insert(uri.clone(), text);
+        write_message(
+            &mut self.output,
+            &json!({
+                "jsonrpc": "2.0",
+                "method": "textDocument/publishDiagnostics",
+                "params": { "uri": uri, "diagnostics": diagnostics },
+            }),
+        )
+    }
+
+    /// Return the diagnostics of `crabby check` for a document, and the errors of its includes
+    fn diagnostics(&self, uri: &str, text: &str) -> Vec<Value> {
+        let path = uri_to_path(uri);
+        let range = |line: usize, column: usize, length: usize| {
+            json!({
+                "start": { "line": line - 1, "character": column - 1 },
+                "end": { "line": line - 1, "character": column - 1 + length },
+            })
+        };
+        let mut diagnostics: Vec<Value> = check::check(&path.to_string_lossy(), text)
+            .into_iter()
+            .map(|x| {
+                let message = match &x.help {
+                    Some(y) => format!("{}\n{}", x.message, y),
+                    None => x.message.clone(),
+                };
+                json!({
+                    "range": range(x.line, x.column, x.length),
+                    "severity": if x.severity == Severity::Error { 1 } else { 2 },
+                    "source": "crabby",
+                    "message": message,
+                })
+            })
+            .collect();
+        let definitions = self.definitions(&path, text, usize::MAX);
+        for line in SyntaxTree::parse(text).lines {
+            if let (LineKind::Include, Ok(x)) = (line.kind(), line.include()) {
+                let included = Self::included_path(&path, x, &definitions);
+                if !self.filesystem.is_file(&included) {
+                    let word = &line.words[1];
+                    diagnostics.push(json!({
+                        "range": range(line.number, word.column, word.text.chars().count()),
+                        "severity": 1,
+                        "source": "crabby",
+                        "message": format!("cannot read `{}`", included.display()),
+                    }));
+                }
+            }
+        }
+        diagnostics
+    }
+
+    /// Answer a request about a position of a document
+    fn at_position(
+        &self,
+        params: &Value,
+        answer: fn(&Self, &Path, &str, usize, usize) -> Value,
+    ) -> Value {
+        let uri = params["textDocument"]["uri"].as_str().unwrap_or_default();
+        let text = match self.documents.get(uri) {
+            Some(x) => x.clone(),
+            None => match self.filesystem.read_to_string(&uri_to_path(uri)) {
+                Ok(x) => x,
+                Err(_) => return Value::Null,
+            },
+        };
+        let line = params["position"]["line"].as_u64().unwrap_or(0) as usize;
+        let character = params["position"]["character"].as_u64().unwrap_or(0) as usize;
+        answer(self, &uri_to_path(uri), &text, line, character)
+    }
+
+    /// Return the keywords or the variables that can be written at a position
+    fn completion(&self, path: &Path, text: &str, line: usize, character: usize) -> Value {
+        let source = text.split('\n').nth(line).unwrap_or_default();
+        let prefix: String = source.chars().take(character).collect();
+        if prefix.contains("//") {
+            return json!([]);
+        }
+        let words: Vec<&str> = prefix.split(' ').filter(|x| !x.is_empty()).collect();
+        let current = match prefix.ends_with(' ') || prefix.is_empty() {
+            true => "",
+            false => words.last().copied().unwrap_or_default(),
+        };
+        let index = words.len() - if current.is_empty() { 0 } else { 1 };
+        let item = |label: &str, kind: u32, detail: String| json!({ "label": label, "kind": kind, "detail": detail });
+        if current.starts_with('$') {
+            let mut names: Vec<Value> = vec![];
+            for x in self.definitions(path, text, line).iter().rev() {
+                if !names.iter().any(|y| y["label"] == x.name.as_str()) {
+                    names.push(item(&x.name, VARIABLE_KIND, x.value.clone()));
+                }
+            }
+            return Value::Array(names);
+        }
+        if current.starts_with('@') {
+            return json!([
+                item(
+                    "@name()",
+                    KEYWORD_KIND,
+                    String::from("name of the rule, for `--only` and `--skip`")
+                ),
+                item(
+                    "@tag()",
+                    KEYWORD_KIND,
+                    String::from("tag of the rule, for `--only` and `--skip`")
+                ),
+            ]);
+        }
+        // The annotations before the keywords do not count in the position of the word
+        let annotated = words.iter().take_while(|x| x.starts_with('@')).count();
+        let (words, index) = (&words[annotated..], index - annotated);
+        let verb = words.first().copied().unwrap_or_default();
+        let core_length = match UNARY_VALID_TOKENS.contains(&verb) {
+            true => 2,
+            false => 4,
+        };
+        let items: Vec<Value> = match index {
+            0 => FIRST_VALID_TOKENS
+                .iter()
+                .chain(UNARY_VALID_TOKENS.iter())
+                .chain(["ignore", "let", "include", "evaluate", "match", "in"].iter())
+                .map(|x| item(x, KEYWORD_KIND, Self::usage(x)))
+                .collect(),
+            1 if verb == "evaluate" => Evaluation::NAMES
+                .iter()
+                .map(|x| item(x, KEYWORD_KIND, String::from("evaluation mode")))
+                .collect(),
+            _ if verb == "evaluate" || verb == "in" => vec![],
+            _ if verb == "match" => Matching::NAMES
+                .iter()
+                .map(|x| item(x, KEYWORD_KIND, String::from("matching flag")))
+                .collect(),
+            2 if core_length == 4 => check::second_keyword(verb)
+                .map(|x| item(x, KEYWORD_KIND, Self::usage(verb)))
+                .into_iter()
+                .collect(),
+            x if x >= core_length => check::options_of(verb)
+                .iter()
+                .map(|x| item(x, KEYWORD_KIND, format!("{} option", verb)))
+                .collect(),
+            _ => vec![],
+        };
+        Value::Array(items)
+    }
+
+    /// Return how a line starting with a keyword is written
+    fn usage(keyword: &str) -> String {
+        match (keyword, check::second_keyword(keyword)) {
+            (x, Some(y)) => format!("{} <pattern> {} <pattern>", x, y),
+            ("ignore", _) => String::from("ignore <pattern>..."),
+            ("let", _) => String::from("let <name> = <value>"),
+            ("include", _) => String::from("include <path>"),
+            ("evaluate", _) => String::from("evaluate first-match|all-match|independent"),
+            ("match", _) => String::from("match ignore-case|unicode..."),
+            ("in", _) => String::from("in <path> [recursive] {"),
+            (x, None) => format!("{} <pattern>", x),
+        }
+    }
+
+    /// Return what the rule or the variable at a position does, and how many files the rule matches
+    fn hover(&self, path: &Path, text: &str, line: usize, character: usize) -> Value {
+        let tree = SyntaxTree::parse(text);
+        let syntax_line = match tree.lines.get(line) {
+            Some(x) => x,
+            None => return Value::Null,
+        };
+        let definitions = self.definitions(path, text, line);
+        let variables: Vec<(String, String)> = definitions
+            .iter()
+            .map(|x| (x.name.clone(), x.value.clone()))
+            .collect();
+        let markdown = |x: String| json!({ "contents": { "kind": "markdown", "value": x } });
+        if let Some(x) = self.reference_at(syntax_line, character, &definitions) {
+            return markdown(format!("`${}` = `{}`", x.name, x.value));
+        }
+        match syntax_line.kind() {
+            LineKind::Rule => (),
+            LineKind::Variable => {
+                return match syntax_line.variable() {
+                    Ok((x, y)) => markdown(format!(
+                        "`${}` = `{}`",
+                        x,
+                        syntax::substitute(y, &variables)
+                    )),
+                    Err(e) => markdown(e),
+                }
+            }
+            LineKind::Include => {
+                return match syntax_line.include() {
+                    Ok(x) => markdown(format!(
+                        "Includes the rules of `{}`.",
+                        Self::included_path(path, x, &definitions).display()
+                    )),
+                    Err(e) => markdown(e),
+                }
+            }
+            LineKind::Ignore => {
+                return markdown(String::from(
+                    "No rule of this file touches the files matching these patterns.",
+                ))
+            }
+            LineKind::Evaluate => {
+                return markdown(match syntax_line.evaluation() {
+                    Ok(Evaluation::Independent) => String::from(
+                        "Every rule runs on the files of the folder as the previous rules left it.",
+                    ),
+                    Ok(Evaluation::FirstMatch) => {
+                        String::from("Each file is only handled by the first rule matching it.")
+                    }
+                    Ok(Evaluation::AllMatch) => String::from(
+                        "Each file goes through all the rules in order, with its new name.",
+                    ),
+                    Err(e) => e,
+                })
+            }
+            LineKind::Match => {
+                return markdown(match syntax_line.matching() {
+                    Ok(x) => {
+                        let mut flags: Vec<&str> = vec![];
+                        if x.ignore_case {
+                            flags.push("whatever their case");
+                        }
+                        if x.unicode {
+                            flags.push("whatever their Unicode normalization");
+                        }
+                        format!(
+                            "The patterns of the rules of this file match the names {}.",
+                            flags.join(" and ")
+                        )
+                    }
+                    Err(e) => e,
+                })
+            }
+            LineKind::Block => {
+                return match syntax_line.block() {
+                    Ok((x, recursive)) => markdown(format!(
+                        "The rules of the block run in `{}`{}.",
+                        Self::included_path(path, x, &definitions).display(),
+                        match recursive {
+                            true => " and its subfolders",
+                            false => "",
+                        }
+                    )),
+                    Err(e) => markdown(e),
+                }
+            }
+            _ => return Value::Null,
+        }
+        let source = syntax::substitute(&syntax_line.to_string(), &variables);
+        let mut rule = Rule::new_from_line(&source, &line);
+        if !rule.get_error_message().is_empty() || !*rule.is_valid() {
+            return markdown(format!("Invalid rule: {}", rule.get_error_message()));
+        }
+        rule.set_filesystem(self.filesystem.clone());
+        let root = match &self.root {
+            Some(x) => x.clone(),
+            None => path.parent().map(|x| x.to_path_buf()).unwrap_or_default(),
+        };
+        let count = rule
+            .files_concerned(root.to_string_lossy().to_string())
+            .len();
+        let mut labels: Vec<String> = vec![];
+        if let Some(x) = rule.get_name() {
+            labels.push(format!("Named `{}`.", x));
+        }
+        if !rule.get_tags().is_empty() {
+            labels.push(format!("Tagged `{}`.", rule.get_tags().join("`, `")));
+        }
+        markdown(format!(
+            "{}{}\n\n{} file(s) currently match in `{}`.",
+            Self::describe(rule.get_tokens()),
+            labels.iter().map(|x| format!(" {}", x)).collect::<String>(),
+            count,
+            root.display()
+        ))
+    }
+
+    /// Return a sentence describing what a valid rule does
+    fn describe(tokens: &[String]) -> String {
+        let pattern = &tokens[1];
+        let target = tokens.get(3).cloned().unwrap_or_default();
+        match tokens[0].as_str() {
+            "mv" => format!("Moves the files matching `{}` to `{}`.", pattern, target),
+            "cp" => format!("Copies the files matching `{}` to `{}`.", pattern, target),
+            "pre" => format!(
+                "Adds `{}` to the beginning of the names of the files matching `{}`.",
+                target, pattern
+            ),
+            "suf" => format!(
+                "Adds `{}` to the end of the names of the files matching `{}`.",
+                target, pattern
+            ),
+            "zip" | "tar.gz" => format!(
+                "Archives the files matching `{}` into `{}`.",
+                pattern, target
+            ),
+            "extract" => format!(
+                "Extracts the archives matching `{}` to `{}`.",
+                pattern, target
+            ),
+            "normalize" => format!(
+                "Renames the files matching `{}` with their normalized names.",
+                pattern
+            ),
+            _ => format!(
+                "Removes the duplicates among the files matching `{}`.",
+                pattern
+            ),
+        }
+    }
+
+    /// Return where the variable or the included file at a position is defined
+    fn definition(&self, path: &Path, text: &str, line: usize, character: usize) -> Value {
+        let tree = SyntaxTree::parse(text);
+        let syntax_line = match tree.lines.get(line) {
+            Some(x) => x,
+            None => return Value::Null,
+        };
+        let definitions = self.definitions(path, text, line);
+        let location = |path: &Path, line: usize| {
+            json!({
+                "uri": path_to_uri(path),
+                "range": {
+                    "start": { "line": line, "character": 0 },
+                    "end": { "line": line, "character": 0 },
+                },
+            })
+        };
+        if let Some(x) = self.reference_at(syntax_line, character, &definitions) {
+            return location(&x.path, x.line);
+        }
+        match (syntax_line.kind(), syntax_line.include()) {
+            (LineKind::Include, Ok(x)) => location(&Self::included_path(path, x, &definitions), 0),
+            _ => Value::Null,
+        }
+    }
+
+    /// Return the definition of the variable referenced by the word at a column, from 0
+    fn reference_at<'a>(
+        &self,
+        line: &syntax::SyntaxLine,
+        character: usize,
+        definitions: &'a [Definition],
+    ) -> Option<&'a Definition> {
+        let word = line.word_at(character + 1)?;
+        let offset = character + 1 - word.column;
+        let start = word
+            .text
+            .chars()
+            .take(offset + 1)
+            .collect::<String>()
+            .rfind('$')?;
+        let name: String = word.text[start + 1..]
+            .chars()
+            .take_while(|x| x.is_ascii_alphanumeric() || *x == '_')
+            .collect();
+        definitions.iter().rev().find(|x| x.name == name)
+    }
+
+    /// Return the path of a file included by a `.crc` file, relative to its folder
+    fn included_path(path: &Path, include: &str, definitions: &[Definition]) -> PathBuf {
+        let variables: Vec<(String, String)> = definitions
+            .iter()
+            .map(|x| (x.name.clone(), x.value.clone()))
+            .collect();
+        let include = syntax::substitute(include, &variables);
+        match path.parent() {
+            Some(x) if Path::new(&include).is_relative() => x.join(include),
+            _ => PathBuf::from(include),
+        }
+    }
+
+    /// Return the variables defined before a line of a `.crc` file, in order, with the ones of the files it includes
+    fn definitions(&self, path: &Path, text: &str, before: usize) -> Vec<Definition> {
+        let mut definitions: Vec<Definition> = vec![];
+        let mut files: Vec<PathBuf> = vec![];
+        self.collect_definitions(path, text, before, &mut definitions, &mut files);
+        definitions
+    }
+
+    fn collect_definitions(
+        &self,
+        path: &Path,
+        text: &str,
+        before: usize,
+        definitions: &mut Vec<Definition>,
+        files: &mut Vec<PathBuf>,
+    ) {
+        files.push(path.to_path_buf());
+        for line in SyntaxTree::parse(text).lines.iter().take(before) {
+            match (line.kind(), line.variable(), line.include()) {
+                (LineKind::Variable, Ok((x, y)), _) => {
+                    let variables: Vec<(String, String)> = definitions
+                        .iter()
+                        .map(|z| (z.name.clone(), z.value.clone()))
+                        .collect();
+                    definitions.push(Definition {
+                        name: x.to_string(),
+                        value: syntax::substitute(y, &variables),
+                        path: path.to_path_buf(),
+                        line: line.number - 1,
+                    });
+                }
+                (LineKind::Include, _, Ok(x)) => {
+                    let included = Self::included_path(path, x, definitions);
+                    if files.contains(&included) {
+                        continue;
+                    }
+                    if let Ok(content) = self.filesystem.read_to_string(&included) {
+                        self.collect_definitions(
+                            &included,
+                            &content,
+                            usize::MAX,
+                            definitions,
+                            files,
+                        );
+                    }
+                }
+                _ => (),
+            }
+        }
+    }
+}
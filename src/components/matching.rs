@@ -0,0 +1,64 @@
+use crate::components::normalize::UnicodeForm;
+use regex::{Regex, RegexBuilder};
+
+/// How the patterns of a rule are compared to the file names, from the `ignore-case` and `unicode` options of the
+/// rule and the `match` line of its `.crc` file
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Matching {
+    /// `Photo.JPG` follows `*.jpg`
+    pub ignore_case: bool,
+    /// The names written with composed or decomposed characters follow the same patterns
+    pub unicode: bool,
+}
+
+impl Matching {
+    /// Names of the flags, as written after `match` or as options of a rule
+    pub const NAMES: [&'static str; 2] = ["ignore-case", "unicode"];
+
+    /// Set the flag of a name, and return false if the name is unknown
+    pub fn set_flag(&mut self, name: &str) -> bool {
+        match name {
+            "ignore-case" => self.ignore_case = true,
+            "unicode" => self.unicode = true,
+            _ => return false,
+        }
+        true
+    }
+
+    /// Return the flags of both `Matching`
+    pub fn union(&self, other: &Self) -> Self {
+        Self {
+            ignore_case: self.ignore_case || other.ignore_case,
+            unicode: self.unicode || other.unicode,
+        }
+    }
+
+    /// Check if a file name follows a pattern, where `*` is any text
+    pub fn is_match(&self, pattern: &str, name: &str) -> bool {
+        match self.regex(pattern) {
+            Ok(x) => self.matches(&x, name),
+            Err(_) => false,
+        }
+    }
+
+    /// Check if a file name follows the regex of a pattern, as returned by `regex`
+    pub fn matches(&self, regex: &Regex, name: &str) -> bool {
+        match self.unicode {
+            true => regex.is_match(&UnicodeForm::Nfc.apply(name)),
+            false => regex.is_match(name),
+        }
+    }
+
+    /// Return the regex of a pattern, the other characters than `*` being matched as they are
+    pub fn regex(&self, pattern: &str) -> Result<Regex, String> {
+        let pattern = match self.unicode {
+            true => UnicodeForm::Nfc.apply(pattern),
+            false => pattern.to_string(),
+        };
+        let replaced_pattern = regex::escape(&pattern).replace(r"\*", ".*");
+        RegexBuilder::new(&format!(r"^{}$", &replaced_pattern))
+            .case_insensitive(self.ignore_case)
+            .build()
+            .map_err(|_| String::from("Invalid pattern."))
+    }
+}
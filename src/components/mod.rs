@@ -1,4 +1,29 @@
+pub mod archive;
+pub mod check;
 pub mod config;
+pub mod confirm;
+pub mod control;
+pub mod copy;
+pub mod dedupe;
+pub mod evaluation;
+pub mod explain;
+pub mod filesystem;
+pub mod format;
+pub mod guard;
+pub mod init;
+pub mod lsp;
+pub mod matching;
+pub mod normalize;
+pub mod observer;
+pub mod output;
+pub mod plan;
+pub mod review;
 pub mod rule;
+pub mod schedule;
+pub mod service;
+pub mod syntax;
+pub mod trash;
+pub mod tui;
+pub mod watch;
 
 pub use self::{config::Config, rule::Rule};
@@ -0,0 +1,141 @@
+use crate::components::filesystem::Filesystem;
+use percent_encoding::percent_decode_str;
+use regex::Regex;
+use std::path::{Path, PathBuf};
+use unicode_normalization::UnicodeNormalization;
+
+/// The marks that browsers and file managers add to the copies of a file, such as ` (1)` or ` [copy]`
+const COPY_SUFFIX: &str =
+    r"(?i)[\s_-]*(\(\d+\)|\[\d+\]|\(copy( \d+)?\)|\[copy( \d+)?\]|- copy( \d+)?)$";
+
+/// A Unicode normalization form of the file names
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum UnicodeForm {
+    /// Composed characters, as written by Linux and Windows
+    Nfc,
+    /// Decomposed characters, as written by the file systems of macOS
+    Nfd,
+}
+
+impl UnicodeForm {
+    /// Return the `UnicodeForm` matching a `.crc` token
+    pub fn from_token(token: &str) -> Option<Self> {
+        match token {
+            "nfc" => Some(Self::Nfc),
+            "nfd" => Some(Self::Nfd),
+            _ => None,
+        }
+    }
+
+    /// Return a text in the current form
+    pub fn apply(&self, text: &str) -> String {
+        match self {
+            Self::Nfc => text.nfc().collect(),
+            Self::Nfd => text.nfd().collect(),
+        }
+    }
+}
+
+/// Options that can follow a `normalize` rule, the transforms to apply to the file names
+#[derive(Clone, Debug, PartialEq)]
+pub struct NormalizeOptions {
+    pub form: UnicodeForm,
+    /// Decode the `%20`-style escapes of the names saved from URLs
+    pub url_decode: bool,
+    pub lowercase_extension: bool,
+    /// The character replacing the runs of spaces, `_` or `-`
+    pub spaces: Option<char>,
+    /// Remove the ` (1)`-style marks of the copies
+    pub strip_copies: bool,
+    /// Transliterate the names to ASCII, `é` becoming `e`
+    pub ascii: bool,
+    /// Maximum number of characters of the names, the extension included
+    pub max_length: Option<usize>,
+}
+
+impl Default for NormalizeOptions {
+    fn default() -> Self {
+        Self {
+            form: UnicodeForm::Nfc,
+            url_decode: false,
+            lowercase_extension: false,
+            spaces: None,
+            strip_copies: false,
+            ascii: false,
+            max_length: None,
+        }
+    }
+}
+
+/// Return a file name once normalized
+pub fn normalized_name(name: &str, options: &NormalizeOptions) -> String {
+    let mut name = name.to_string();
+    if options.url_decode {
+        name = percent_decode_str(&name)
+            .decode_utf8_lossy()
+            .replace(['/', '\\'], "_");
+    }
+    name = options.form.apply(&name);
+    if options.ascii {
+        name = deunicode::deunicode(&name).replace(['/', '\\'], "_");
+    }
+    let (mut stem, mut extension) = match name.rfind('.') {
+        Some(i) if i > 0 => (name[..i].to_string(), Some(name[i + 1..].to_string())),
+        _ => (name.clone(), None),
+    };
+    if options.strip_copies {
+        let copy_suffix = Regex::new(COPY_SUFFIX).unwrap();
+        let mut stripped = stem.clone();
+        while let Some(x) = copy_suffix.find(&stripped) {
+            stripped.truncate(x.start());
+        }
+        if !stripped.trim().is_empty() {
+            stem = stripped;
+        }
+    }
+    if let Some(x) = options.spaces {
+        let words: Vec<&str> = stem.split_whitespace().collect();
+        stem = words.join(&x.to_string());
+    }
+    if options.lowercase_extension {
+        extension = extension.map(|x| x.to_lowercase());
+    }
+    if let Some(x) = options.max_length {
+        let reserved = extension.as_ref().map_or(0, |y| y.chars().count() + 1);
+        let kept = x.saturating_sub(reserved).max(1);
+        if stem.chars().count() > kept {
+            let cut: String = stem.chars().take(kept).collect();
+            stem = match cut.trim_end_matches([' ', '_', '-', '.']) {
+                "" => cut,
+                x => x.to_string(),
+            };
+        }
+    }
+    match extension {
+        Some(x) => format!("{}.{}", stem, x),
+        None => stem,
+    }
+}
+
+/// Return the path of a file once its name is normalized, or `None` if its name does not change
+pub fn normalized_path(path: &Path, options: &NormalizeOptions) -> Option<PathBuf> {
+    let name = path.file_name()?.to_string_lossy().to_string();
+    let normalized = normalized_name(&name, options);
+    match normalized == name || normalized.is_empty() {
+        true => None,
+        false => Some(path.with_file_name(normalized)),
+    }
+}
+
+/// Check if a file of the folder of a path already has its name. The names of the folder are compared rather than
+/// checking if the path exists, which is also true for the renamed file itself on a file system ignoring the case
+/// or the Unicode form of the names.
+pub(crate) fn is_taken_in(fs: &dyn Filesystem, path: &Path) -> bool {
+    let dir = match path.parent() {
+        Some(x) if !x.as_os_str().is_empty() => x,
+        _ => Path::new("."),
+    };
+    fs.read_dir(dir)
+        .map(|x| x.iter().any(|y| y.file_name() == path.file_name()))
+        .unwrap_or(false)
+}
@@ -0,0 +1,31 @@
+use crate::components::plan::{Operation, Outcome, RuleReport};
+use crate::components::Rule;
+
+/// An Observer is told what the rules do while they are executed, to render their progress.
+/// All the methods do nothing by default, so an implementation only writes the ones it needs.
+pub trait Observer {
+    /// Called for each rule of a `.crc` file before any rule is executed, even the invalid or scheduled ones
+    fn on_rule_parsed(&mut self, _file: &str, _rule: &Rule) {}
+
+    /// Called before a rule looks for the files it concerns
+    fn on_rule_start(&mut self, _rule: &Rule) {}
+
+    /// Called when a rule of an `in` block finds files it concerns in one of the folders of its block
+    fn on_rule_scoped(&mut self, _rule: &Rule, _folder: &str) {}
+
+    /// Called for each file a rule concerns
+    fn on_file_matched(&mut self, _rule: &Rule, _file: &str) {}
+
+    /// Called for each file following the pattern of a rule that the rule does not touch because it is protected,
+    /// or because another file already has its new name
+    fn on_file_skipped(&mut self, _rule: &Rule, _file: &str, _reason: &str) {}
+
+    /// Called once the outcome of an operation is known
+    fn on_operation(&mut self, _operation: &Operation, _outcome: &Outcome) {}
+
+    /// Called when a rule cannot plan its operations, such as an invalid rule
+    fn on_error(&mut self, _rule: &Rule, _error: &str) {}
+
+    /// Called when a rule was executed, with what it did
+    fn on_finish(&mut self, _report: &RuleReport) {}
+}
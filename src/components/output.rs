@@ -0,0 +1,462 @@
+use crate::components::check::Diagnostic;
+use crate::components::control::Response;
+use crate::components::explain::RuleExplanation;
+use crate::components::observer::Observer;
+use crate::components::plan::{Operation, Outcome, RuleReport};
+use crate::components::Rule;
+use serde::Serialize;
+use serde_json::Value;
+use std::io::{self, Write};
+
+/// Version of the fields of the events, increased when a field is renamed or removed
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// How the events of a command are written
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum OutputFormat {
+    /// Sentences for humans
+    Text,
+    /// One JSON document with all the events, written when the command ends
+    Json,
+    /// One JSON object per line, written as soon as the event happens
+    Ndjson,
+}
+
+impl OutputFormat {
+    /// Return the `OutputFormat` matching the value of the `--output` argument
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "text" => Some(Self::Text),
+            "json" => Some(Self::Json),
+            "ndjson" => Some(Self::Ndjson),
+            _ => None,
+        }
+    }
+}
+
+/// Something that happened while running a command
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(tag = "event", rename_all = "kebab-case")]
+pub enum Event {
+    RuleParsed {
+        file: String,
+        line: usize,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        name: Option<String>,
+        #[serde(skip_serializing_if = "Vec::is_empty")]
+        tags: Vec<String>,
+        rule: String,
+        valid: bool,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        error: Option<String>,
+    },
+    FileMatched {
+        line: usize,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        name: Option<String>,
+        file: String,
+    },
+    /// A rule of an `in` block running in one of the folders of its block
+    RuleScoped {
+        line: usize,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        name: Option<String>,
+        rule: String,
+        folder: String,
+    },
+    /// A file following the pattern of a rule that the rule does not touch because it is protected, or because
+    /// another file already has its new name
+    FileSkipped {
+        line: usize,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        name: Option<String>,
+        file: String,
+        reason: String,
+    },
+    /// The operations carry the name of their rule, when it has one
+    OperationPlanned {
+        #[serde(flatten)]
+        operation: Operation,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        name: Option<String>,
+    },
+    OperationApplied {
+        #[serde(flatten)]
+        operation: Operation,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        name: Option<String>,
+    },
+    OperationFailed {
+        #[serde(flatten)]
+        operation: Operation,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        name: Option<String>,
+        error: String,
+    },
+    RuleFinished {
+        line: usize,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        name: Option<String>,
+        rule: String,
+        succeeded: bool,
+        /// Number of operations planned, in a dry run
+        planned: usize,
+        /// Number of operations applied, always 0 in a dry run
+        applied: usize,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        error: Option<String>,
+    },
+    DuplicateGroup {
+        size: u64,
+        files: Vec<String>,
+    },
+    /// A message of a command that has no specific event, such as `watch` or `service`
+    Message {
+        level: String,
+        text: String,
+    },
+    /// The answer of a running daemon to a `ctl` request
+    Response {
+        #[serde(flatten)]
+        response: Response,
+    },
+    /// A problem found in a `.crc` file by the `check` command
+    Diagnostic {
+        #[serde(flatten)]
+        diagnostic: Diagnostic,
+    },
+    /// What a rule would do to the file given to the `explain` command
+    RuleExplained {
+        #[serde(flatten)]
+        explanation: RuleExplanation,
+    },
+    Summary {
+        rules: usize,
+        planned: usize,
+        applied: usize,
+        failed: usize,
+    },
+}
+
+/// Write the events of a command in an `OutputFormat`, and count the operations for the summary
+#[derive(Debug)]
+pub struct Output {
+    format: OutputFormat,
+    buffer: Option<Vec<u8>>,
+    events: Vec<Value>,
+    rules: usize,
+    planned: usize,
+    applied: usize,
+    failed: usize,
+    /// The sentence of the running rule, written once for its planned operations in the text format
+    description: String,
+    /// The name of the running rule, given to the events of its operations
+    name: Option<String>,
+}
+
+impl Output {
+    /// Create a new `Output` writing on the standard output
+    pub fn new(format: OutputFormat) -> Self {
+        Self {
+            format,
+            buffer: None,
+            events: vec![],
+            rules: 0,
+            planned: 0,
+            applied: 0,
+            failed: 0,
+            description: String::new(),
+            name: None,
+        }
+    }
+
+    /// Create a new `Output` writing in memory, to read what was written with `written`
+    pub fn buffered(format: OutputFormat) -> Self {
+        Self {
+            buffer: Some(vec![]),
+            ..Self::new(format)
+        }
+    }
+
+    /// Return the format of the current `Output`
+    pub fn get_format(&self) -> OutputFormat {
+        self.format
+    }
+
+    /// Return what was written by a buffered `Output`
+    pub fn written(&self) -> String {
+        match &self.buffer {
+            Some(x) => String::from_utf8_lossy(x).to_string(),
+            None => String::new(),
+        }
+    }
+
+    /// Write an event, or keep it until `finish` for the JSON format
+    pub fn emit(&mut self, event: Event) {
+        match &event {
+            Event::OperationPlanned { .. } => self.planned += 1,
+            Event::OperationApplied { .. } => self.applied += 1,
+            Event::OperationFailed { .. } => self.failed += 1,
+            Event::RuleFinished { .. } => self.rules += 1,
+            _ => {}
+        }
+        match self.format {
+            OutputFormat::Text => {
+                let text = match &event {
+                    Event::RuleFinished { line, planned, .. } if *planned > 0 => {
+                        Some(format!("[dry-run][line {}] {}", line, self.description))
+                    }
+                    x => Self::text(x),
+                };
+                if let Some(line) = text {
+                    self.write_line(&line);
+                }
+            }
+            OutputFormat::Json => self.events.push(Self::value(&event)),
+            OutputFormat::Ndjson => {
+                let line = Self::value(&event).to_string();
+                self.write_line(&line);
+            }
+        }
+    }
+
+    /// Write a message of a command
+    pub fn message(&mut self, level: &str, text: String) {
+        self.emit(Event::Message {
+            level: level.to_string(),
+            text,
+        });
+    }
+
+    /// Write the summary of the operations, then the JSON document for the JSON format
+    pub fn finish(&mut self) {
+        self.emit(Event::Summary {
+            rules: self.rules,
+            planned: self.planned,
+            applied: self.applied,
+            failed: self.failed,
+        });
+        if self.format == OutputFormat::Json {
+            let document = serde_json::json!({
+                "schema_version": SCHEMA_VERSION,
+                "events": self.events,
+            });
+            self.write_line(&document.to_string());
+            self.events.clear();
+        }
+    }
+
+    /// Return the JSON object of an event, with the schema version
+    fn value(event: &Event) -> Value {
+        let mut value = serde_json::to_value(event).unwrap_or(Value::Null);
+        if let Value::Object(x) = &mut value {
+            x.insert(String::from("schema_version"), Value::from(SCHEMA_VERSION));
+        }
+        value
+    }
+
+    /// Return how the text format refers to a rule, by its name when it has one
+    fn rule_label(line: usize, name: &Option<String>) -> String {
+        match name {
+            Some(x) => format!("rule `{}`", x),
+            None => format!("rule line {}", line),
+        }
+    }
+
+    /// Return the sentence of an event for the text format, if it has one
+    fn text(event: &Event) -> Option<String> {
+        match event {
+            Event::RuleScoped {
+                line, name, folder, ..
+            } => Some(format!(
+                "scope: {} runs in `{}`.",
+                Self::rule_label(*line, name),
+                folder
+            )),
+            Event::FileSkipped {
+                line,
+                name,
+                file,
+                reason,
+            } => Some(format!(
+                "skipped: {} does not touch `{}` ({}).",
+                Self::rule_label(*line, name),
+                file,
+                reason
+            )),
+            Event::OperationFailed {
+                operation,
+                name,
+                error,
+            } => Some(format!(
+                "error: {} could not {}: {}.",
+                Self::rule_label(operation.line, name),
+                operation.describe(),
+                error
+            )),
+            Event::RuleFinished {
+                line,
+                name,
+                error: Some(error),
+                ..
+            } => Some(format!(
+                "error: {}: {}",
+                Self::rule_label(*line, name),
+                error
+            )),
+            Event::RuleFinished {
+                line,
+                name,
+                rule,
+                succeeded: true,
+                applied,
+                ..
+            } if *applied > 0 => Some(format!(
+                "success: {} - {}",
+                Self::rule_label(*line, name),
+                rule
+            )),
+            Event::DuplicateGroup { size, files } => {
+                let mut text = format!("duplicates: {} files of {} bytes", files.len(), size);
+                for file in files {
+                    text.push_str(&format!("\n    {}", file));
+                }
+                Some(text)
+            }
+            Event::Diagnostic { diagnostic } => Some(format!("{}\n", diagnostic.render())),
+            Event::RuleExplained { explanation } => {
+                let location = match explanation.config.as_str() {
+                    "" => format!("line {}", explanation.line),
+                    x => format!("{}:{}", x, explanation.line),
+                };
+                let scope = match &explanation.scope {
+                    Some(x) if x.recursive => format!(" (in `{}` recursive)", x.dir),
+                    Some(x) => format!(" (in `{}`)", x.dir),
+                    None => String::new(),
+                };
+                let mut text = format!(
+                    "[{}] {}{}\n    {}",
+                    location,
+                    explanation.rule,
+                    scope,
+                    explanation.decision.describe()
+                );
+                for operation in &explanation.operations {
+                    text.push_str(&format!("\n    would {}", operation.describe()));
+                }
+                Some(text)
+            }
+            Event::Message { level, text } => match level.as_str() {
+                "error" => Some(format!("error: {}.", text)),
+                _ => Some(text.clone()),
+            },
+            Event::Summary {
+                rules: 0,
+                planned: 0,
+                applied: 0,
+                failed: 0,
+            } => None,
+            Event::Summary {
+                planned,
+                applied,
+                failed,
+                ..
+            } => match planned {
+                0 => Some(format!(
+                    "summary: {} operation(s) applied, {} failed.",
+                    applied, failed
+                )),
+                _ => Some(format!("summary: {} operation(s) planned.", planned)),
+            },
+            _ => None,
+        }
+    }
+
+    /// Write a line on the standard output or in the buffer
+    fn write_line(&mut self, line: &str) {
+        match &mut self.buffer {
+            Some(x) => {
+                let _ = writeln!(x, "{}", line);
+            }
+            None => {
+                let _ = writeln!(io::stdout(), "{}", line);
+            }
+        }
+    }
+}
+
+/// The console output, or the JSON events, of the rules executed
+impl Observer for Output {
+    fn on_rule_start(&mut self, rule: &Rule) {
+        self.description = rule.describe();
+        self.name = rule.get_name().clone();
+    }
+
+    fn on_rule_parsed(&mut self, file: &str, rule: &Rule) {
+        self.emit(Event::RuleParsed {
+            file: file.to_string(),
+            line: *rule.get_line_number(),
+            name: rule.get_name().clone(),
+            tags: rule.get_tags().clone(),
+            rule: rule.get_line_value().clone(),
+            valid: *rule.is_valid(),
+            error: match rule.get_error_message().as_str() {
+                "" => None,
+                x => Some(x.to_string()),
+            },
+        });
+    }
+
+    fn on_file_matched(&mut self, rule: &Rule, file: &str) {
+        self.emit(Event::FileMatched {
+            line: *rule.get_line_number(),
+            name: rule.get_name().clone(),
+            file: file.to_string(),
+        });
+    }
+
+    fn on_rule_scoped(&mut self, rule: &Rule, folder: &str) {
+        self.emit(Event::RuleScoped {
+            line: *rule.get_line_number(),
+            name: rule.get_name().clone(),
+            rule: rule.get_line_value().clone(),
+            folder: folder.to_string(),
+        });
+    }
+
+    fn on_file_skipped(&mut self, rule: &Rule, file: &str, reason: &str) {
+        self.emit(Event::FileSkipped {
+            line: *rule.get_line_number(),
+            name: rule.get_name().clone(),
+            file: file.to_string(),
+            reason: reason.to_string(),
+        });
+    }
+
+    fn on_operation(&mut self, operation: &Operation, outcome: &Outcome) {
+        let (operation, name) = (operation.clone(), self.name.clone());
+        match outcome {
+            Outcome::Planned => self.emit(Event::OperationPlanned { operation, name }),
+            Outcome::Applied => self.emit(Event::OperationApplied { operation, name }),
+            Outcome::Failed(error) => self.emit(Event::OperationFailed {
+                operation,
+                name,
+                error: error.clone(),
+            }),
+            Outcome::Declined => {}
+        }
+    }
+
+    /// The error of a rule is written with the `rule-finished` event
+    fn on_finish(&mut self, report: &RuleReport) {
+        self.emit(Event::RuleFinished {
+            line: report.line,
+            name: report.name.clone(),
+            rule: report.rule.clone(),
+            succeeded: report.succeeded(),
+            planned: report.count(&Outcome::Planned),
+            applied: report.count(&Outcome::Applied),
+            error: report.error.clone(),
+        });
+    }
+}
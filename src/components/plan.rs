@@ -0,0 +1,91 @@
+use serde::Serialize;
+
+/// What an `Operation` does to its source file
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Action {
+    Move,
+    Copy,
+    Prefix,
+    Suffix,
+    /// A new name for the file in its folder, such as its normalized name
+    Rename,
+    Archive,
+    Extract,
+    RemoveDuplicate,
+}
+
+/// A change a `Rule` makes to one file
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct Operation {
+    /// Line of the rule that planned the operation
+    pub line: usize,
+    pub action: Action,
+    pub source: String,
+    /// The new path of the file, the archive, the extraction folder or the kept duplicate
+    pub destination: String,
+}
+
+impl Operation {
+    /// Return a sentence describing the operation, such as "move `a.pdf` to `Docs/a.pdf`"
+    pub fn describe(&self) -> String {
+        match self.action {
+            Action::Move => format!("move `{}` to `{}`", self.source, self.destination),
+            Action::Copy => format!("copy `{}` to `{}`", self.source, self.destination),
+            Action::Prefix | Action::Suffix | Action::Rename => {
+                format!("rename `{}` to `{}`", self.source, self.destination)
+            }
+            Action::Archive => format!("archive `{}` into `{}`", self.source, self.destination),
+            Action::Extract => format!("extract `{}` to `{}`", self.source, self.destination),
+            Action::RemoveDuplicate => format!(
+                "remove `{}`, duplicate of `{}`",
+                self.source, self.destination
+            ),
+        }
+    }
+}
+
+/// What happened to a planned `Operation`
+#[derive(Clone, Debug, PartialEq)]
+pub enum Outcome {
+    /// The operation was only planned, in a dry run
+    Planned,
+    Applied,
+    Failed(String),
+    /// The user refused the operation in interactive mode
+    Declined,
+}
+
+/// What the execution of a `Rule` did
+#[derive(Clone, Debug, PartialEq)]
+pub struct RuleReport {
+    pub line: usize,
+    /// The name of the rule, from its `@name(...)` annotation
+    pub name: Option<String>,
+    pub rule: String,
+    /// Why the rule could not plan any operation, such as an invalid rule
+    pub error: Option<String>,
+    pub operations: Vec<(Operation, Outcome)>,
+}
+
+impl RuleReport {
+    /// Return false if the rule could not plan its operations, or if one of them failed
+    pub fn succeeded(&self) -> bool {
+        self.error.is_none()
+            && !self
+                .operations
+                .iter()
+                .any(|x| matches!(x.1, Outcome::Failed(_)))
+    }
+
+    /// Return the number of operations with an outcome
+    pub fn count(&self, outcome: &Outcome) -> usize {
+        self.operations
+            .iter()
+            .filter(|x| match (&x.1, outcome) {
+                (Outcome::Failed(_), Outcome::Failed(_)) => true,
+                (x, y) => x == y,
+            })
+            .count()
+    }
+}
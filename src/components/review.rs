@@ -0,0 +1,224 @@
+use crate::components::observer::Observer;
+use crate::components::plan::{Action, Operation, Outcome, RuleReport};
+use crate::components::{Config, Rule};
+use std::path::Path;
+
+/// An operation of a `Review`, and whether the user kept it
+#[derive(Clone, Debug, PartialEq)]
+pub struct ReviewItem {
+    pub operation: Operation,
+    pub selected: bool,
+}
+
+/// The operations planned by one rule
+#[derive(Clone, Debug)]
+pub struct ReviewGroup {
+    pub rule: Rule,
+    /// Why the rule could not plan any operation, such as an invalid rule
+    pub error: Option<String>,
+    pub items: Vec<ReviewItem>,
+}
+
+/// Which operations of a `Review` are shown
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ReviewFilter {
+    /// Line of the only rule shown
+    pub line: Option<usize>,
+    /// Extension of the only source files shown, without its dot
+    pub extension: Option<String>,
+}
+
+/// The plan of the rules of some configurations on a folder, that the user selects and edits before applying it
+#[derive(Clone, Debug)]
+pub struct Review {
+    groups: Vec<ReviewGroup>,
+    filter: ReviewFilter,
+}
+
+impl Review {
+    /// Plan the rules of some configurations on the files of a folder, except the scheduled rules, following their
+    /// evaluation mode. All the operations are selected.
+    pub fn plan(configurations: &[Config], dir: &str) -> Self {
+        let mut groups: Vec<ReviewGroup> = vec![];
+        for config in configurations {
+            config.evaluate(dir, None, &mut |rule, _, candidates| {
+                let group = Self::plan_rule(rule.clone(), &candidates);
+                // A rule of a recursive block only gets a group in the folders where it has something to do
+                if rule.get_scope().is_some() && group.items.is_empty() && *rule.is_valid() {
+                    return vec![];
+                }
+                let operations = group.items.iter().map(|x| x.operation.clone()).collect();
+                groups.push(group);
+                operations
+            });
+        }
+        Self {
+            groups,
+            filter: ReviewFilter::default(),
+        }
+    }
+
+    /// Plan a rule on the files it concerns among some candidates
+    fn plan_rule(rule: Rule, candidates: &[String]) -> ReviewGroup {
+        let planned = match (*rule.is_valid(), rule.get_error_message().is_empty()) {
+            (true, true) => {
+                let files: Vec<String> = candidates
+                    .iter()
+                    .filter(|x| rule.concerns(x))
+                    .cloned()
+                    .collect();
+                match files.is_empty() {
+                    true => Ok(vec![]),
+                    false => rule.plan(&files),
+                }
+            }
+            _ => Err(rule.get_error_message().clone()),
+        };
+        let (items, error) = match planned {
+            Ok(operations) => (
+                operations
+                    .into_iter()
+                    .map(|operation| ReviewItem {
+                        operation,
+                        selected: true,
+                    })
+                    .collect(),
+                None,
+            ),
+            Err(e) => (vec![], Some(e)),
+        };
+        ReviewGroup { rule, error, items }
+    }
+
+    /// Return the planned operations, grouped by rule
+    pub fn get_groups(&self) -> &Vec<ReviewGroup> {
+        &self.groups
+    }
+
+    pub fn get_filter(&self) -> &ReviewFilter {
+        &self.filter
+    }
+
+    pub fn set_filter(&mut self, filter: ReviewFilter) {
+        self.filter = filter;
+    }
+
+    /// Check if an operation of a rule is shown by the current filter
+    fn is_visible(&self, group: &ReviewGroup, item: &ReviewItem) -> bool {
+        let extension = Path::new(&item.operation.source)
+            .extension()
+            .map(|x| x.to_string_lossy().to_lowercase());
+        let line = *group.rule.get_line_number();
+        self.filter.line.iter().all(|x| *x == line)
+            && self
+                .filter
+                .extension
+                .iter()
+                .all(|x| Some(x.to_lowercase()) == extension)
+    }
+
+    /// Return the `(group, item)` indexes of the operations shown by the current filter, in order
+    pub fn visible(&self) -> Vec<(usize, usize)> {
+        let mut res: Vec<(usize, usize)> = vec![];
+        for (i, group) in self.groups.iter().enumerate() {
+            for (j, item) in group.items.iter().enumerate() {
+                if self.is_visible(group, item) {
+                    res.push((i, j));
+                }
+            }
+        }
+        res
+    }
+
+    /// Return the lines of the rules that planned operations, to filter by rule
+    pub fn lines(&self) -> Vec<usize> {
+        self.groups
+            .iter()
+            .filter(|x| !x.items.is_empty())
+            .map(|x| *x.rule.get_line_number())
+            .collect()
+    }
+
+    /// Select an operation if it is not, or unselect it
+    pub fn toggle(&mut self, group: usize, item: usize) {
+        let item = &mut self.groups[group].items[item];
+        item.selected = !item.selected;
+    }
+
+    /// Select or unselect all the operations shown by the current filter
+    pub fn select_visible(&mut self, selected: bool) {
+        for (i, j) in self.visible() {
+            self.groups[i].items[j].selected = selected;
+        }
+    }
+
+    /// Change the destination of an operation. The operations of an archive rule share their archive.
+    pub fn set_destination(
+        &mut self,
+        group: usize,
+        item: usize,
+        destination: &str,
+    ) -> Result<(), String> {
+        let operation = &mut self.groups[group].items[item].operation;
+        match operation.action {
+            Action::Archive => Err(String::from(
+                "The files of an archive rule share their destination.",
+            )),
+            _ if destination.trim().is_empty() => Err(String::from("The destination is empty.")),
+            _ => {
+                operation.destination = destination.trim().to_string();
+                Ok(())
+            }
+        }
+    }
+
+    /// Return the number of selected operations
+    pub fn selected(&self) -> usize {
+        self.groups
+            .iter()
+            .flat_map(|x| x.items.iter())
+            .filter(|x| x.selected)
+            .count()
+    }
+
+    /// Apply the selected operations, even the hidden ones, telling an `Observer` what they do.
+    /// The unselected operations are declined, and nothing is changed in a dry run.
+    pub fn apply(&self, dry_run: bool, observer: &mut dyn Observer) -> Vec<RuleReport> {
+        let mut reports: Vec<RuleReport> = vec![];
+        for group in &self.groups {
+            observer.on_rule_start(&group.rule);
+            let mut report = RuleReport {
+                line: *group.rule.get_line_number(),
+                name: group.rule.get_name().clone(),
+                rule: group.rule.get_line_value().clone(),
+                error: group.error.clone(),
+                operations: vec![],
+            };
+            if let Some(e) = &group.error {
+                observer.on_error(&group.rule, e);
+            }
+            let (selected, declined): (Vec<&ReviewItem>, Vec<&ReviewItem>) =
+                group.items.iter().partition(|x| x.selected);
+            let selected: Vec<Operation> = selected.iter().map(|x| x.operation.clone()).collect();
+            let outcomes = match dry_run {
+                true => {
+                    for operation in &selected {
+                        observer.on_operation(operation, &Outcome::Planned);
+                    }
+                    vec![Outcome::Planned; selected.len()]
+                }
+                false => group.rule.apply(&selected, observer),
+            };
+            report.operations = selected.into_iter().zip(outcomes).collect();
+            for item in declined {
+                observer.on_operation(&item.operation, &Outcome::Declined);
+                report
+                    .operations
+                    .push((item.operation.clone(), Outcome::Declined));
+            }
+            observer.on_finish(&report);
+            reports.push(report);
+        }
+        reports
+    }
+}
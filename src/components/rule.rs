@@ -1,23 +1,68 @@
+use crate::components::archive::{self, ArchiveFormat, ArchiveOptions, ExtractOptions};
+use crate::components::confirm::{Confirmation, TerminalConfirmer};
+use crate::components::copy::{self, ConflictPolicy, CopyOptions, CopyOutcome, LinkMode};
+use crate::components::dedupe::{self, DedupeOptions, KeepPolicy};
+use crate::components::evaluation::{Flow, Scope};
+use crate::components::explain::Decision;
+use crate::components::filesystem::{Filesystem, StdFs};
+use crate::components::guard;
+use crate::components::matching::Matching;
+use crate::components::normalize::{self, NormalizeOptions, UnicodeForm};
+use crate::components::observer::Observer;
+use crate::components::output::{Output, OutputFormat};
+use crate::components::plan::{Action, Operation, Outcome, RuleReport};
+use crate::components::schedule::{self, Schedule};
 use crate::App;
+use chrono::Local;
 use regex::Regex;
-use std::fs;
-use std::fs::File;
-use std::io::{stdin, stdout, Write};
+use std::collections::HashMap;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, LazyLock};
+use std::time::{Duration, SystemTime};
 
-const FIRST_VALID_TOKENS: [&str; 3] = ["mv", "pre", "suf"];
-const SECOND_VALID_TOKENS: [&str; 2] = ["to", "with"];
+pub(crate) const FIRST_VALID_TOKENS: [&str; 7] =
+    ["mv", "pre", "suf", "cp", "zip", "tar.gz", "extract"];
+pub(crate) const SECOND_VALID_TOKENS: [&str; 3] = ["to", "with", "into"];
 
-const TOKEN_PAIRS_INDEXES: [[i8; 2]; 3] = [
-    [0, 0], // mv  _ to   _
-    [1, 1], // pre _ with _
-    [2, 1], // suf _ with _
+/// Keywords that are only followed by a pattern
+pub(crate) const UNARY_VALID_TOKENS: [&str; 2] = ["dedupe", "normalize"];
+
+pub(crate) const TOKEN_PAIRS_INDEXES: [[i8; 2]; 7] = [
+    [0, 0], // mv      _ to   _
+    [1, 1], // pre     _ with _
+    [2, 1], // suf     _ with _
+    [3, 0], // cp      _ to   _
+    [4, 2], // zip     _ into _
+    [5, 2], // tar.gz  _ into _
+    [6, 0], // extract _ to   _
 ];
 
+/// An `@name(...)` or `@tag(...)` annotation, compiled once
+static ANNOTATION: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^@(name|tag)\(([A-Za-z0-9_.-]+)\)$").unwrap());
+
+/// The options that all rules accept, and the options left for the verb of the rule
+#[derive(Default)]
+struct CommonOptions<'a> {
+    exceptions: Vec<String>,
+    allow_protected: bool,
+    schedule: Option<Schedule>,
+    older_than: Option<Duration>,
+    flow: Option<Flow>,
+    matching: Matching,
+    others: Vec<&'a str>,
+}
+
 /// A Rule is a line that was parsed and verify to work.
 /// It can execute the following tasks :
 ///     Move multiple files to a destination path;
 ///     Add a prefix to multiple files;
-///     Add a suffix to multiple files.
+///     Add a suffix to multiple files;
+///     Copy multiple files to a destination path;
+///     Archive multiple files into a zip or tar.gz file;
+///     Extract multiple archives to a destination path;
+///     Remove the duplicates among multiple files.
 /// All these actions are determined by the Configuration structure and implementation
 #[derive(Clone, Debug)]
 pub struct Rule {
@@ -30,9 +75,29 @@ pub struct Rule {
     first_pattern: String,
     second_keyword: String,
     second_pattern: String,
+    copy_options: CopyOptions,
+    archive_options: ArchiveOptions,
+    extract_options: ExtractOptions,
+    dedupe_options: DedupeOptions,
+    normalize_options: NormalizeOptions,
+    exceptions: Vec<String>,
+    ignored: Vec<String>,
+    allow_protected: bool,
+    schedule: Option<Schedule>,
+    older_than: Option<Duration>,
+    flow: Option<Flow>,
+    matching: Matching,
+    /// The regexes of the first pattern, the exceptions and the ignored patterns, compiled with `matching`
+    regexes: HashMap<String, Regex>,
+    scope: Option<Scope>,
+    name: Option<String>,
+    tags: Vec<String>,
+    config_file: String,
+    /// The `.crc` files of the configuration of the rule, that it does not touch
+    config_files: Vec<String>,
+    filesystem: Arc<dyn Filesystem>,
 }
 impl Rule {
-
     /// Return the line number, where the current `Rule` was defined
     pub fn get_line_number(&self) -> &usize {
         &self.line_number
@@ -68,6 +133,142 @@ impl Rule {
         &self.error_message
     }
 
+    /// Return the options used to copy files in a `mv` or `cp` rule
+    pub fn get_copy_options(&self) -> &CopyOptions {
+        &self.copy_options
+    }
+
+    /// Return the options used to create the archive of a `zip` or `tar.gz` rule
+    pub fn get_archive_options(&self) -> &ArchiveOptions {
+        &self.archive_options
+    }
+
+    /// Return the options used to extract the archives of an `extract` rule
+    pub fn get_extract_options(&self) -> &ExtractOptions {
+        &self.extract_options
+    }
+
+    /// Return the options used to remove the duplicates of a `dedupe` rule
+    pub fn get_dedupe_options(&self) -> &DedupeOptions {
+        &self.dedupe_options
+    }
+
+    /// Return the patterns of the files the current `Rule` must not touch, from its `except` clauses
+    pub fn get_exceptions(&self) -> &Vec<String> {
+        &self.exceptions
+    }
+
+    /// Return a boolean defining if the current `Rule` can move or delete protected paths
+    pub fn allows_protected(&self) -> &bool {
+        &self.allow_protected
+    }
+
+    /// Return the schedule of the current `Rule`, from its `every` and `at` clauses
+    pub fn get_schedule(&self) -> &Option<Schedule> {
+        &self.schedule
+    }
+
+    /// Set the schedule of the current `Rule`, `None` running it with the other rules
+    pub fn set_schedule(&mut self, schedule: Option<Schedule>) {
+        self.schedule = schedule;
+    }
+
+    /// Return the minimum age of the files concerned by the current `Rule`, from its `older-than` clause
+    pub fn get_older_than(&self) -> &Option<Duration> {
+        &self.older_than
+    }
+
+    /// Return what happens to the files the current `Rule` claims, from its `stop` or `continue` option
+    pub fn get_flow(&self) -> &Option<Flow> {
+        &self.flow
+    }
+
+    /// Return the options used to rename files in a `normalize` rule
+    pub fn get_normalize_options(&self) -> &NormalizeOptions {
+        &self.normalize_options
+    }
+
+    /// Return how the patterns of the current `Rule` are compared to the file names
+    pub fn get_matching(&self) -> &Matching {
+        &self.matching
+    }
+
+    /// Set how the patterns of the current `Rule` are compared to the file names
+    pub fn set_matching(&mut self, matching: Matching) {
+        self.matching = matching;
+        self.compile_patterns();
+    }
+
+    /// Return the folder the current `Rule` runs in, from its block, instead of the folder to clean
+    pub fn get_scope(&self) -> &Option<Scope> {
+        &self.scope
+    }
+
+    /// Set the folder the current `Rule` runs in, from its block
+    pub fn set_scope(&mut self, scope: Option<Scope>) {
+        self.scope = scope;
+    }
+
+    /// Return the name of the current `Rule`, from its `@name(...)` annotation
+    pub fn get_name(&self) -> &Option<String> {
+        &self.name
+    }
+
+    /// Return the tags of the current `Rule`, from its `@tag(...)` annotations
+    pub fn get_tags(&self) -> &Vec<String> {
+        &self.tags
+    }
+
+    /// Check if the current `Rule` has a name or a tag, as given to `--only` and `--skip`
+    pub fn is_labeled(&self, label: &str) -> bool {
+        self.name.as_deref() == Some(label) || self.tags.iter().any(|x| x == label)
+    }
+
+    /// Set the patterns of the files ignored by the whole configuration of the current `Rule`
+    pub fn set_ignored(&mut self, ignored: Vec<String>) {
+        self.ignored = ignored;
+        self.compile_patterns();
+    }
+
+    /// Compile the regexes of the patterns of the current `Rule`, once its patterns or its `Matching` changed. The
+    /// invalid patterns are left out, so they match no file.
+    fn compile_patterns(&mut self) {
+        let patterns = std::iter::once(&self.first_pattern)
+            .chain(self.exceptions.iter())
+            .chain(self.ignored.iter());
+        self.regexes = patterns
+            .filter_map(|x| Some((x.clone(), self.matching.regex(x).ok()?)))
+            .collect();
+    }
+
+    /// Check if a file name follows one of the patterns of the current `Rule`
+    fn is_match(&self, pattern: &str, name: &str) -> bool {
+        self.regexes
+            .get(pattern)
+            .is_some_and(|x| self.matching.matches(x, name))
+    }
+
+    /// Return the path of the `.crc` file the current `Rule` was defined in, empty when it was read from memory
+    pub fn get_config_file(&self) -> &String {
+        &self.config_file
+    }
+
+    /// Set the path of the `.crc` file the current `Rule` was defined in
+    pub fn set_config_file(&mut self, config_file: String) {
+        self.config_file = config_file;
+    }
+
+    /// Set the paths of the `.crc` files of the configuration of the current `Rule`, the file it was defined in and
+    /// the ones it includes or is included by, that the rule does not touch
+    pub fn set_config_files(&mut self, config_files: Vec<String>) {
+        self.config_files = config_files;
+    }
+
+    /// Set the `Filesystem` the current `Rule` reads and changes files in, the disk by default
+    pub fn set_filesystem(&mut self, filesystem: Arc<dyn Filesystem>) {
+        self.filesystem = filesystem;
+    }
+
     /// Create a new `Rule`
     pub fn new(
         line_value: String,
@@ -80,7 +281,7 @@ impl Rule {
         second_keyword: String,
         second_pattern: String,
     ) -> Self {
-        Self {
+        let mut rule = Self {
             line_value,
             line_number,
             valid,
@@ -90,289 +291,553 @@ impl Rule {
             first_pattern,
             second_keyword,
             second_pattern,
+            copy_options: CopyOptions::default(),
+            archive_options: ArchiveOptions::default(),
+            extract_options: ExtractOptions::default(),
+            dedupe_options: DedupeOptions::default(),
+            normalize_options: NormalizeOptions::default(),
+            exceptions: vec![],
+            ignored: vec![],
+            allow_protected: false,
+            schedule: None,
+            older_than: None,
+            flow: None,
+            matching: Matching::default(),
+            regexes: HashMap::new(),
+            scope: None,
+            name: None,
+            tags: vec![],
+            config_file: String::new(),
+            config_files: vec![],
+            filesystem: Arc::new(StdFs),
+        };
+        rule.compile_patterns();
+        rule
+    }
+
+    /// Return the path of a file with a prefix added to its name
+    fn prefixed_path(path: &Path, prefix: &str) -> PathBuf {
+        path.with_file_name(format!("{}{}", prefix, Self::file_name(path)))
+    }
+
+    /// Return the path of a file with a suffix added to its name, before its extension
+    fn suffixed_path(path: &Path, suffix: &str) -> PathBuf {
+        let file_name = Self::file_name(path);
+        let new_name = match file_name.rfind('.') {
+            Some(i) if i > 0 => format!("{}{}{}", &file_name[..i], suffix, &file_name[i..]),
+            _ => format!("{}{}", file_name, suffix),
+        };
+        path.with_file_name(new_name)
+    }
+
+    /// Return the name of a file without its folder
+    fn file_name(path: &Path) -> String {
+        match path.file_name() {
+            Some(x) => x.to_string_lossy().to_string(),
+            None => String::new(),
         }
     }
 
-    /// Add a prefix to a file
-    fn prefix(&mut self, file_path: &String, prefix: &String) -> bool {
-        match fs::rename(file_path, format!("{}{}", prefix, file_path)) {
-            Err(_) => return false,
-            Ok(_) => return true,
+    /// Return the action the verb of the current `Rule` runs on each file, or `None` if the verb is unknown
+    pub fn get_action(&self) -> Option<Action> {
+        match self.first_keyword.as_str() {
+            "mv" => Some(Action::Move),
+            "cp" => Some(Action::Copy),
+            "pre" => Some(Action::Prefix),
+            "suf" => Some(Action::Suffix),
+            "normalize" => Some(Action::Rename),
+            "zip" | "tar.gz" => Some(Action::Archive),
+            "extract" => Some(Action::Extract),
+            "dedupe" => Some(Action::RemoveDuplicate),
+            _ => None,
+        }
+    }
+
+    /// Return a sentence describing what the current `Rule` does, as written by a dry run in the text format
+    pub fn describe(&self) -> String {
+        let (first, second) = (&self.first_pattern, &self.second_pattern);
+        match self.get_action() {
+            Some(Action::Move) => format!(
+                "move all files following the pattern `{}` to `{}`",
+                first, second
+            ),
+            Some(Action::Copy) => format!(
+                "copy all files following the pattern `{}` to `{}`",
+                first, second
+            ),
+            Some(Action::Prefix) => format!(
+                "add prefix `{}` to all files following the pattern `{}`",
+                second, first
+            ),
+            Some(Action::Suffix) => format!(
+                "add suffix `{}` to all files following the pattern `{}`",
+                second, first
+            ),
+            Some(Action::Archive) => format!(
+                "archive all files following the pattern `{}` into `{}`",
+                first, second
+            ),
+            Some(Action::Extract) => format!(
+                "extract all archives following the pattern `{}` to `{}`",
+                first, second
+            ),
+            Some(Action::RemoveDuplicate) => format!(
+                "remove the duplicates among all files following the pattern `{}`",
+                first
+            ),
+            Some(Action::Rename) => format!(
+                "normalize the names of all files following the pattern `{}`",
+                first
+            ),
+            None => format!("run `{}`", self.line_value),
+        }
+    }
+
+    /// Return a `Vec<String>` with the names of files concerned by a `Rule`
+    pub fn files_concerned(&mut self, dir: String) -> Vec<String> {
+        Self::files_matching_in(&*self.filesystem, &dir, "*")
+            .into_iter()
+            .filter(|x| self.concerns(x))
+            .collect()
+    }
+
+    /// Check if a file follows the first pattern, without being excluded nor protected
+    pub fn concerns(&self, file: &str) -> bool {
+        self.matches(file) && self.protection(file).is_none()
+    }
+
+    /// Check if a file follows the first pattern, without being excluded by an `except`, `ignore`
+    /// or `older-than` clause
+    fn matches(&self, file: &str) -> bool {
+        let file_name = Self::file_name(Path::new(file));
+        self.is_match(&self.first_pattern, &file_name)
+            && !self
+                .exceptions
+                .iter()
+                .chain(self.ignored.iter())
+                .any(|x| self.is_match(x, &file_name))
+            && self.is_old_enough(file)
+    }
+
+    /// Return which part of the current `Rule` decides if it touches a file, checked in the order the rule is run
+    pub fn decide(&self, file: &str) -> Decision {
+        if !self.valid || !self.error_message.is_empty() {
+            return Decision::Invalid {
+                error: self.error_message.clone(),
+            };
+        }
+        let file_name = Self::file_name(Path::new(file));
+        let matching = |patterns: &[String]| {
+            patterns
+                .iter()
+                .find(|x| self.is_match(x, &file_name))
+                .cloned()
         };
+        if !self.is_match(&self.first_pattern, &file_name) {
+            return Decision::Pattern {
+                pattern: self.first_pattern.clone(),
+            };
+        }
+        if let Some(pattern) = matching(&self.exceptions) {
+            return Decision::Exception { pattern };
+        }
+        if let Some(pattern) = matching(&self.ignored) {
+            return Decision::Ignored { pattern };
+        }
+        if !self.is_old_enough(file) {
+            return Decision::TooRecent {
+                older_than: self.older_than.unwrap_or_default().as_secs(),
+            };
+        }
+        if let Some(reason) = self.protection(file) {
+            return Decision::Protected {
+                reason: reason.to_string(),
+            };
+        }
+        match self.schedule {
+            Some(_) => Decision::Scheduled,
+            None => Decision::Matched,
+        }
     }
 
-    /// Add a suffix to a file
-    fn suffix(&self, file_path: &String, suffix: &String) -> bool {
-        let v: Vec<&str> = file_path.split(".").collect();
-        let file_name: &str = v[0];
-        let extension: &str = v[1];
-        match fs::rename(file_path, format!("{}{}.{}", file_name, suffix, extension)) {
-            Err(_) => return false,
-            Ok(_) => return true,
+    /// Check if a file was modified before the `older-than` clause of the current `Rule`
+    fn is_old_enough(&self, file: &str) -> bool {
+        let older_than = match self.older_than {
+            Some(x) => x,
+            None => return true,
         };
+        match self
+            .filesystem
+            .metadata(Path::new(file))
+            .map(|x| x.modified)
+        {
+            Ok(modified) => match SystemTime::now().duration_since(modified) {
+                Ok(age) => age >= older_than,
+                Err(_) => false,
+            },
+            Err(_) => false,
+        }
     }
 
-    /// Make a copy of a file from the source full path towards the destination full path
-    fn copy_file(src: &String, dest: &String) -> std::io::Result<()> {
-        File::create(dest)?;
-        fs::copy(src, dest)?;
-        Ok(())
+    /// Return why a file is protected against the current `Rule`, or `None` if it is not
+    pub fn protection(&self, file: &str) -> Option<&'static str> {
+        if self.allow_protected || !self.modifies_files() {
+            return None;
+        }
+        let mut config_files = self
+            .config_files
+            .iter()
+            .chain(std::iter::once(&self.config_file))
+            .filter(|x| !x.is_empty());
+        match config_files.find_map(|x| guard::protection(Path::new(file), Some(Path::new(x)))) {
+            Some(x) => Some(x),
+            None => guard::protection(Path::new(file), None),
+        }
     }
 
-    /// Creates a new file in the destination path
-    fn copy_to(&self, file_path: &String, destination: &String) -> bool {
-        let destination_full_path = match file_path.chars().last().unwrap() {
-            '\\' => format!("{}{}", destination, file_path),
-            '/' => format!("{}{}", destination, file_path),
-            _ => format!("{}\\{}", destination, file_path),
-        };
-        match Self::copy_file(&file_path, &destination_full_path) {
-            Ok(_) => return true,
-            Err(_) => return false,
+    /// Return the folder the current `Rule` moves, copies or extracts files to, if it has one
+    pub(crate) fn get_destination_dir(&self) -> Option<&str> {
+        match (self.first_keyword.as_str(), self.second_keyword.as_str()) {
+            ("mv", "to") | ("cp", "to") | ("extract", "to") => Some(&self.second_pattern),
+            _ => None,
         }
     }
 
-    /// Delete origin file from the origin file path
-    fn delete_origin(&self, origin_file_path: &String) -> bool {
-        match fs::remove_file(origin_file_path) {
-            Ok(_) => return true,
-            Err(_) => return false,
+    /// Return true if the current `Rule` moves, renames or deletes the files it concerns
+    pub(crate) fn modifies_files(&self) -> bool {
+        match self.first_keyword.as_str() {
+            "cp" | "extract" => false,
+            "zip" | "tar.gz" => self.archive_options.remove,
+            _ => true,
         }
     }
 
-    /// Return a `Vec<String>` with the names of files concerned by a `Rule`
-    fn files_concerned(&mut self, dir: String) -> Vec<String> {
+    /// Return a `Vec<String>` with the paths of the files of a folder that matches with a pattern
+    pub fn files_matching(dir: &str, pattern: &str) -> Vec<String> {
+        Self::files_matching_in(&StdFs, dir, pattern)
+    }
+
+    /// Return a `Vec<String>` with the paths of the files of a folder of a `Filesystem` that matches with a pattern
+    pub fn files_matching_in(fs: &dyn Filesystem, dir: &str, pattern: &str) -> Vec<String> {
         let mut res: Vec<String> = Vec::new();
-        let paths = fs::read_dir(dir).unwrap();
+        let paths = match fs.read_dir(Path::new(dir)) {
+            Ok(x) => x,
+            Err(_) => return res,
+        };
+        let matching = Matching::default();
+        let regex = match matching.regex(pattern) {
+            Ok(x) => x,
+            Err(_) => return res,
+        };
 
         for path in paths {
-            let tmp0 = path.unwrap().path();
-            let tmp = tmp0.to_str().unwrap().split("\\");
-            for el in tmp {
-                let replaced_pattern =
-                    &self.first_pattern.replace("*", "[\\w,\\s,\\-,\\[,\\]]{0,}");
-                if Regex::new(&format!(r"^{}$", &replaced_pattern))
-                    .unwrap()
-                    .is_match(el)
-                {
-                    &res.push(el.to_string());
-                }
+            if fs.is_file(&path) && matching.matches(&regex, &Self::file_name(&path)) {
+                res.push(path.to_string_lossy().to_string());
             }
         }
         res
     }
 
-    /// Asks the user if he wants to pursue a `Rule` execution
-    fn interactive_mode_question(&self, question: String, file: &str, input: &mut String) -> bool {
-        println!("{}", question);
-        stdout().flush().unwrap();
-        stdin().read_line(input).expect("Failed to read line");
-        if input.to_string().replace("\r", "").replace("\n", "") == String::from("y") {
-            println!("continuing...");
-            true
-        } else if input.to_string().replace("\r", "").replace("\n", "") == String::from("n") {
-            println!("skipping...");
-            false
-        } else {
-            return self.interactive_mode_question(question, file, input);
-        }
-    }
-
-    /// Move the files that matches with the first pattern to the second pattern (PATH)
-    fn mv_to(&mut self, dir: String, dry_run: bool, interactive_mode: bool) -> bool {
-        let mut input: String = String::new();
-        let files: Vec<String> = self.files_concerned(dir);
-        for file in files {
-            if interactive_mode {
-                let _ = stdout().flush();
-                if self.interactive_mode_question(
-                    format!(
-                        "Are you sure you want to move {} to {} ? (y or n)",
-                        &file, &self.second_pattern
-                    ),
-                    &file,
-                    &mut input,
-                ) {
-                    if dry_run {
-                        self.dry_run("mv");
-                        return true;
-                    } else {
-                        return self.copy_to(&file, &self.second_pattern)
-                            && self.delete_origin(&file);
-                    }
-                } else {
-                    continue;
+    /// Return the operations of the current `Rule` on some files, that must be concerned by it
+    pub fn plan(&self, files: &[String]) -> Result<Vec<Operation>, String> {
+        self.plan_skipping(files, &mut |_, _| {})
+    }
+
+    /// Return the operations of the current `Rule` on some files, that must be concerned by it, calling `skip` with
+    /// each file it does not rename and the reason
+    fn plan_skipping(
+        &self,
+        files: &[String],
+        skip: &mut dyn FnMut(&str, &str),
+    ) -> Result<Vec<Operation>, String> {
+        let operation = |action: Action, source: &str, destination: &Path| Operation {
+            line: self.line_number,
+            action,
+            source: source.to_string(),
+            destination: destination.to_string_lossy().to_string(),
+        };
+        let mut operations: Vec<Operation> = vec![];
+        match (self.first_keyword.as_str(), self.second_keyword.as_str()) {
+            ("mv", "to") | ("cp", "to") => {
+                let action = match self.first_keyword.as_str() {
+                    "mv" => Action::Move,
+                    _ => Action::Copy,
+                };
+                for file in files {
+                    let destination =
+                        Path::new(&self.second_pattern).join(Self::file_name(Path::new(file)));
+                    operations.push(operation(action, file, &destination));
                 }
-            } else {
-                if dry_run {
-                    self.dry_run("mv");
-                    return true;
-                } else {
-                    return self.copy_to(&file, &self.second_pattern) && self.delete_origin(&file);
+            }
+            ("pre", "with") | ("suf", "with") | ("normalize", _) => {
+                for file in files {
+                    let path = Path::new(file);
+                    let (action, destination) = match self.first_keyword.as_str() {
+                        "pre" => (
+                            Action::Prefix,
+                            Self::prefixed_path(path, &self.second_pattern),
+                        ),
+                        "suf" => (
+                            Action::Suffix,
+                            Self::suffixed_path(path, &self.second_pattern),
+                        ),
+                        _ => match normalize::normalized_path(path, &self.normalize_options) {
+                            Some(x) => (Action::Rename, x),
+                            None => continue,
+                        },
+                    };
+                    // A file keeps its name when another file already has the new one
+                    if self.is_name_taken(&destination, &operations) {
+                        skip(
+                            file,
+                            &format!("another file is named `{}`", Self::file_name(&destination)),
+                        );
+                        continue;
+                    }
+                    operations.push(operation(action, file, &destination));
                 }
             }
-        }
-        false
-    }
-
-    /// Add a prefix at the beginning of the files that matches with the first pattern
-    fn pre_with(&mut self, dir: String, dry_run: bool, interactive_mode: bool) -> bool {
-        let mut input: String = String::new();
-        let files: Vec<String> = self.files_concerned(dir);
-        for file in files {
-            if interactive_mode {
-                let _ = stdout().flush();
-                if self.interactive_mode_question(
-                    format!(
-                        "Are you sure you want to prefix {} with {} ? (y or n)",
-                        &file, &self.second_pattern
-                    ),
-                    &file,
-                    &mut input,
-                ) {
-                    if dry_run {
-                        self.dry_run("pre");
-                        return true;
-                    } else {
-                        return self.prefix(&file, &self.second_pattern.clone());
+            ("zip", "into") | ("tar.gz", "into") => {
+                let archive =
+                    archive::expand_date_placeholders(&self.second_pattern, &Local::now())?;
+                let canonical = |x: &str| {
+                    self.filesystem
+                        .canonicalize(Path::new(x))
+                        .unwrap_or_else(|_| PathBuf::from(x))
+                };
+                for file in files {
+                    if canonical(file) != canonical(&archive) {
+                        operations.push(operation(Action::Archive, file, Path::new(&archive)));
                     }
-                } else {
-                    continue;
                 }
-            } else {
-                if dry_run {
-                    self.dry_run("pre");
-                    return true;
-                } else {
-                    return self.prefix(&file, &self.second_pattern.clone());
+            }
+            ("extract", "to") => {
+                for file in files {
+                    let destination = match self.second_pattern.as_str() {
+                        "." => Path::new(file)
+                            .parent()
+                            .map(|x| x.to_path_buf())
+                            .unwrap_or_default(),
+                        x => PathBuf::from(x),
+                    };
+                    operations.push(operation(Action::Extract, file, &destination));
                 }
             }
-        }
-        false
-    }
-
-    /// Add a suffix at the end of files that matches with the first pattern
-    fn suf_with(&mut self, dir: String, dry_run: bool, interactive_mode: bool) -> bool {
-        let mut input: String = String::new();
-        let files: Vec<String> = self.files_concerned(dir);
-        for file in files {
-            if interactive_mode {
-                let _ = stdout().flush();
-                if self.interactive_mode_question(
-                    format!(
-                        "Are you sure you want to suffix {} with {} ? (y or n)",
-                        &file, &self.second_pattern
-                    ),
-                    &file,
-                    &mut input,
-                ) {
-                    if dry_run {
-                        self.dry_run("suf");
-                        return true;
-                    } else {
-                        return self.suffix(&file, &self.second_pattern.clone());
+            ("dedupe", _) => {
+                let paths: Vec<PathBuf> = files.iter().map(PathBuf::from).collect();
+                let groups = dedupe::find_duplicates_in(&*self.filesystem, &paths)
+                    .map_err(|e| format!("Could not compare the files: {}.", e))?;
+                for group in groups {
+                    let kept =
+                        dedupe::choose_kept_in(&*self.filesystem, &group, &self.dedupe_options);
+                    for file in group.iter().filter(|x| x != &&kept) {
+                        let file = file.to_string_lossy();
+                        operations.push(operation(Action::RemoveDuplicate, &file, &kept));
                     }
-                } else {
-                    continue;
-                }
-            } else {
-                if dry_run {
-                    self.dry_run("suf");
-                    return true;
-                } else {
-                    return self.suffix(&file, &self.second_pattern.clone());
                 }
             }
+            _ => {
+                return Err(format!(
+                    "Tokens {} and {} are not handled.",
+                    self.first_keyword, self.second_keyword
+                ))
+            }
         }
-        false
+        Ok(operations)
     }
 
-    /// Prints a success message
-    fn success(&self) {
-        println!(
-            "success: rule line {} - {}",
-            self.line_number, self.line_value
-        );
+    /// Check if a file can not be renamed to a path because another file already has its name, in its folder or as
+    /// the destination of an operation planned before
+    fn is_name_taken(&self, destination: &Path, planned: &[Operation]) -> bool {
+        planned
+            .iter()
+            .any(|x| Path::new(&x.destination) == destination)
+            || normalize::is_taken_in(&*self.filesystem, destination)
     }
 
-    /// Prints an error message
-    fn error(&self) {
-        println!("error: rule line {} is invalid.", self.line_number);
+    /// Apply operations planned by the current `Rule`, telling an `Observer` the outcome of each of them.
+    /// The files of an archive rule are all added to the archive at once.
+    pub fn apply(&self, operations: &[Operation], observer: &mut dyn Observer) -> Vec<Outcome> {
+        let archived: Vec<PathBuf> = operations
+            .iter()
+            .filter(|x| x.action == Action::Archive)
+            .map(|x| PathBuf::from(&x.source))
+            .collect();
+        if !archived.is_empty() {
+            let outcome = match ArchiveFormat::from_keyword(&self.first_keyword) {
+                Some(_) if !self.filesystem.is_native() => {
+                    Outcome::Failed(String::from("archives are only supported on the disk"))
+                }
+                Some(format) => match archive::create_archive(
+                    format,
+                    Path::new(&operations[0].destination),
+                    &archived,
+                    &self.archive_options,
+                ) {
+                    Ok(_) => Outcome::Applied,
+                    Err(e) => Outcome::Failed(e.to_string()),
+                },
+                None => Outcome::Failed(String::from("unknown archive format")),
+            };
+            for operation in operations {
+                observer.on_operation(operation, &outcome);
+            }
+            return operations.iter().map(|_| outcome.clone()).collect();
+        }
+        operations
+            .iter()
+            .map(|x| {
+                let outcome = match self.apply_operation(x) {
+                    Ok(_) => Outcome::Applied,
+                    Err(e) => Outcome::Failed(e.to_string()),
+                };
+                observer.on_operation(x, &outcome);
+                outcome
+            })
+            .collect()
     }
 
-    /// Shows a message if the app is launched with the dry-run
-    fn dry_run(&self, action: &str) {
-        match action {
-            "mv" => {
-                println!(
-                    "[dry-run][line {}] move all files following the pattern `{}` to `{}`",
-                    self.line_number, self.first_pattern, self.second_pattern
-                );
+    /// Apply an operation that changes a single file
+    fn apply_operation(&self, operation: &Operation) -> io::Result<()> {
+        let fs = &*self.filesystem;
+        let source = Path::new(&operation.source);
+        let destination = Path::new(&operation.destination);
+        match operation.action {
+            Action::Move => {
+                match copy::copy_file_in(fs, source, destination, &self.copy_options)? {
+                    CopyOutcome::Skipped => Ok(()),
+                    _ => fs.remove_file(source),
+                }
             }
-            "pre" => {
-                println!(
-                    "[dry-run][line {}] add prefix `{}` to all files following the pattern `{}`",
-                    self.line_number, self.second_pattern, self.first_pattern,
-                );
+            Action::Copy => {
+                copy::copy_file_in(fs, source, destination, &self.copy_options).map(|_| ())
             }
-            "suf" => {
-                println!(
-                    "[dry-run][line {}] add suffix `{}` to all files following the pattern `{}`",
-                    self.line_number, self.second_pattern, self.first_pattern,
-                );
+            Action::Prefix | Action::Suffix | Action::Rename => fs.rename(source, destination),
+            Action::Extract if !fs.is_native() => {
+                Err(io::Error::other("archives are only supported on the disk"))
             }
-            _ => panic!(""),
-        }
-    }
-
-    /// Execute the current `Rule`
-    pub fn execute(&mut self, dir: String, dry_run: bool, interactive_mode: bool) {
-        if self.error_message == String::from("") {
-            if self.first_keyword == "mv" && self.second_keyword == "to" {
-                if dry_run && !interactive_mode {
-                    self.dry_run("mv");
-                } else {
-                    if self.mv_to(dir.clone(), dry_run, interactive_mode) && !dry_run {
-                        self.success();
-                    } else {
-                        if !interactive_mode && !dry_run {
-                            self.error();
-                        }
-                    }
-                }
-            } else if self.first_keyword == "pre" && self.second_keyword == "with" {
-                if dry_run && !interactive_mode {
-                    self.dry_run("pre");
-                } else {
-                    if self.pre_with(dir.clone(), dry_run, interactive_mode) && !dry_run {
-                        self.success();
-                    } else {
-                        if !interactive_mode && !dry_run {
-                            self.error();
-                        }
-                    }
+            Action::Extract => {
+                archive::extract_archive(source, destination, &self.extract_options).map(|_| ())
+            }
+            Action::RemoveDuplicate => {
+                dedupe::remove_duplicate_in(fs, source, destination, &self.dedupe_options)
+            }
+            Action::Archive => Ok(()),
+        }
+    }
+
+    /// Execute the current `Rule` on the files of a folder, writing its events on the standard output
+    pub fn execute(&mut self, dir: String, dry_run: bool, interactive_mode: bool) -> RuleReport {
+        let mut output = Output::new(OutputFormat::Text);
+        let mut terminal = TerminalConfirmer::new();
+        let mut confirmation = match interactive_mode {
+            true => Confirmation::new(&mut terminal),
+            false => Confirmation::none(),
+        };
+        self.execute_with(dir, dry_run, &mut confirmation, &mut output)
+    }
+
+    /// Execute the current `Rule` on the files of a folder, telling an `Observer` what it does
+    pub fn execute_with(
+        &mut self,
+        dir: String,
+        dry_run: bool,
+        confirmation: &mut Confirmation,
+        observer: &mut dyn Observer,
+    ) -> RuleReport {
+        let candidates = Self::files_matching_in(&*self.filesystem, &dir, "*");
+        self.execute_among(candidates, dry_run, confirmation, observer)
+    }
+
+    /// Execute the current `Rule` on the files it concerns among some candidates, telling an `Observer` what it does
+    pub fn execute_among(
+        &mut self,
+        candidates: Vec<String>,
+        dry_run: bool,
+        confirmation: &mut Confirmation,
+        observer: &mut dyn Observer,
+    ) -> RuleReport {
+        observer.on_rule_start(self);
+        let mut files: Vec<String> = vec![];
+        for file in candidates {
+            if !self.matches(&file) {
+                continue;
+            }
+            match self.protection(&file) {
+                Some(reason) => observer.on_file_skipped(self, &file, reason),
+                None => {
+                    observer.on_file_matched(self, &file);
+                    files.push(file);
                 }
-            } else if self.first_keyword == "suf" && self.second_keyword == "with" {
-                if dry_run && !interactive_mode {
-                    self.dry_run("suf");
-                } else {
-                    if self.suf_with(dir.clone(), dry_run, interactive_mode) && !dry_run {
-                        self.success();
-                    } else {
-                        if !interactive_mode && !dry_run {
-                            self.error();
-                        }
-                    }
+            }
+        }
+        self.execute_on(files, dry_run, confirmation, observer)
+    }
+
+    /// Execute the current `Rule` on some files, that must be concerned by it, telling an `Observer` what it does
+    pub fn execute_on(
+        &mut self,
+        files: Vec<String>,
+        dry_run: bool,
+        confirmation: &mut Confirmation,
+        observer: &mut dyn Observer,
+    ) -> RuleReport {
+        let mut report = RuleReport {
+            line: self.line_number,
+            name: self.name.clone(),
+            rule: self.line_value.clone(),
+            error: None,
+            operations: vec![],
+        };
+        match self.run(files, dry_run, confirmation, observer) {
+            Ok(x) => report.operations = x,
+            Err(e) => {
+                observer.on_error(self, &e);
+                report.error = Some(e);
+            }
+        }
+        observer.on_finish(&report);
+        report
+    }
+
+    /// Plan the operations of the current `Rule` on some files, then apply the ones accepted by a `Confirmation`
+    fn run(
+        &mut self,
+        files: Vec<String>,
+        dry_run: bool,
+        confirmation: &mut Confirmation,
+        observer: &mut dyn Observer,
+    ) -> Result<Vec<(Operation, Outcome)>, String> {
+        if !self.valid || !self.error_message.is_empty() {
+            return Err(self.error_message.clone());
+        }
+        if files.is_empty() {
+            return Ok(vec![]);
+        }
+        confirmation.start_rule();
+        let planned = self.plan_skipping(&files, &mut |file, reason| {
+            observer.on_file_skipped(self, file, reason)
+        })?;
+        let (accepted, declined): (Vec<Operation>, Vec<Operation>) = planned
+            .into_iter()
+            .partition(|x| confirmation.accepts(self, x));
+        let outcomes = match dry_run {
+            true => {
+                for operation in &accepted {
+                    observer.on_operation(operation, &Outcome::Planned);
                 }
-            } else {
-                println!(
-                    "error: rule line {},token {} and {} are not handled.",
-                    self.line_number, self.first_keyword, self.second_keyword
-                );
+                vec![Outcome::Planned; accepted.len()]
             }
-        } else {
-            self.error();
+            false => self.apply(&accepted, observer),
+        };
+        for operation in &declined {
+            observer.on_operation(operation, &Outcome::Declined);
         }
+        Ok(accepted
+            .into_iter()
+            .zip(outcomes)
+            .chain(declined.into_iter().map(|x| (x, Outcome::Declined)))
+            .collect())
     }
 
     /// Verify if valid pairs of keyword exists
@@ -399,7 +864,11 @@ impl Rule {
     fn check_error_message(token: &String, i: usize) -> (bool, String) {
         let default = (false, String::new());
         match i {
-            0 => match !FIRST_VALID_TOKENS.iter().any(|v| v == &token) {
+            0 => match !FIRST_VALID_TOKENS
+                .iter()
+                .chain(UNARY_VALID_TOKENS.iter())
+                .any(|v| v == &token)
+            {
                 true => (true, String::from("Invalid first keyword.")),
                 false => default,
             },
@@ -413,6 +882,190 @@ impl Rule {
         }
     }
 
+    /// Parse the `except <pattern>`, `allow-protected`, `older-than <duration>`, `every <period>`, `at <time>`,
+    /// `stop` and `continue` options that all rules accept, and return the other options untouched
+    fn parse_common_options<'a>(options: &[&'a str]) -> Result<CommonOptions<'a>, String> {
+        let mut common = CommonOptions::default();
+        let mut every: Option<&str> = None;
+        let mut at: Option<&str> = None;
+        let mut i = 0;
+        while i < options.len() {
+            match options[i] {
+                "except" => {
+                    i += 1;
+                    match options.get(i) {
+                        Some(x) => common.exceptions.push(x.to_string()),
+                        None => return Err(String::from("Missing exception pattern.")),
+                    }
+                }
+                "allow-protected" => common.allow_protected = true,
+                "stop" | "continue" if common.flow.is_some() => {
+                    return Err(String::from(
+                        "Only one of `stop` and `continue` is allowed.",
+                    ))
+                }
+                "stop" => common.flow = Some(Flow::Stop),
+                "continue" => common.flow = Some(Flow::Continue),
+                "ignore-case" | "unicode" => {
+                    common.matching.set_flag(options[i]);
+                }
+                "older-than" => {
+                    i += 1;
+                    common.older_than =
+                        match options.get(i).and_then(|x| schedule::parse_duration(x)) {
+                            Some(x) => Some(x),
+                            None => return Err(String::from("Invalid age.")),
+                        };
+                }
+                "every" => {
+                    i += 1;
+                    every = Some(options.get(i).ok_or("Missing schedule.")?);
+                }
+                "at" => {
+                    i += 1;
+                    at = Some(options.get(i).ok_or("Missing time.")?);
+                }
+                x => common.others.push(x),
+            }
+            i += 1;
+        }
+        common.schedule = Schedule::from_clauses(every, at)?;
+        Ok(common)
+    }
+
+    /// Parse the options written after the pattern of a `normalize` rule
+    fn parse_normalize_options(options: &[&str]) -> Result<NormalizeOptions, String> {
+        let mut normalize_options = NormalizeOptions::default();
+        let mut i = 0;
+        while i < options.len() {
+            match options[i] {
+                "url-decode" => normalize_options.url_decode = true,
+                "lowercase-ext" => normalize_options.lowercase_extension = true,
+                "strip-copies" => normalize_options.strip_copies = true,
+                "ascii" => normalize_options.ascii = true,
+                "spaces" => {
+                    i += 1;
+                    normalize_options.spaces = match options.get(i) {
+                        Some(&"_") => Some('_'),
+                        Some(&"-") => Some('-'),
+                        _ => return Err(String::from("Invalid space replacement.")),
+                    };
+                }
+                "max-length" => {
+                    i += 1;
+                    normalize_options.max_length =
+                        match options.get(i).and_then(|x| x.parse::<usize>().ok()) {
+                            Some(x) if x > 0 => Some(x),
+                            _ => return Err(String::from("Invalid maximum length.")),
+                        };
+                }
+                x => match UnicodeForm::from_token(x) {
+                    Some(x) => normalize_options.form = x,
+                    None => return Err(String::from("Invalid option.")),
+                },
+            }
+            i += 1;
+        }
+        Ok(normalize_options)
+    }
+
+    /// Parse the options written after the patterns of a `mv` or `cp` rule
+    fn parse_copy_options(options: &[&str]) -> Result<CopyOptions, String> {
+        let mut copy_options = CopyOptions::default();
+        let mut i = 0;
+        while i < options.len() {
+            match options[i] {
+                "conflict" => {
+                    i += 1;
+                    copy_options.conflict =
+                        match options.get(i).and_then(|x| ConflictPolicy::from_token(x)) {
+                            Some(x) => x,
+                            None => return Err(String::from("Invalid conflict policy.")),
+                        };
+                }
+                "preserve" => copy_options.preserve = true,
+                "verify" => copy_options.verify = true,
+                "hardlink" => copy_options.link = LinkMode::Hardlink,
+                "reflink" => copy_options.link = LinkMode::Reflink,
+                _ => return Err(String::from("Invalid option.")),
+            }
+            i += 1;
+        }
+        Ok(copy_options)
+    }
+
+    /// Parse the options written after the patterns of a `zip` or `tar.gz` rule
+    fn parse_archive_options(options: &[&str]) -> Result<ArchiveOptions, String> {
+        let mut archive_options = ArchiveOptions::default();
+        for option in options {
+            match *option {
+                "remove" => archive_options.remove = true,
+                "append" => archive_options.append = true,
+                _ => return Err(String::from("Invalid option.")),
+            }
+        }
+        Ok(archive_options)
+    }
+
+    /// Parse the options written after the patterns of an `extract` rule
+    fn parse_extract_options(options: &[&str]) -> Result<ExtractOptions, String> {
+        let mut extract_options = ExtractOptions::default();
+        let mut i = 0;
+        while i < options.len() {
+            match options[i] {
+                "flatten" => extract_options.flatten = true,
+                "max-size" => {
+                    i += 1;
+                    extract_options.max_size =
+                        match options.get(i).and_then(|x| archive::parse_size(x)) {
+                            Some(x) => x,
+                            None => return Err(String::from("Invalid maximum size.")),
+                        };
+                }
+                "max-entries" => {
+                    i += 1;
+                    extract_options.max_entries =
+                        match options.get(i).and_then(|x| x.parse::<usize>().ok()) {
+                            Some(x) => x,
+                            None => return Err(String::from("Invalid maximum number of entries.")),
+                        };
+                }
+                _ => return Err(String::from("Invalid option.")),
+            }
+            i += 1;
+        }
+        Ok(extract_options)
+    }
+
+    /// Parse the options written after the pattern of a `dedupe` rule
+    fn parse_dedupe_options(options: &[&str]) -> Result<DedupeOptions, String> {
+        let mut dedupe_options = DedupeOptions::default();
+        let mut i = 0;
+        while i < options.len() {
+            match options[i] {
+                "keep" => {
+                    i += 1;
+                    dedupe_options.keep =
+                        match options.get(i).and_then(|x| KeepPolicy::from_token(x)) {
+                            Some(x) => x,
+                            None => return Err(String::from("Invalid keep policy.")),
+                        };
+                }
+                "prefer" => {
+                    i += 1;
+                    dedupe_options.prefer = match options.get(i) {
+                        Some(x) => Some(PathBuf::from(x)),
+                        None => return Err(String::from("Missing preferred folder.")),
+                    };
+                }
+                "hardlink" => dedupe_options.hardlink = true,
+                _ => return Err(String::from("Invalid option.")),
+            }
+            i += 1;
+        }
+        Ok(dedupe_options)
+    }
+
     /// Returns an empty `Rule` to act as a skipped one
     fn skipped_rule(line_value: &str, tokens: Vec<String>) -> Self {
         Self::new(
@@ -428,6 +1081,11 @@ impl Rule {
         )
     }
 
+    /// Returns an invalid `Rule` for a line that is not a rule, such as an `include` line whose file is unreadable
+    pub(crate) fn invalid(line_value: &str, line_number: usize, message: String) -> Self {
+        Self::rule_missing_element(line_value, line_number, vec![], message)
+    }
+
     /// Returns a `Rule` with missing elements
     fn rule_missing_element(
         line_value: &str,
@@ -448,18 +1106,65 @@ impl Rule {
         );
     }
 
+    /// Return the kind and the value of an `@name(...)` or `@tag(...)` annotation
+    pub(crate) fn annotation(word: &str) -> Option<(&str, &str)> {
+        ANNOTATION
+            .captures(word)
+            .map(|x| (x.get(1).unwrap().as_str(), x.get(2).unwrap().as_str()))
+    }
+
+    /// Return the name and the tags of the annotations written before a rule, or why they are invalid
+    fn parse_annotations(words: &[&str]) -> Result<(Option<String>, Vec<String>), String> {
+        let mut name: Option<String> = None;
+        let mut tags: Vec<String> = vec![];
+        for word in words {
+            match Self::annotation(word) {
+                Some(("name", _)) if name.is_some() => {
+                    return Err(String::from("Only one `@name` is allowed."))
+                }
+                Some(("name", x)) => name = Some(x.to_string()),
+                Some((_, x)) => tags.push(x.to_string()),
+                None => {
+                    return Err(String::from(
+                        "Invalid annotation, expected `@name(<name>)` or `@tag(<tag>)`.",
+                    ))
+                }
+            }
+        }
+        Ok((name, tags))
+    }
+
     /// Creates a new `Rule` from a string
     pub fn new_from_line(line: &String, line_number: &usize) -> Self {
         let line_number = line_number + 1;
         let line_value = App::remove_comment(&line);
-        let line_value = line_value.trim_end();
+        let line_value = line_value.trim();
         let strings: Vec<&str> = line_value.split(" ").filter(|x| x != &"").collect();
         let mut tokens: Vec<String> = vec![];
         let mut error_message: (bool, String) = (false, String::new());
 
         if (strings.len() == 1 && &strings[0] == &"\r") || strings.len() == 0 {
             return Self::skipped_rule(line_value, tokens);
-        } else if strings.len() < 4 {
+        }
+        let annotated = strings.iter().take_while(|x| x.starts_with('@')).count();
+        let (name, tags) = match Self::parse_annotations(&strings[..annotated]) {
+            Ok(x) => x,
+            Err(e) => return Self::rule_missing_element(line_value, line_number, tokens, e),
+        };
+        let strings = &strings[annotated..];
+        if strings.is_empty() {
+            return Self::rule_missing_element(
+                line_value,
+                line_number,
+                tokens,
+                String::from("Missing keyword(s) or pattern(s)."),
+            );
+        }
+        let core_length = match UNARY_VALID_TOKENS.contains(&strings[0]) {
+            true => 2,
+            false => 4,
+        };
+        if strings.len() < core_length {
             return Self::rule_missing_element(
                 line_value,
                 line_number,
@@ -468,12 +1173,8 @@ impl Rule {
             );
         }
 
-        for i in 0..strings.len() {
-            let token: String = if i <= 3 {
-                strings[i].to_string()
-            } else {
-                String::new()
-            };
+        for i in 0..core_length {
+            let token: String = strings[i].to_string();
             error_message = Self::check_error_message(&token, i);
             match error_message.0 {
                 true => break,
@@ -483,28 +1184,89 @@ impl Rule {
 
         error_message = Self::check_pairs(&tokens, &error_message.0, &error_message.1);
 
-        Self::new(
+        let mut copy_options = CopyOptions::default();
+        let mut archive_options = ArchiveOptions::default();
+        let mut extract_options = ExtractOptions::default();
+        let mut dedupe_options = DedupeOptions::default();
+        let mut normalize_options = NormalizeOptions::default();
+        let mut common = CommonOptions::default();
+        let keywords: Vec<String> = tokens.clone();
+        if !error_message.0 && strings.len() > core_length {
+            match Self::parse_common_options(&strings[core_length..]) {
+                Ok(x) => common = x,
+                Err(x) => error_message = (true, x),
+            };
+            let options = &common.others[..];
+            let parsed = match tokens[0].as_str() {
+                _ if options.is_empty() => Ok(()),
+                "mv" | "cp" => Self::parse_copy_options(options).map(|x| copy_options = x),
+                "zip" | "tar.gz" => {
+                    Self::parse_archive_options(options).map(|x| archive_options = x)
+                }
+                "extract" => Self::parse_extract_options(options).map(|x| extract_options = x),
+                "dedupe" => Self::parse_dedupe_options(options).map(|x| dedupe_options = x),
+                "normalize" => {
+                    Self::parse_normalize_options(options).map(|x| normalize_options = x)
+                }
+                _ => Err(String::from("Query has too much elements.")),
+            };
+            if let Err(x) = parsed {
+                error_message = (true, x);
+            }
+            tokens.extend(strings[core_length..].iter().map(|x| x.to_string()));
+        }
+        if !error_message.0 && tokens.len() > 1 {
+            let patterns = std::iter::once(&tokens[1]).chain(common.exceptions.iter());
+            for pattern in patterns {
+                if let Err(x) = common.matching.regex(pattern) {
+                    error_message = (true, x);
+                    break;
+                }
+            }
+        }
+        if !error_message.0 && ArchiveFormat::from_keyword(&tokens[0]).is_some() {
+            if let Err(x) = archive::expand_date_placeholders(&tokens[3], &Local::now()) {
+                error_message = (true, x);
+            }
+        }
+
+        let mut rule = Self::new(
             line_value.to_string(),
             line_number,
             !error_message.0,
             tokens.clone(),
             error_message.1,
-            match tokens.len() >= 1 {
-                true => tokens[0].to_string(),
+            match keywords.len() >= 1 {
+                true => keywords[0].to_string(),
                 _ => String::new(),
             },
-            match tokens.len() >= 2 {
-                true => tokens[1].to_string(),
+            match keywords.len() >= 2 {
+                true => keywords[1].to_string(),
                 _ => String::new(),
             },
-            match tokens.len() >= 3 {
-                true => tokens[2].to_string(),
+            match keywords.len() >= 3 {
+                true => keywords[2].to_string(),
                 _ => String::new(),
             },
-            match tokens.len() == 4 {
-                true => tokens[3].to_string(),
+            match keywords.len() >= 4 {
+                true => keywords[3].to_string(),
                 _ => String::new(),
             },
-        )
+        );
+        rule.copy_options = copy_options;
+        rule.archive_options = archive_options;
+        rule.extract_options = extract_options;
+        rule.dedupe_options = dedupe_options;
+        rule.normalize_options = normalize_options;
+        rule.exceptions = common.exceptions;
+        rule.allow_protected = common.allow_protected;
+        rule.schedule = common.schedule;
+        rule.older_than = common.older_than;
+        rule.flow = common.flow;
+        rule.matching = common.matching;
+        rule.compile_patterns();
+        rule.name = name;
+        rule.tags = tags;
+        rule
     }
 }
@@ -0,0 +1,291 @@
+use crate::components::confirm::Confirmation;
+use crate::components::evaluation::Selection;
+use crate::components::output::{Output, OutputFormat};
+use crate::components::{trash, Config, Rule};
+use chrono::{DateTime, Datelike, Local, NaiveDateTime, NaiveTime, TimeZone, Weekday};
+use std::collections::HashMap;
+use std::env;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::Duration;
+
+/// How often a scheduled rule runs
+#[derive(Clone, Debug, PartialEq)]
+pub enum Every {
+    /// A fixed time after the last run, e.g. `every 6h` or `every week`
+    Interval(Duration),
+    /// Every day at a time, e.g. `every day at 08:00`
+    Day,
+    /// Every week on a day at a time, e.g. `every sunday at 18:00`
+    Weekday(Weekday),
+}
+
+/// The `every` and `at` clauses of a `Rule`
+#[derive(Clone, Debug, PartialEq)]
+pub struct Schedule {
+    pub every: Every,
+    /// Time of the day of the runs, midnight by default
+    pub at: NaiveTime,
+}
+
+impl Schedule {
+    /// Return the `Schedule` of the values of the `every` and `at` clauses, if any of them is present.
+    /// `at` alone means every day.
+    pub fn from_clauses(every: Option<&str>, at: Option<&str>) -> Result<Option<Self>, String> {
+        let time = match at {
+            Some(x) => match NaiveTime::parse_from_str(x, "%H:%M") {
+                Ok(y) => Some(y),
+                Err(_) => return Err(String::from("Invalid time, expected HH:MM.")),
+            },
+            None => None,
+        };
+        let every = match every {
+            Some("day") => Every::Day,
+            Some("hour") => Every::Interval(Duration::from_secs(3600)),
+            Some("week") => Every::Interval(Duration::from_secs(7 * 86400)),
+            Some(x) => match (x.parse::<Weekday>(), parse_duration(x)) {
+                (Ok(y), _) => Every::Weekday(y),
+                (_, Some(y)) if y.as_secs() > 0 => Every::Interval(y),
+                _ => return Err(String::from("Invalid schedule.")),
+            },
+            None if time.is_some() => Every::Day,
+            None => return Ok(None),
+        };
+        if time.is_some() {
+            if let Every::Interval(_) = every {
+                return Err(String::from(
+                    "A time can only be given to a daily or weekly schedule.",
+                ));
+            }
+        }
+        Ok(Some(Self {
+            every,
+            at: time.unwrap_or(NaiveTime::MIN),
+        }))
+    }
+
+    /// Return the first time the rule must run after its last run
+    pub fn next_after(&self, last: DateTime<Local>) -> DateTime<Local> {
+        let last_local = last.naive_local();
+        match self.every {
+            Every::Interval(x) => last + chrono::Duration::from_std(x).unwrap_or_default(),
+            Every::Day | Every::Weekday(_) => {
+                let mut date = last_local.date();
+                loop {
+                    let candidate = date.and_time(self.at);
+                    let day_matches = match self.every {
+                        Every::Weekday(x) => date.weekday() == x,
+                        _ => true,
+                    };
+                    if day_matches && candidate > last_local {
+                        return local(candidate);
+                    }
+                    date = date.succ_opt().unwrap_or(date);
+                }
+            }
+        }
+    }
+
+    /// Check if the rule must run now, knowing its last run.
+    /// A rule that never ran is due at once, and missed runs are caught up by a single run.
+    pub fn is_due(&self, last: Option<DateTime<Local>>, now: DateTime<Local>) -> bool {
+        match last {
+            Some(x) => self.next_after(x) <= now,
+            None => true,
+        }
+    }
+}
+
+/// Return the local time of a date and time, skipping the hour missing when the clock moves forward
+fn local(time: NaiveDateTime) -> DateTime<Local> {
+    match Local.from_local_datetime(&time).earliest() {
+        Some(x) => x,
+        None => local(time + chrono::Duration::hours(1)),
+    }
+}
+
+/// Parse a duration such as `30m`, `12h`, `7d` or `2w`
+pub fn parse_duration(value: &str) -> Option<Duration> {
+    let unit = value.chars().last()?;
+    let number: u64 = value[..value.len() - unit.len_utf8()].parse().ok()?;
+    let seconds = match unit {
+        'm' => 60,
+        'h' => 3600,
+        'd' => 86400,
+        'w' => 7 * 86400,
+        _ => return None,
+    };
+    number.checked_mul(seconds).map(Duration::from_secs)
+}
+
+/// Return the file keeping the last runs of the scheduled rules, following the XDG base directory specification
+pub fn state_file() -> PathBuf {
+    let state_home = match env::var_os("XDG_STATE_HOME") {
+        Some(x) if !x.is_empty() => PathBuf::from(x),
+        _ => trash::home_dir().join(".local").join("state"),
+    };
+    state_home.join("crabby").join("schedule.state")
+}
+
+/// The last runs of the scheduled rules, saved in a state file so that missed runs are caught up
+#[derive(Clone, Debug)]
+pub struct ScheduleState {
+    path: PathBuf,
+    last_runs: HashMap<String, DateTime<Local>>,
+}
+
+impl ScheduleState {
+    /// Read a state file, that is empty if it does not exist yet
+    pub fn load(path: &Path) -> io::Result<Self> {
+        let mut last_runs = HashMap::new();
+        let content = match fs::read_to_string(path) {
+            Ok(x) => x,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => String::new(),
+            Err(e) => return Err(e),
+        };
+        for line in content.lines() {
+            let mut values = line.splitn(2, '\t');
+            if let (Some(time), Some(key)) = (values.next(), values.next()) {
+                if let Ok(time) = DateTime::parse_from_rfc3339(time) {
+                    last_runs.insert(key.to_string(), time.with_timezone(&Local));
+                }
+            }
+        }
+        Ok(Self {
+            path: path.to_path_buf(),
+            last_runs,
+        })
+    }
+
+    /// Write the state file
+    pub fn save(&self) -> io::Result<()> {
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let mut keys: Vec<&String> = self.last_runs.keys().collect();
+        keys.sort();
+        let content: String = keys
+            .iter()
+            .map(|x| format!("{}\t{}\n", self.last_runs[*x].to_rfc3339(), x))
+            .collect();
+        fs::write(&self.path, content)
+    }
+
+    /// Return the last run of a scheduled rule
+    pub fn last_run(&self, key: &str) -> Option<DateTime<Local>> {
+        self.last_runs.get(key).cloned()
+    }
+
+    /// Remember the last run of a scheduled rule
+    pub fn record(&mut self, key: &str, time: DateTime<Local>) {
+        self.last_runs.insert(key.to_string(), time);
+    }
+}
+
+/// A Scheduler runs the scheduled rules of a `.crc` file on some folders when they are due
+pub struct Scheduler {
+    targets: Vec<String>,
+    config_file: String,
+    state: ScheduleState,
+    dry_run: bool,
+    selection: Selection,
+    output: Output,
+}
+
+impl Scheduler {
+    /// Create a new `Scheduler`, keeping its state in a file
+    pub fn new(
+        targets: Vec<String>,
+        config_file: String,
+        state_file: &Path,
+        dry_run: bool,
+    ) -> io::Result<Self> {
+        Ok(Self {
+            targets,
+            config_file,
+            state: ScheduleState::load(state_file)?,
+            dry_run,
+            selection: Selection::default(),
+            output: Output::new(OutputFormat::Text),
+        })
+    }
+
+    /// Set the format of the events written while the `Scheduler` runs.
+    /// The JSON format writes one event per line, since the `Scheduler` never finishes.
+    pub fn set_output(&mut self, format: OutputFormat) {
+        self.output = Output::new(match format {
+            OutputFormat::Json => OutputFormat::Ndjson,
+            x => x,
+        });
+    }
+
+    /// Only run the scheduled rules of a `Selection`
+    pub fn set_selection(&mut self, selection: Selection) {
+        self.selection = selection;
+    }
+
+    /// Run the scheduled rules that are due, and return how many runs happened.
+    /// The `.crc` file is read again each time, so that it can be edited while the scheduler runs.
+    pub fn run_due(&mut self, now: DateTime<Local>) -> io::Result<usize> {
+        let config = Config::from_file(&self.config_file)?;
+        let mut runs = 0;
+        for target in self.targets.clone() {
+            let due: Vec<String> = config
+                .get_rules()
+                .iter()
+                .filter(|x| *x.is_valid() && self.selection.selects(x))
+                .filter(|x| match x.get_schedule() {
+                    Some(schedule) => {
+                        schedule.is_due(self.state.last_run(&self.key(&target, x)), now)
+                    }
+                    None => false,
+                })
+                .map(|x| self.key(&target, x))
+                .collect();
+            if due.is_empty() {
+                continue;
+            }
+            // The due rules run like a clean of the target, in the folders of their blocks
+            config
+                .scheduled(|x| due.contains(&self.key(&target, x)))
+                .execute_rules_with(
+                    target.clone(),
+                    self.dry_run,
+                    &mut Confirmation::none(),
+                    &mut self.output,
+                );
+            for key in &due {
+                self.state.record(key, now);
+            }
+            runs += due.len();
+        }
+        if runs > 0 && !self.dry_run {
+            self.state.save()?;
+        }
+        Ok(runs)
+    }
+
+    /// Return the key of the last run of a scheduled rule on a target in the state file.
+    /// A named rule keeps its last run when its line is edited.
+    fn key(&self, target: &str, rule: &Rule) -> String {
+        let id = match rule.get_name() {
+            Some(x) => format!("@name({})", x),
+            None => rule.get_line_value().clone(),
+        };
+        format!("{}\t{}\t{}", target, self.config_file, id)
+    }
+
+    /// Run the scheduled rules when they are due, until the process is stopped.
+    /// An error, such as the `.crc` file being unreadable while it is saved, is written and the next check retries.
+    pub fn run(&mut self) -> io::Result<()> {
+        loop {
+            if let Err(e) = self.run_due(Local::now()) {
+                let text = format!("could not run the rules of `{}`: {}", self.config_file, e);
+                self.output.message("error", text);
+            }
+            thread::sleep(Duration::from_secs(30));
+        }
+    }
+}
@@ -0,0 +1,225 @@
+use crate::components::{trash, Config};
+use std::env;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// What the `service` subcommand must do
+#[derive(Clone, Debug, PartialEq)]
+pub enum ServiceAction {
+    /// Write the unit files of a service
+    Install(ServiceOptions),
+    /// Remove the unit files of the service with this name
+    Uninstall(String),
+    /// Tell if the service with this name is installed
+    Status(String),
+}
+
+/// Options of a `systemd --user` service running Crabby
+#[derive(Clone, Debug, PartialEq)]
+pub struct ServiceOptions {
+    /// Name of the unit files, without extension
+    pub name: String,
+    /// Folders to clean
+    pub targets: Vec<PathBuf>,
+    /// `.crc` files applied to the folders
+    pub config_files: Vec<PathBuf>,
+    /// `OnCalendar` expression of a timer, e.g. `hourly`.
+    /// Without it the service watches the folders instead of cleaning them periodically.
+    pub timer: Option<String>,
+}
+
+impl Default for ServiceOptions {
+    fn default() -> Self {
+        Self {
+            name: String::from("crabby"),
+            targets: vec![],
+            config_files: vec![],
+            timer: None,
+        }
+    }
+}
+
+/// Return the folder of the `systemd --user` units, following the XDG base directory specification
+pub fn unit_dir() -> PathBuf {
+    let config_home = match env::var_os("XDG_CONFIG_HOME") {
+        Some(x) if !x.is_empty() => PathBuf::from(x),
+        _ => trash::home_dir().join(".config"),
+    };
+    config_home.join("systemd").join("user")
+}
+
+/// Return the `journalctl` command showing the logs of a service
+pub fn journal_filter(name: &str) -> String {
+    format!("journalctl --user -u {}.service -f", name)
+}
+
+/// Check that the folders and the `.crc` files exist and that every rule is valid.
+/// Return the absolute paths of the folders and files, that the units must use.
+pub fn validate_service(options: &ServiceOptions) -> Result<ServiceOptions, String> {
+    if options.targets.is_empty() || options.config_files.is_empty() {
+        return Err(String::from("a folder and a configuration file are needed"));
+    }
+    if options.timer.is_none() && options.config_files.len() > 1 {
+        return Err(String::from(
+            "watch mode uses a single configuration file, add a timer to use several",
+        ));
+    }
+    let absolute = |x: &PathBuf| {
+        fs::canonicalize(x).map_err(|e| format!("could not find `{}`: {}", x.display(), e))
+    };
+    let targets = options
+        .targets
+        .iter()
+        .map(absolute)
+        .collect::<Result<Vec<PathBuf>, String>>()?;
+    let config_files = options
+        .config_files
+        .iter()
+        .map(absolute)
+        .collect::<Result<Vec<PathBuf>, String>>()?;
+    for file in &config_files {
+        let config = Config::from_file(&file.to_string_lossy())
+            .map_err(|e| format!("could not read `{}`: {}", file.display(), e))?;
+        if let Some(rule) = config.get_rules().iter().find(|x| !*x.is_valid()) {
+            return Err(format!(
+                "rule line {} of `{}` is invalid: {}",
+                rule.get_line_number(),
+                file.display(),
+                rule.get_error_message()
+            ));
+        }
+    }
+    Ok(ServiceOptions {
+        targets,
+        config_files,
+        ..options.clone()
+    })
+}
+
+/// Return the content of the `.service` unit running an executable of Crabby
+pub fn render_service(options: &ServiceOptions, executable: &Path) -> String {
+    let mut unit = format!(
+        "[Unit]\nDescription=Crabby cleaning {}\n\n[Service]\n",
+        options
+            .targets
+            .iter()
+            .map(|x| x.display().to_string())
+            .collect::<Vec<String>>()
+            .join(", ")
+    );
+    match &options.timer {
+        Some(_) => {
+            unit.push_str("Type=oneshot\n");
+            for file in &options.config_files {
+                for target in &options.targets {
+                    let arguments = [executable, Path::new("-c"), target, Path::new("-f"), file];
+                    unit.push_str(&format!("ExecStart={}\n", command_line(&arguments)));
+                }
+            }
+        }
+        None => {
+            let mut arguments = vec![executable, Path::new("watch")];
+            for target in &options.targets {
+                arguments.push(Path::new("-c"));
+                arguments.push(target);
+            }
+            arguments.push(Path::new("-f"));
+            arguments.push(&options.config_files[0]);
+            unit.push_str(&format!(
+                "Type=simple\nExecStart={}\nRestart=on-failure\n\n[Install]\nWantedBy=default.target\n",
+                command_line(&arguments)
+            ));
+        }
+    }
+    unit
+}
+
+/// Return the content of the `.timer` unit of a service, if it has one
+pub fn render_timer(options: &ServiceOptions) -> Option<String> {
+    options.timer.as_ref().map(|x| {
+        format!(
+            "[Unit]\nDescription=Run {}.service periodically\n\n[Timer]\nOnCalendar={}\nPersistent=true\n\n[Install]\nWantedBy=timers.target\n",
+            options.name, x
+        )
+    })
+}
+
+/// Validate the options, then write the unit files and return their paths
+pub fn install_service(
+    options: &ServiceOptions,
+    executable: &Path,
+) -> Result<Vec<PathBuf>, String> {
+    install_service_in(options, executable, &unit_dir())
+}
+
+/// Validate the options, then write the unit files in a folder and return their paths
+pub fn install_service_in(
+    options: &ServiceOptions,
+    executable: &Path,
+    dir: &Path,
+) -> Result<Vec<PathBuf>, String> {
+    let options = validate_service(options)?;
+    let mut units = vec![(
+        dir.join(format!("{}.service", options.name)),
+        render_service(&options, executable),
+    )];
+    if let Some(timer) = render_timer(&options) {
+        units.push((dir.join(format!("{}.timer", options.name)), timer));
+    }
+    let write = || -> io::Result<()> {
+        fs::create_dir_all(dir)?;
+        for (path, content) in &units {
+            fs::write(path, content)?;
+        }
+        Ok(())
+    };
+    write().map_err(|e| format!("could not write the units in `{}`: {}", dir.display(), e))?;
+    Ok(units.into_iter().map(|x| x.0).collect())
+}
+
+/// Remove the unit files of a service and return their paths
+pub fn uninstall_service(name: &str) -> io::Result<Vec<PathBuf>> {
+    uninstall_service_in(name, &unit_dir())
+}
+
+/// Remove the unit files of a service from a folder and return their paths
+pub fn uninstall_service_in(name: &str, dir: &Path) -> io::Result<Vec<PathBuf>> {
+    let mut removed = vec![];
+    for path in installed_units_in(name, dir) {
+        fs::remove_file(&path)?;
+        removed.push(path);
+    }
+    Ok(removed)
+}
+
+/// Return the paths of the unit files of a service that are installed
+pub fn installed_units(name: &str) -> Vec<PathBuf> {
+    installed_units_in(name, &unit_dir())
+}
+
+/// Return the paths of the unit files of a service that are installed in a folder
+pub fn installed_units_in(name: &str, dir: &Path) -> Vec<PathBuf> {
+    ["service", "timer"]
+        .iter()
+        .map(|x| dir.join(format!("{}.{}", name, x)))
+        .filter(|x| x.exists())
+        .collect()
+}
+
+/// Join the arguments of a command, quoted for the `ExecStart` key of a unit
+fn command_line(arguments: &[&Path]) -> String {
+    arguments
+        .iter()
+        .map(|x| {
+            let escaped = x
+                .to_string_lossy()
+                .replace('\\', "\\\\")
+                .replace('"', "\\\"")
+                .replace('%', "%%")
+                .replace('$', "$$");
+            format!("\"{}\"", escaped)
+        })
+        .collect::<Vec<String>>()
+        .join(" ")
+}
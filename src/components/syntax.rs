@@ -0,0 +1,246 @@
+use crate::components::evaluation::Evaluation;
+use crate::components::matching::Matching;
+use regex::{Captures, Regex};
+use std::fmt;
+
+/// A word of a line of a `.crc` file, with the spaces written before it
+#[derive(Clone, Debug, PartialEq)]
+pub struct Word {
+    /// The spaces before the word, the indentation for the first word of a line
+    pub leading: String,
+    pub text: String,
+    /// Column of the first character of the word, from 1
+    pub column: usize,
+}
+
+/// What a line of a `.crc` file holds
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum LineKind {
+    /// Nothing but spaces
+    Blank,
+    /// A comment and nothing else
+    Comment,
+    /// An `ignore` line
+    Ignore,
+    /// A `let <name> = <value>` line
+    Variable,
+    /// An `include <path>` line
+    Include,
+    /// An `evaluate <mode>` line
+    Evaluate,
+    /// A `match <flag>...` line
+    Match,
+    /// An `in <path> [recursive] {` line, opening a block of rules scoped to a folder
+    Block,
+    /// A `}` line, closing a block
+    BlockEnd,
+    /// A rule, valid or not
+    Rule,
+}
+
+/// A line of a `.crc` file, that is written back exactly as it was read
+#[derive(Clone, Debug, PartialEq)]
+pub struct SyntaxLine {
+    /// Number of the line, from 1
+    pub number: usize,
+    /// The words before the comment, separated by spaces as the rules are
+    pub words: Vec<Word>,
+    /// The whitespace after the last word, before the comment or the end of the line
+    pub trailing: String,
+    /// The comment, from its `//` to the end of the line
+    pub comment: Option<String>,
+    /// `\n`, or nothing for the last line
+    pub ending: String,
+}
+
+impl SyntaxLine {
+    /// Parse a line without its `\n`
+    fn parse(number: usize, line: &str, ending: &str) -> Self {
+        let (code, comment) = match line.find("//") {
+            Some(i) => (&line[..i], Some(line[i..].to_string())),
+            None => (line, None),
+        };
+        let content = code.trim_end();
+        let mut words: Vec<Word> = vec![];
+        let mut leading = String::new();
+        let mut start: Option<(usize, usize)> = None;
+        for (column, (i, c)) in content.char_indices().enumerate() {
+            match (c, start) {
+                (' ', Some((x, y))) => {
+                    words.push(Word {
+                        leading: std::mem::take(&mut leading),
+                        text: content[x..i].to_string(),
+                        column: y + 1,
+                    });
+                    leading.push(' ');
+                    start = None;
+                }
+                (' ', None) => leading.push(' '),
+                (_, None) => start = Some((i, column)),
+                _ => (),
+            }
+        }
+        if let Some((x, y)) = start {
+            words.push(Word {
+                leading: std::mem::take(&mut leading),
+                text: content[x..].to_string(),
+                column: y + 1,
+            });
+        }
+        let trailing = format!("{}{}", leading, &code[content.len()..]);
+        Self {
+            number,
+            words,
+            trailing,
+            comment,
+            ending: ending.to_string(),
+        }
+    }
+
+    pub fn kind(&self) -> LineKind {
+        match (self.words.first(), &self.comment) {
+            (None, None) => LineKind::Blank,
+            (None, Some(_)) => LineKind::Comment,
+            (Some(x), _) if x.text == "ignore" => LineKind::Ignore,
+            (Some(x), _) if x.text == "let" => LineKind::Variable,
+            (Some(x), _) if x.text == "include" => LineKind::Include,
+            (Some(x), _) if x.text == "evaluate" => LineKind::Evaluate,
+            (Some(x), _) if x.text == "match" => LineKind::Match,
+            (Some(x), _) if x.text == "in" => LineKind::Block,
+            (Some(x), _) if x.text == "}" && self.words.len() == 1 => LineKind::BlockEnd,
+            _ => LineKind::Rule,
+        }
+    }
+
+    /// Return the name and the value of a `let <name> = <value>` line, or why it is invalid
+    pub fn variable(&self) -> Result<(&str, &str), String> {
+        let name = Regex::new(r"^[A-Za-z_][A-Za-z0-9_]*$").unwrap();
+        match &self.words[..] {
+            [_, x, y, z] if y.text == "=" && name.is_match(&x.text) => Ok((&x.text, &z.text)),
+            _ => Err(String::from(
+                "Invalid variable, expected `let <name> = <value>`.",
+            )),
+        }
+    }
+
+    /// Return the path of an `include <path>` line, or why it is invalid
+    pub fn include(&self) -> Result<&str, String> {
+        match &self.words[..] {
+            [_, x] => Ok(&x.text),
+            _ => Err(String::from("Invalid include, expected `include <path>`.")),
+        }
+    }
+
+    /// Return the mode of an `evaluate <mode>` line, or why it is invalid
+    pub fn evaluation(&self) -> Result<Evaluation, String> {
+        let mode = match &self.words[..] {
+            [_, x] => Evaluation::from_name(&x.text),
+            _ => None,
+        };
+        mode.ok_or_else(|| {
+            format!(
+                "Invalid evaluation, expected `evaluate <mode>` with a mode among `{}`.",
+                Evaluation::NAMES.join("`, `")
+            )
+        })
+    }
+
+    /// Return the flags of a `match <flag>...` line, or why it is invalid
+    pub fn matching(&self) -> Result<Matching, String> {
+        let mut matching = Matching::default();
+        let flags = &self.words[1..];
+        match !flags.is_empty() && flags.iter().all(|x| matching.set_flag(&x.text)) {
+            true => Ok(matching),
+            false => Err(format!(
+                "Invalid matching, expected `match <flag>...` with flags among `{}`.",
+                Matching::NAMES.join("`, `")
+            )),
+        }
+    }
+
+    /// Return the folder of an `in <path> [recursive] {` line and if it is recursive, or why it is invalid
+    pub fn block(&self) -> Result<(&str, bool), String> {
+        match &self.words[..] {
+            [_, x, y] if y.text == "{" => Ok((&x.text, false)),
+            [_, x, y, z] if y.text == "recursive" && z.text == "{" => Ok((&x.text, true)),
+            _ => Err(String::from(
+                "Invalid block, expected `in <path> {` or `in <path> recursive {`.",
+            )),
+        }
+    }
+
+    /// Return the words of the line separated by one space, without the comment
+    pub fn code(&self) -> String {
+        let words: Vec<&str> = self.words.iter().map(|x| x.text.as_str()).collect();
+        words.join(" ")
+    }
+
+    /// Return the word written at a column, from 1
+    pub fn word_at(&self, column: usize) -> Option<&Word> {
+        self.words
+            .iter()
+            .find(|x| x.column <= column && column <= x.column + x.text.chars().count())
+    }
+}
+
+impl fmt::Display for SyntaxLine {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for word in &self.words {
+            write!(f, "{}{}", word.leading, word.text)?;
+        }
+        write!(
+            f,
+            "{}{}{}",
+            self.trailing,
+            self.comment.as_deref().unwrap_or_default(),
+            self.ending
+        )
+    }
+}
+
+/// Replace the `$name` references of a text by the values of the variables defined before, the last definition of
+/// a name winning. The references to unknown variables are kept.
+pub fn substitute(text: &str, variables: &[(String, String)]) -> String {
+    let reference = Regex::new(r"\$([A-Za-z_][A-Za-z0-9_]*)").unwrap();
+    reference
+        .replace_all(text, |caps: &Captures| {
+            match variables.iter().rev().find(|x| x.0 == caps[1]) {
+                Some(x) => x.1.clone(),
+                None => caps[0].to_string(),
+            }
+        })
+        .to_string()
+}
+
+/// The lines of a `.crc` file, with all their spaces and comments so that the file is written back unchanged
+#[derive(Clone, Debug, PartialEq)]
+pub struct SyntaxTree {
+    pub lines: Vec<SyntaxLine>,
+}
+
+impl SyntaxTree {
+    /// Parse the content of a `.crc` file
+    pub fn parse(content: &str) -> Self {
+        let count = content.split('\n').count();
+        let lines = content
+            .split('\n')
+            .enumerate()
+            .map(|(i, x)| SyntaxLine::parse(i + 1, x, if i + 1 < count { "\n" } else { "" }))
+            .collect();
+        Self { lines }
+    }
+
+    /// Return the line of a number, from 1
+    pub fn line(&self, number: usize) -> Option<&SyntaxLine> {
+        self.lines.get(number.wrapping_sub(1))
+    }
+}
+
+impl fmt::Display for SyntaxTree {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for line in &self.lines {
+            write!(f, "{}", line)?;
+        }
+        Ok(())
+    }
+}
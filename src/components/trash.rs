@@ -0,0 +1,71 @@
+use crate::components::copy;
+use chrono::Local;
+use std::env;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Return the trash folder of the current user, following the freedesktop.org specification
+pub fn trash_dir() -> PathBuf {
+    let data_home = match env::var_os("XDG_DATA_HOME") {
+        Some(x) if !x.is_empty() => PathBuf::from(x),
+        _ => home_dir().join(".local").join("share"),
+    };
+    data_home.join("Trash")
+}
+
+/// Return the home directory of the current user
+pub fn home_dir() -> PathBuf {
+    match env::var_os("HOME").or_else(|| env::var_os("USERPROFILE")) {
+        Some(x) => PathBuf::from(x),
+        None => PathBuf::from("."),
+    }
+}
+
+/// Move a file to the trash, with the `.trashinfo` file that allows to restore it
+pub fn trash(path: &Path) -> io::Result<PathBuf> {
+    trash_in(path, &trash_dir())
+}
+
+/// Move a file to a trash folder, with the `.trashinfo` file that allows to restore it
+pub fn trash_in(path: &Path, trash_dir: &Path) -> io::Result<PathBuf> {
+    let original = fs::canonicalize(path)?;
+    let files = trash_dir.join("files");
+    let info = trash_dir.join("info");
+    fs::create_dir_all(&files)?;
+    fs::create_dir_all(&info)?;
+
+    let mut trashed = files.join(original.file_name().unwrap_or_default());
+    if trashed.exists() {
+        trashed = copy::numbered_path(&trashed);
+    }
+    let name = trashed.file_name().unwrap_or_default().to_string_lossy();
+    fs::write(
+        info.join(format!("{}.trashinfo", name)),
+        format!(
+            "[Trash Info]\nPath={}\nDeletionDate={}\n",
+            encode_path(&original),
+            Local::now().format("%Y-%m-%dT%H:%M:%S")
+        ),
+    )?;
+
+    if fs::rename(&original, &trashed).is_err() {
+        fs::copy(&original, &trashed)?;
+        fs::remove_file(&original)?;
+    }
+    Ok(trashed)
+}
+
+/// Percent-encode a path for the `Path` key of a `.trashinfo` file
+fn encode_path(path: &Path) -> String {
+    let mut encoded = String::new();
+    for byte in path.to_string_lossy().bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' | b'/' => {
+                encoded.push(byte as char)
+            }
+            _ => encoded.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    encoded
+}
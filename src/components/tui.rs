@@ -0,0 +1,310 @@
+use crate::components::plan::Action;
+use crate::components::review::{Review, ReviewFilter};
+use crossterm::{
+    cursor,
+    event::{self, Event as TermEvent, KeyCode, KeyEventKind},
+    execute, queue,
+    style::{Attribute, Print, SetAttribute},
+    terminal::{self, ClearType},
+};
+use std::io::{self, Write};
+
+/// What the terminal UI does after a key
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Step {
+    Continue,
+    /// Leave the terminal UI and apply the selected operations
+    Apply,
+    /// Leave the terminal UI without applying anything
+    Quit,
+}
+
+/// What the keys of the terminal UI do
+#[derive(Clone, Debug, PartialEq)]
+enum Mode {
+    Browse,
+    /// Typing the new destination of the current operation
+    EditDestination(String),
+    /// Typing the extension of the only files shown
+    FilterExtension(String),
+    /// Waiting for `y` before applying the selected operations
+    ConfirmApply,
+}
+
+/// A terminal UI showing the plan of the rules grouped by rule, where the user toggles and edits operations
+pub struct Tui {
+    review: Review,
+    title: String,
+    cursor: usize,
+    offset: usize,
+    mode: Mode,
+    message: String,
+}
+
+const HELP: &str = "space toggle, s/u select/unselect shown, e edit, r rule, t type, c clear filters, enter apply, q quit";
+
+impl Tui {
+    /// Create a new `Tui` reviewing a plan, with a title such as the folder to clean
+    pub fn new(review: Review, title: &str) -> Self {
+        Self {
+            review,
+            title: title.to_string(),
+            cursor: 0,
+            offset: 0,
+            mode: Mode::Browse,
+            message: String::new(),
+        }
+    }
+
+    /// Return the plan, with the choices of the user
+    pub fn get_review(&self) -> &Review {
+        &self.review
+    }
+
+    pub fn into_review(self) -> Review {
+        self.review
+    }
+
+    /// Show the terminal UI until the user applies the plan or quits
+    pub fn run(&mut self) -> io::Result<Step> {
+        let mut stdout = io::stdout();
+        terminal::enable_raw_mode()?;
+        execute!(stdout, terminal::EnterAlternateScreen, cursor::Hide)?;
+        let step = self.event_loop(&mut stdout);
+        let _ = execute!(stdout, cursor::Show, terminal::LeaveAlternateScreen);
+        let _ = terminal::disable_raw_mode();
+        step
+    }
+
+    fn event_loop(&mut self, stdout: &mut io::Stdout) -> io::Result<Step> {
+        loop {
+            let (width, height) = terminal::size()?;
+            self.draw(stdout, width as usize, height as usize)?;
+            if let TermEvent::Key(key) = event::read()? {
+                if key.kind == KeyEventKind::Release {
+                    continue;
+                }
+                match self.handle(key.code) {
+                    Step::Continue => (),
+                    x => return Ok(x),
+                }
+            }
+        }
+    }
+
+    /// Change the state of the terminal UI after a key, and return what to do next
+    pub fn handle(&mut self, key: KeyCode) -> Step {
+        self.message.clear();
+        let visible = self.review.visible();
+        let current = visible.get(self.cursor).copied();
+        match (self.mode.clone(), key) {
+            (Mode::EditDestination(mut x), KeyCode::Char(c)) => {
+                x.push(c);
+                self.mode = Mode::EditDestination(x);
+            }
+            (Mode::FilterExtension(mut x), KeyCode::Char(c)) => {
+                x.push(c);
+                self.mode = Mode::FilterExtension(x);
+            }
+            (Mode::EditDestination(mut x), KeyCode::Backspace) => {
+                x.pop();
+                self.mode = Mode::EditDestination(x);
+            }
+            (Mode::FilterExtension(mut x), KeyCode::Backspace) => {
+                x.pop();
+                self.mode = Mode::FilterExtension(x);
+            }
+            (Mode::EditDestination(x), KeyCode::Enter) => {
+                if let Some((i, j)) = current {
+                    if let Err(e) = self.review.set_destination(i, j, &x) {
+                        self.message = e;
+                    }
+                }
+                self.mode = Mode::Browse;
+            }
+            (Mode::FilterExtension(x), KeyCode::Enter) => {
+                let extension = x.trim().trim_start_matches('.').to_string();
+                self.set_filter(ReviewFilter {
+                    extension: Some(extension).filter(|y| !y.is_empty()),
+                    ..self.review.get_filter().clone()
+                });
+                self.mode = Mode::Browse;
+            }
+            (Mode::ConfirmApply, KeyCode::Char('y')) => return Step::Apply,
+            (Mode::Browse, KeyCode::Char('q')) | (Mode::Browse, KeyCode::Esc) => return Step::Quit,
+            (Mode::Browse, KeyCode::Up) | (Mode::Browse, KeyCode::Char('k')) => {
+                self.cursor = self.cursor.saturating_sub(1);
+            }
+            (Mode::Browse, KeyCode::Down) | (Mode::Browse, KeyCode::Char('j')) => {
+                self.cursor = (self.cursor + 1).min(visible.len().saturating_sub(1));
+            }
+            (Mode::Browse, KeyCode::Home) => self.cursor = 0,
+            (Mode::Browse, KeyCode::End) => self.cursor = visible.len().saturating_sub(1),
+            (Mode::Browse, KeyCode::Char(' ')) => {
+                if let Some((i, j)) = current {
+                    self.review.toggle(i, j);
+                    self.cursor = (self.cursor + 1).min(visible.len().saturating_sub(1));
+                }
+            }
+            (Mode::Browse, KeyCode::Char('s')) => self.review.select_visible(true),
+            (Mode::Browse, KeyCode::Char('u')) => self.review.select_visible(false),
+            (Mode::Browse, KeyCode::Char('e')) => {
+                if let Some((i, j)) = current {
+                    let operation = &self.review.get_groups()[i].items[j].operation;
+                    match operation.action {
+                        Action::Archive => {
+                            self.message = String::from(
+                                "The files of an archive rule share their destination.",
+                            )
+                        }
+                        _ => self.mode = Mode::EditDestination(operation.destination.clone()),
+                    }
+                }
+            }
+            (Mode::Browse, KeyCode::Char('r')) => {
+                let lines = self.review.lines();
+                let line = match self.review.get_filter().line {
+                    None => lines.first().copied(),
+                    Some(x) => lines.iter().copied().find(|y| *y > x),
+                };
+                self.set_filter(ReviewFilter {
+                    line,
+                    ..self.review.get_filter().clone()
+                });
+            }
+            (Mode::Browse, KeyCode::Char('t')) => {
+                let extension = self.review.get_filter().extension.clone();
+                self.mode = Mode::FilterExtension(extension.unwrap_or_default());
+            }
+            (Mode::Browse, KeyCode::Char('c')) => self.set_filter(ReviewFilter::default()),
+            (Mode::Browse, KeyCode::Enter) => self.mode = Mode::ConfirmApply,
+            (Mode::Browse, _) => (),
+            (_, KeyCode::Esc) | (Mode::ConfirmApply, _) => self.mode = Mode::Browse,
+            _ => (),
+        }
+        Step::Continue
+    }
+
+    /// Change the filter, and go back to the first operation shown
+    fn set_filter(&mut self, filter: ReviewFilter) {
+        self.review.set_filter(filter);
+        self.cursor = 0;
+        self.offset = 0;
+    }
+
+    /// Return the lines of the plan, and the index of the line of the current operation
+    pub fn rows(&self) -> (Vec<String>, Option<usize>) {
+        let visible = self.review.visible();
+        let groups = self.review.get_groups();
+        let filter = self.review.get_filter();
+        let mut rows: Vec<String> = vec![];
+        let mut cursor_row: Option<usize> = None;
+        for (i, group) in groups.iter().enumerate() {
+            let line = *group.rule.get_line_number();
+            if filter.line.iter().any(|x| *x != line) {
+                continue;
+            }
+            let items: Vec<usize> = visible.iter().filter(|x| x.0 == i).map(|x| x.1).collect();
+            match &group.error {
+                Some(e) => rows.push(format!(
+                    "line {}: {}  (error: {})",
+                    line,
+                    group.rule.get_line_value(),
+                    e
+                )),
+                None if items.is_empty() => continue,
+                None => rows.push(format!(
+                    "line {}: {}  ({}/{} selected)",
+                    line,
+                    group.rule.get_line_value(),
+                    group.items.iter().filter(|x| x.selected).count(),
+                    group.items.len()
+                )),
+            }
+            for j in items {
+                let item = &group.items[j];
+                if visible.get(self.cursor) == Some(&(i, j)) {
+                    cursor_row = Some(rows.len());
+                }
+                rows.push(format!(
+                    "  [{}] {}",
+                    if item.selected { "x" } else { " " },
+                    item.operation.describe()
+                ));
+            }
+        }
+        (rows, cursor_row)
+    }
+
+    /// Return the last line, with the help, the text being typed or a message
+    fn status(&self) -> String {
+        match &self.mode {
+            Mode::EditDestination(x) => format!("destination: {}_", x),
+            Mode::FilterExtension(x) => format!("file type: {}_", x),
+            Mode::ConfirmApply => format!(
+                "Apply {} operation(s) ? [y]es, any other key to go back",
+                self.review.selected()
+            ),
+            Mode::Browse if !self.message.is_empty() => self.message.clone(),
+            Mode::Browse => HELP.to_string(),
+        }
+    }
+
+    fn draw(&mut self, stdout: &mut io::Stdout, width: usize, height: usize) -> io::Result<()> {
+        let (rows, cursor_row) = self.rows();
+        let body = height.saturating_sub(2).max(1);
+        if let Some(x) = cursor_row {
+            if x < self.offset {
+                self.offset = x;
+            } else if x >= self.offset + body {
+                self.offset = x + 1 - body;
+            }
+        }
+        let filter = self.review.get_filter();
+        let header = format!(
+            "crabby tui - {} - {} selected{}{}",
+            self.title,
+            self.review.selected(),
+            filter
+                .line
+                .map(|x| format!(" - rule line {}", x))
+                .unwrap_or_default(),
+            filter
+                .extension
+                .as_ref()
+                .map(|x| format!(" - *.{}", x))
+                .unwrap_or_default()
+        );
+        let fit = |x: &str| x.chars().take(width).collect::<String>();
+        queue!(
+            stdout,
+            terminal::Clear(ClearType::All),
+            cursor::MoveTo(0, 0),
+            SetAttribute(Attribute::Bold),
+            Print(fit(&header)),
+            SetAttribute(Attribute::Reset)
+        )?;
+        if rows.is_empty() {
+            queue!(stdout, cursor::MoveTo(0, 1), Print(fit("No operation.")))?;
+        }
+        for (y, row) in rows.iter().enumerate().skip(self.offset).take(body) {
+            queue!(stdout, cursor::MoveTo(0, (y - self.offset + 1) as u16))?;
+            if Some(y) == cursor_row {
+                queue!(
+                    stdout,
+                    SetAttribute(Attribute::Reverse),
+                    Print(fit(row)),
+                    SetAttribute(Attribute::Reset)
+                )?;
+            } else {
+                queue!(stdout, Print(fit(row)))?;
+            }
+        }
+        queue!(
+            stdout,
+            cursor::MoveTo(0, height.saturating_sub(1) as u16),
+            Print(fit(&self.status()))
+        )?;
+        stdout.flush()
+    }
+}
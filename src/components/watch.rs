@@ -0,0 +1,443 @@
+use crate::components::confirm::Confirmation;
+use crate::components::control::{
+    Report, ReportEntry, Request, Response, RuleStats, Status, TargetStats,
+};
+use crate::components::evaluation::Selection;
+use crate::components::output::{Output, OutputFormat};
+use crate::components::plan::Outcome;
+use crate::components::{Config, Rule};
+use chrono::Local;
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+/// Number of errors kept for the `status` of the control socket
+const RECENT_ERRORS: usize = 20;
+
+/// Extensions of the files that are still being downloaded
+const PARTIAL_EXTENSIONS: [&str; 4] = ["part", "crdownload", "download", "tmp"];
+
+/// A Watcher applies the rules of a `Configuration` to the files that appear in some folders.
+/// A file is only handled once it was closed and its size stopped changing during the settle
+/// delay, so partially written downloads are left alone.
+pub struct Watcher {
+    targets: Vec<PathBuf>,
+    config_file: PathBuf,
+    config: Config,
+    settle: Duration,
+    pending: HashMap<PathBuf, (u64, Instant)>,
+    produced: HashSet<PathBuf>,
+    dry_run: bool,
+    paused: bool,
+    socket: Option<PathBuf>,
+    stats: BTreeMap<(String, usize), RuleStats>,
+    recent_errors: VecDeque<String>,
+    last_report: Option<Report>,
+    output: Output,
+}
+
+impl Watcher {
+    /// Create a new `Watcher` of some folders, with the rules of a `.crc` file
+    pub fn new(targets: Vec<PathBuf>, config_file: PathBuf, dry_run: bool) -> io::Result<Self> {
+        let targets = targets
+            .iter()
+            .map(fs::canonicalize)
+            .collect::<io::Result<Vec<PathBuf>>>()?;
+        let config_file = fs::canonicalize(config_file)?;
+        let config = Config::from_file(&config_file.to_string_lossy())?;
+        Ok(Self {
+            targets,
+            config_file,
+            config,
+            settle: Duration::from_secs(1),
+            pending: HashMap::new(),
+            produced: HashSet::new(),
+            dry_run,
+            paused: false,
+            socket: None,
+            stats: BTreeMap::new(),
+            recent_errors: VecDeque::new(),
+            last_report: None,
+            output: Output::new(OutputFormat::Text),
+        })
+    }
+
+    /// Set how long the size of a file must stay the same before it is handled
+    pub fn set_settle(&mut self, settle: Duration) {
+        self.settle = settle;
+    }
+
+    /// Set the path of the socket the `Watcher` answers control requests on while it runs
+    pub fn set_socket(&mut self, socket: PathBuf) {
+        self.socket = Some(socket);
+    }
+
+    /// Set the format of the events written while the `Watcher` runs.
+    /// The JSON format writes one event per line, since the `Watcher` never finishes.
+    pub fn set_output(&mut self, format: OutputFormat) {
+        self.output = Output::new(match format {
+            OutputFormat::Json => OutputFormat::Ndjson,
+            x => x,
+        });
+    }
+
+    /// Only apply the rules of a `Selection`, even after the `.crc` file is reloaded
+    pub fn set_selection(&mut self, selection: Selection) {
+        self.config.set_selection(selection);
+    }
+
+    /// Return the `Configuration` currently applied
+    pub fn get_config(&self) -> &Config {
+        &self.config
+    }
+
+    /// Check if a file is a download in progress, such as `file.pdf.part` or `file.crdownload`
+    pub fn is_partial_download(path: &Path) -> bool {
+        match path.extension() {
+            Some(x) => PARTIAL_EXTENSIONS.iter().any(|y| x == *y),
+            None => false,
+        }
+    }
+
+    /// Record that a file was written or moved in a watched folder
+    pub fn notice(&mut self, path: PathBuf) {
+        if path == self.config_file {
+            if let Err(e) = self.reload() {
+                self.output.message("error", e);
+            }
+            return;
+        }
+        if Self::is_partial_download(&path) || self.produced.remove(&path) {
+            return;
+        }
+        if let Ok(metadata) = fs::metadata(&path) {
+            if metadata.is_file() {
+                self.pending.insert(path, (metadata.len(), Instant::now()));
+            }
+        }
+    }
+
+    /// Return the noticed files whose size did not change during the settle delay
+    pub fn ready_files(&mut self) -> Vec<PathBuf> {
+        let mut ready: Vec<PathBuf> = vec![];
+        let now = Instant::now();
+        let settle = self.settle;
+        self.pending.retain(|path, (size, seen)| {
+            if now.duration_since(*seen) < settle {
+                return true;
+            }
+            match fs::metadata(path) {
+                Ok(metadata) if metadata.len() == *size => {
+                    ready.push(path.clone());
+                    false
+                }
+                Ok(metadata) => {
+                    *size = metadata.len();
+                    *seen = now;
+                    true
+                }
+                Err(_) => false,
+            }
+        });
+        ready.sort();
+        ready
+    }
+
+    /// Apply the rules that concern a file, except the scheduled ones, and return how many were applied.
+    /// The files created by the rules in the folder are not handled again.
+    pub fn handle(&mut self, file: &Path) -> usize {
+        let dir = file.parent().unwrap_or_else(|| Path::new("."));
+        let target = dir.to_string_lossy().to_string();
+        let before = Self::listing(dir);
+        let file = file.to_string_lossy().to_string();
+        let entries = self.apply(&target, Some(&file));
+        let after = Self::listing(dir);
+        self.produced.extend(after.difference(&before).cloned());
+        let applied = entries.len();
+        self.report("new-file", entries);
+        applied
+    }
+
+    /// Apply the rules, except the scheduled ones, to all the files of a watched folder
+    pub fn run_now(&mut self, target: &str) -> Result<Report, String> {
+        let dir =
+            fs::canonicalize(target).map_err(|e| format!("could not find `{}`: {}", target, e))?;
+        if !self.targets.contains(&dir) {
+            return Err(format!("`{}` is not watched", target));
+        }
+        let target = dir.to_string_lossy().to_string();
+        let before = Self::listing(&dir);
+        let entries = self.apply(&target, None);
+        let after = Self::listing(&dir);
+        self.produced.extend(after.difference(&before).cloned());
+        self.report("run-now", entries);
+        Ok(self.last_report.clone().unwrap_or_default())
+    }
+
+    /// Apply the rules, except the scheduled ones, to the files of a folder or only one of them, following the
+    /// evaluation mode of the configuration. Return an entry for each rule that concerned a file.
+    fn apply(&mut self, target: &str, file: Option<&str>) -> Vec<ReportEntry> {
+        let mut entries = vec![];
+        let config = self.config.clone();
+        config.evaluate(target, file, &mut |rule, _, candidates| {
+            let files: Vec<String> = candidates
+                .into_iter()
+                .filter(|x| *rule.is_valid() && rule.concerns(x))
+                .collect();
+            if files.is_empty() {
+                return vec![];
+            }
+            let report = rule.execute_on(
+                files.clone(),
+                self.dry_run,
+                &mut Confirmation::none(),
+                &mut self.output,
+            );
+            entries.push(self.record(target, rule, files, report.succeeded()));
+            report
+                .operations
+                .into_iter()
+                .filter(|x| matches!(x.1, Outcome::Applied | Outcome::Planned))
+                .map(|x| x.0)
+                .collect()
+        });
+        entries
+    }
+
+    /// Update the statistics of a rule applied to some files of a folder
+    fn record(
+        &mut self,
+        target: &str,
+        rule: &Rule,
+        files: Vec<String>,
+        succeeded: bool,
+    ) -> ReportEntry {
+        let line = *rule.get_line_number();
+        // The statistics of a named rule follow it when a reload moved it to another line
+        let moved = self
+            .stats
+            .iter()
+            .find(|(x, y)| {
+                x.0 == target && x.1 != line && y.name.is_some() && y.name == *rule.get_name()
+            })
+            .map(|x| x.0.clone());
+        if let Some(mut stats) = moved.and_then(|x| self.stats.remove(&x)) {
+            stats.line = line;
+            self.stats.insert((target.to_string(), line), stats);
+        }
+        let stats = self
+            .stats
+            .entry((target.to_string(), line))
+            .or_insert_with(|| RuleStats {
+                line,
+                name: rule.get_name().clone(),
+                rule: rule.get_line_value().to_string(),
+                ..RuleStats::default()
+            });
+        stats.name = rule.get_name().clone();
+        stats.rule = rule.get_line_value().to_string();
+        stats.runs += 1;
+        stats.files += files.len() as u64;
+        if !succeeded {
+            stats.errors += 1;
+            if self.recent_errors.len() == RECENT_ERRORS {
+                self.recent_errors.pop_front();
+            }
+            let label = match rule.get_name() {
+                Some(x) => format!("rule `{}`", x),
+                None => format!("rule line {}", line),
+            };
+            self.recent_errors.push_back(format!(
+                "{}: {} failed on {}",
+                Local::now().to_rfc3339(),
+                label,
+                files.join(", ")
+            ));
+        }
+        ReportEntry {
+            target: target.to_string(),
+            line,
+            name: rule.get_name().clone(),
+            rule: rule.get_line_value().to_string(),
+            files: files.len(),
+            succeeded,
+        }
+    }
+
+    /// Remember what the last rules applied did, when some were applied
+    fn report(&mut self, trigger: &str, entries: Vec<ReportEntry>) {
+        if !entries.is_empty() {
+            self.last_report = Some(Report {
+                time: Local::now().to_rfc3339(),
+                trigger: trigger.to_string(),
+                entries,
+            });
+        }
+    }
+
+    /// Return the state of the `Watcher`, with the statistics of the rules of each folder
+    pub fn status(&self) -> Status {
+        let targets = self
+            .targets
+            .iter()
+            .map(|x| {
+                let target = x.to_string_lossy().to_string();
+                TargetStats {
+                    rules: self
+                        .stats
+                        .iter()
+                        .filter(|y| (y.0).0 == target)
+                        .map(|y| y.1.clone())
+                        .collect(),
+                    target,
+                }
+            })
+            .collect();
+        Status {
+            paused: self.paused,
+            queue_depth: self.pending.len(),
+            targets,
+            recent_errors: self.recent_errors.iter().cloned().collect(),
+        }
+    }
+
+    /// Answer a request received on the control socket
+    pub fn answer(&mut self, request: Request) -> Response {
+        match request {
+            Request::Status => Response {
+                status: Some(self.status()),
+                ..Response::ok()
+            },
+            Request::Pause => {
+                self.paused = true;
+                Response::ok()
+            }
+            Request::Resume => {
+                self.paused = false;
+                Response::ok()
+            }
+            Request::Reload => match self.reload() {
+                Ok(_) => Response::ok(),
+                Err(e) => Response::error(e),
+            },
+            Request::RunNow { target } => match self.run_now(&target) {
+                Ok(x) => Response {
+                    report: Some(x),
+                    ..Response::ok()
+                },
+                Err(e) => Response::error(e),
+            },
+            Request::LastReport => match &self.last_report {
+                Some(x) => Response {
+                    report: Some(x.clone()),
+                    ..Response::ok()
+                },
+                None => Response::error(String::from("no rule was applied yet")),
+            },
+        }
+    }
+
+    /// Read the `.crc` file again, keeping the current rules if it became unreadable
+    pub fn reload(&mut self) -> Result<(), String> {
+        match Config::from_file(&self.config_file.to_string_lossy()) {
+            Ok(mut x) => {
+                let text = format!("watch: reloaded `{}`.", self.config_file.display());
+                self.output.message("info", text);
+                x.set_selection(self.config.get_selection().clone());
+                self.config = x;
+                Ok(())
+            }
+            Err(e) => Err(format!(
+                "could not reload `{}`: {}",
+                self.config_file.display(),
+                e
+            )),
+        }
+    }
+
+    /// Return the paths of the entries of a folder
+    fn listing(dir: &Path) -> HashSet<PathBuf> {
+        match fs::read_dir(dir) {
+            Ok(x) => x.filter_map(|y| y.ok()).map(|y| y.path()).collect(),
+            Err(_) => HashSet::new(),
+        }
+    }
+
+    /// Watch the folders with inotify until the process is stopped, answering the control requests
+    #[cfg(target_os = "linux")]
+    pub fn run(&mut self) -> io::Result<()> {
+        use crate::components::control::ControlServer;
+        use inotify::{Inotify, WatchMask};
+        use std::thread;
+
+        let server = match &self.socket {
+            Some(x) => match ControlServer::bind(x) {
+                Ok(y) => Some(y),
+                Err(e) => {
+                    let text = format!("could not open the control socket: {}", e);
+                    self.output.message("error", text);
+                    None
+                }
+            },
+            None => None,
+        };
+
+        let mut inotify = Inotify::init()?;
+        let mask = WatchMask::CLOSE_WRITE | WatchMask::MOVED_TO;
+        let mut watched = vec![];
+        for target in self.targets.clone() {
+            watched.push((inotify.watches().add(&target, mask)?, target.clone()));
+            let text = format!("watch: watching `{}`.", target.display());
+            self.output.message("info", text);
+        }
+        if let Some(config_dir) = self.config_file.parent() {
+            if !self.targets.iter().any(|x| x == config_dir) {
+                watched.push((inotify.watches().add(config_dir, mask)?, config_dir.into()));
+            }
+        }
+
+        let mut buffer = [0u8; 4096];
+        loop {
+            match inotify.read_events(&mut buffer) {
+                Ok(events) => {
+                    for event in events {
+                        let dir = watched.iter().find(|x| x.0 == event.wd).map(|x| &x.1);
+                        if let (Some(dir), Some(name)) = (dir, event.name) {
+                            let path = dir.join(name);
+                            if path == self.config_file || self.targets.contains(dir) {
+                                self.notice(path);
+                            }
+                        }
+                    }
+                }
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => {}
+                Err(e) => return Err(e),
+            }
+            if let Some(server) = &server {
+                for e in server.serve(|x| self.answer(x)) {
+                    let text = format!("could not answer a control request: {}", e);
+                    self.output.message("error", text);
+                }
+            }
+            if self.paused {
+                thread::sleep(Duration::from_millis(200));
+                continue;
+            }
+            for file in self.ready_files() {
+                self.handle(&file);
+            }
+            thread::sleep(Duration::from_millis(200));
+        }
+    }
+
+    /// Watch the folders until the process is stopped
+    #[cfg(not(target_os = "linux"))]
+    pub fn run(&mut self) -> io::Result<()> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "watch mode is only available on Linux",
+        ))
+    }
+}
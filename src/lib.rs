@@ -1,7 +1,56 @@
 pub use crate::{
-    app::App,
-    components::{Config, Rule},
+    app::{App, AppBuilder, Command},
+    components::{
+        archive::{
+            create_archive, expand_date_placeholders, extract_archive, parse_size, ArchiveFormat,
+            ArchiveOptions, ExtractOptions,
+        },
+        check::{check, Diagnostic, Severity},
+        confirm::{Answer, Confirmation, Confirmer, ScriptedConfirmer, TerminalConfirmer},
+        control::{
+            send, socket_path, Report, ReportEntry, Request, Response, RuleStats, Status,
+            TargetStats,
+        },
+        copy::{
+            checksum, checksum_in, copy_file, copy_file_in, ConflictPolicy, CopyOptions,
+            CopyOutcome, LinkMode,
+        },
+        dedupe::{
+            choose_kept, choose_kept_in, find_duplicates, find_duplicates_in, remove_duplicate,
+            remove_duplicate_in, DedupeOptions, KeepPolicy,
+        },
+        evaluation::{Evaluation, Flow, Scope, Selection},
+        explain::{explain, Decision, RuleExplanation},
+        filesystem::{FileInfo, Filesystem, MemFs, StdFs},
+        format::format,
+        guard::protection,
+        init::{
+            render_scaffold, scaffold, select_proposals, user_dirs, user_dirs_in, Proposal,
+            Scaffold,
+        },
+        lsp::{read_message, write_message, LanguageServer},
+        matching::Matching,
+        normalize::{normalized_name, normalized_path, NormalizeOptions, UnicodeForm},
+        observer::Observer,
+        output::{Event, Output, OutputFormat, SCHEMA_VERSION},
+        plan::{Action, Operation, Outcome, RuleReport},
+        review::{Review, ReviewFilter, ReviewGroup, ReviewItem},
+        schedule::{parse_duration, state_file, Every, Schedule, ScheduleState, Scheduler},
+        service::{
+            install_service, install_service_in, installed_units, installed_units_in,
+            journal_filter, render_service, render_timer, uninstall_service, uninstall_service_in,
+            unit_dir, validate_service, ServiceAction, ServiceOptions,
+        },
+        syntax::{LineKind, SyntaxLine, SyntaxTree, Word},
+        trash::{trash, trash_dir, trash_in},
+        tui::{Step, Tui},
+        watch::Watcher,
+        Config, Rule,
+    },
 };
 
+#[cfg(unix)]
+pub use crate::components::control::ControlServer;
+
 mod app;
 mod components;
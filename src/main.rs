@@ -1,6 +1,7 @@
 use ::crabby::App;
+use std::process;
 
 fn main() {
     let app: App = App::new();
-    app.launch();
+    process::exit(app.launch());
 }
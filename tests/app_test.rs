@@ -1,15 +1,20 @@
 use crabby::App;
 
+mod common;
+
 #[test]
 fn app_get_configurations_works() {
-    let app: App = App::new_for_test();
+    let app: App = App::new_for_test_in(common::default_fs());
     assert!(!app.get_configurations().is_empty());
 }
 
 #[test]
 fn app_get_configuration_works() {
-    let app: App = App::new_for_test();
-    assert_eq!(".\\default.crc", app.get_configuration(0).get_filename());
+    let app: App = App::new_for_test_in(common::default_fs());
+    assert_eq!(
+        &common::default_crc(),
+        app.get_configuration(0).get_filename()
+    );
 }
 
 #[test]
@@ -31,7 +36,7 @@ fn app_get_arg_works() {
 
 #[test]
 fn app_default_conf_lines_works() {
-    assert_eq!(String::from("mv file.mp3 to C:\\\\Users\\\\Username\\\\Music      // This rule moves `file.mp3` to the Music folder"),App::default_conf_lines().1);
+    assert_eq!(String::from("mv file.mp3 to C:\\\\Users\\\\Username\\\\Music      // This rule moves `file.mp3` to the Music folder"),App::default_conf_lines_in(&*common::default_fs()).1);
 }
 
 #[test]
@@ -0,0 +1,141 @@
+use chrono::{Local, TimeZone};
+use crabby::{create_archive, expand_date_placeholders, ArchiveFormat, ArchiveOptions, Rule};
+use std::fs::{self, File};
+use std::path::PathBuf;
+
+mod common;
+
+#[test]
+fn expand_date_placeholders_works() {
+    let now = Local.with_ymd_and_hms(2021, 8, 3, 10, 0, 0).unwrap();
+    assert_eq!(
+        "logs-2021-08.tar.gz",
+        expand_date_placeholders("logs-{now:%Y-%m}.tar.gz", &now).unwrap()
+    );
+    assert!(expand_date_placeholders("logs-{now:%Q}.zip", &now).is_err());
+}
+
+#[test]
+fn create_zip_archive_works() {
+    let dir = common::temp_dir("archive-zip");
+    fs::write(dir.join("a.log"), "first").unwrap();
+    fs::write(dir.join("b.log"), "second").unwrap();
+    let files: Vec<PathBuf> = vec![dir.join("a.log"), dir.join("b.log")];
+    let options = ArchiveOptions {
+        remove: true,
+        append: false,
+    };
+    create_archive(ArchiveFormat::Zip, &dir.join("logs.zip"), &files, &options).unwrap();
+    assert!(!dir.join("a.log").exists());
+    assert!(!dir.join("b.log").exists());
+    let zip = zip::ZipArchive::new(File::open(dir.join("logs.zip")).unwrap()).unwrap();
+    assert_eq!(2, zip.len());
+}
+
+#[test]
+fn append_tar_gz_archive_works() {
+    let dir = common::temp_dir("archive-tar-gz");
+    let options = ArchiveOptions {
+        remove: false,
+        append: true,
+    };
+    fs::write(dir.join("a.log"), "first").unwrap();
+    create_archive(
+        ArchiveFormat::TarGz,
+        &dir.join("logs.tar.gz"),
+        &[dir.join("a.log")],
+        &options,
+    )
+    .unwrap();
+    fs::write(dir.join("b.log"), "second").unwrap();
+    create_archive(
+        ArchiveFormat::TarGz,
+        &dir.join("logs.tar.gz"),
+        &[dir.join("b.log")],
+        &options,
+    )
+    .unwrap();
+    let file = File::open(dir.join("logs.tar.gz")).unwrap();
+    let mut tar = tar::Archive::new(flate2::read::GzDecoder::new(file));
+    assert_eq!(2, tar.entries().unwrap().count());
+    assert!(dir.join("a.log").exists());
+}
+
+#[test]
+fn rule_archive_into_works() {
+    let dir = common::temp_dir("rule-archive");
+    fs::write(dir.join("screenshot.png"), "pixels").unwrap();
+    let line = format!(
+        "zip *.png into {} remove",
+        dir.join("shots-{now:%Y}.zip").display()
+    );
+    let mut rule: Rule = Rule::new_from_line(&line, &0);
+    assert!(*rule.is_valid());
+    rule.execute(dir.to_string_lossy().to_string(), false, false);
+    let archive = dir.join(format!("shots-{}.zip", Local::now().format("%Y")));
+    assert!(archive.exists());
+    assert!(!dir.join("screenshot.png").exists());
+}
+
+#[test]
+fn append_same_name_works() {
+    let dir = common::temp_dir("archive-same-name");
+    let options = ArchiveOptions {
+        remove: false,
+        append: true,
+    };
+    fs::write(dir.join("a.log"), "first").unwrap();
+    create_archive(
+        ArchiveFormat::Zip,
+        &dir.join("logs.zip"),
+        &[dir.join("a.log")],
+        &options,
+    )
+    .unwrap();
+    fs::write(dir.join("a.log"), "second").unwrap();
+    create_archive(
+        ArchiveFormat::Zip,
+        &dir.join("logs.zip"),
+        &[dir.join("a.log")],
+        &options,
+    )
+    .unwrap();
+    let zip = zip::ZipArchive::new(File::open(dir.join("logs.zip")).unwrap()).unwrap();
+    let mut names: Vec<String> = zip.file_names().map(|x| x.unwrap().to_string()).collect();
+    names.sort();
+    assert_eq!(vec!["a (1).log", "a.log"], names);
+
+    let missing = create_archive(
+        ArchiveFormat::TarGz,
+        &dir.join("logs.tar.gz"),
+        &[dir.join("missing.log")],
+        &options,
+    );
+    assert!(missing.is_err());
+    assert!(!dir.join(".logs.tar.gz.crabby").exists());
+}
+
+#[test]
+fn append_failure_works() {
+    let dir = common::temp_dir("archive-append-failure");
+    let options = ArchiveOptions {
+        remove: true,
+        append: true,
+    };
+    fs::write(dir.join("a.log"), "a").unwrap();
+    fs::write(dir.join("b.log"), "b").unwrap();
+    let archive = dir.join("logs.zip");
+    create_archive(ArchiveFormat::Zip, &archive, &[dir.join("a.log")], &options).unwrap();
+    let failed = create_archive(
+        ArchiveFormat::Zip,
+        &archive,
+        &[dir.join("b.log"), dir.join("missing.log")],
+        &options,
+    );
+    assert!(failed.is_err());
+    let zip = zip::ZipArchive::new(File::open(&archive).unwrap()).unwrap();
+    let names: Vec<String> = zip.file_names().map(|x| x.unwrap().to_string()).collect();
+    assert_eq!(vec!["a.log"], names);
+    assert!(dir.join("b.log").exists());
+    assert!(!dir.join(".logs.zip.crabby").exists());
+}
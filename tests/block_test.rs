@@ -0,0 +1,111 @@
+use crabby::{check, explain, format, App, Config, Decision, Filesystem, MemFs, Scope, Severity};
+use std::path::Path;
+use std::sync::Arc;
+
+mod common;
+
+fn home() -> Arc<MemFs> {
+    common::mem_fs(
+        &[
+            "home/a.pdf",
+            "home/Downloads/b.pdf",
+            "home/Shots/s1.png",
+            "home/Shots/2024/s2.png",
+            "home/Shots/.cache/s3.png",
+        ],
+        &["home/Docs"],
+    )
+}
+
+const RULES: &str = "mv *.pdf to home/Docs\nin ./Downloads {\n    pre *.pdf with dl_\n}\nin Shots recursive {\n    suf *.png with _old\n}\n";
+
+#[test]
+fn block_works() {
+    let fs = home();
+    fs.add_file("home/rules.crc", RULES);
+    App::builder()
+        .filesystem(fs.clone())
+        .target("home")
+        .config_file("home/rules.crc")
+        .build()
+        .unwrap()
+        .launch();
+    for file in &[
+        "home/Docs/a.pdf",
+        "home/Downloads/dl_b.pdf",
+        "home/Shots/s1_old.png",
+        "home/Shots/2024/s2_old.png",
+        "home/Shots/.cache/s3.png",
+    ] {
+        assert!(fs.exists(Path::new(file)), "{}", file);
+    }
+}
+
+#[test]
+fn block_explain_works() {
+    let fs = home();
+    fs.add_file("home/rules.crc", RULES);
+    let config = Config::new_in(fs.clone(), String::from("home/rules.crc"));
+    assert_eq!(
+        &Some(Scope {
+            dir: String::from("home/Downloads"),
+            recursive: false
+        }),
+        config.get_rules()[1].get_scope()
+    );
+    let explanations = explain(&[config.clone()], "home", "home/Shots/2024/s2.png");
+    assert_eq!(
+        Decision::OutOfScope {
+            dir: String::from("home/Downloads"),
+            recursive: false
+        },
+        explanations[1].decision
+    );
+    assert_eq!(Decision::Matched, explanations[2].decision);
+    assert_eq!(
+        "home/Shots/2024/s2_old.png",
+        explanations[2].operations[0].destination
+    );
+    // The rules outside of the blocks only run in the target, even if the file follows their pattern
+    let explanations = explain(&[config], "home", "home/Downloads/b.pdf");
+    assert_eq!(
+        Decision::OutOfScope {
+            dir: String::from("home"),
+            recursive: false
+        },
+        explanations[0].decision
+    );
+    assert_eq!(Decision::Matched, explanations[1].decision);
+    assert_eq!(
+        "home/Downloads/dl_b.pdf",
+        explanations[1].operations[0].destination
+    );
+}
+
+#[test]
+fn block_check_and_format_works() {
+    let diagnostics = check(
+        "rules.crc",
+        "in A {\nmv *.pdf to X\nin B {\n}\n}\nmv a*.pdf to Y\nin C {\n",
+    );
+    let errors: Vec<(usize, &str)> = diagnostics
+        .iter()
+        .filter(|x| x.severity == Severity::Error)
+        .map(|x| (x.line, x.message.as_str()))
+        .collect();
+    assert_eq!(
+        vec![
+            (3, "blocks can not be nested"),
+            (5, "no block to close"),
+            (7, "unclosed block")
+        ],
+        errors
+    );
+    assert_eq!(errors.len(), diagnostics.len());
+
+    let formatted = format("in A {\nmv *.pdf to X // papers\n  // texts\n}\n").unwrap();
+    assert_eq!(
+        "in A {\n    mv *.pdf to X    // papers\n    // texts\n}\n",
+        formatted
+    );
+}
@@ -0,0 +1,73 @@
+use crabby::{App, Command, Config, Filesystem, MemFs};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+#[test]
+fn builder_works() {
+    let fs = Arc::new(MemFs::new());
+    fs.add_file("Downloads/a.pdf", "a");
+    fs.add_file("Downloads/b.txt", "b");
+    fs.add_dir("Docs");
+    fs.add_file("rules.crc", "pre *.txt with old_\n");
+    let app = App::builder()
+        .filesystem(fs.clone())
+        .target("Downloads")
+        .config_file("rules.crc")
+        .config_str("mv *.pdf to Docs\n")
+        .build()
+        .unwrap();
+    assert_eq!(2, app.get_configurations().len());
+    app.launch();
+    assert!(fs.exists(Path::new("Docs/a.pdf")));
+    assert!(fs.exists(Path::new("Downloads/old_b.txt")));
+}
+
+#[test]
+fn builder_dry_run_works() {
+    let fs = Arc::new(MemFs::new());
+    fs.add_file("Downloads/a.pdf", "a");
+    fs.add_dir("Docs");
+    App::builder()
+        .filesystem(fs.clone())
+        .target("Downloads")
+        .config_str("mv *.pdf to Docs\n")
+        .dry_run(true)
+        .build()
+        .unwrap()
+        .launch();
+    assert_eq!(vec![PathBuf::from("Downloads/a.pdf")], fs.files());
+}
+
+#[test]
+fn builder_errors_work() {
+    let fs = Arc::new(MemFs::new());
+    fs.add_dir("Downloads");
+    let builder = App::builder().filesystem(fs.clone());
+    assert!(builder.clone().target("Downloads").build().is_err());
+    assert!(builder
+        .clone()
+        .config_str("mv *.pdf to Docs")
+        .build()
+        .is_err());
+    assert!(builder
+        .clone()
+        .target("Downloads")
+        .config_file("missing.crc")
+        .build()
+        .is_err());
+    assert!(builder
+        .command(Command::Dupes {
+            pattern: String::from("*")
+        })
+        .target("Downloads")
+        .build()
+        .is_ok());
+}
+
+#[test]
+fn config_from_str_works() {
+    let config: Config = "mv *.pdf to ./Docs\nmv *.pdf to\n".parse().unwrap();
+    assert_eq!(2, config.get_rules().len());
+    assert!(config.get_rule(0).get_error_message().is_empty());
+    assert!(!config.get_rule(1).get_error_message().is_empty());
+}
@@ -0,0 +1,69 @@
+use crabby::{check, App, Command, MemFs, Severity};
+use std::sync::Arc;
+
+#[test]
+fn check_errors_work() {
+    let diagnostics = check(
+        "rules.crc",
+        "// Rules\npre *.txt wiht old_\nmvv *.mp3 to ./Music\nmv *.md to\ncp *.png to ./Img evry day\n",
+    );
+    let found: Vec<(usize, usize, usize, Option<&str>)> = diagnostics
+        .iter()
+        .map(|x| (x.line, x.column, x.length, x.help.as_deref()))
+        .collect();
+    assert_eq!(
+        vec![
+            (2, 11, 4, Some("did you mean `with`?")),
+            (3, 1, 3, Some("did you mean `mv`?")),
+            (
+                4,
+                11,
+                1,
+                Some("a `mv` rule is written `mv <pattern> to <pattern>`")
+            ),
+            (5, 19, 4, Some("did you mean `every`?")),
+        ],
+        found
+    );
+    assert!(diagnostics.iter().all(|x| x.severity == Severity::Error));
+    assert_eq!(
+        "error: invalid second keyword\n --> rules.crc:2:11\n  |\n2 | pre *.txt wiht old_\n  |           ^^^^ did you mean `with`?",
+        diagnostics[0].render()
+    );
+}
+
+#[test]
+fn check_warnings_work() {
+    let diagnostics = check(
+        "rules.crc",
+        "mv *.pdf to ./Docs\nmv report*.pdf to ./Reports\ncp *.png to ./Img\nmv *.png to ./Photos\nmv *.txt to ./Texts except a*\nmv a.txt to ./A\nmv invoice* to ./Invoices\n",
+    );
+    let found: Vec<(usize, &str)> = diagnostics
+        .iter()
+        .map(|x| (x.line, x.message.as_str()))
+        .collect();
+    assert_eq!(
+        vec![(2, "unreachable rule"), (7, "overlapping rules")],
+        found
+    );
+    assert!(diagnostics.iter().all(|x| x.severity == Severity::Warning));
+}
+
+#[test]
+fn check_command_works() {
+    let fs = Arc::new(MemFs::new());
+    fs.add_file("good.crc", "mv *.pdf to ./Docs\n");
+    fs.add_file("bad.crc", "mv *.pdf too ./Docs\n");
+    let app = |file: &str| {
+        App::builder()
+            .filesystem(fs.clone())
+            .command(Command::Check {
+                files: vec![file.to_string()],
+            })
+            .build()
+            .unwrap()
+    };
+    assert_eq!(0, app("good.crc").launch());
+    assert_eq!(1, app("bad.crc").launch());
+    assert_eq!(1, app("missing.crc").launch());
+}
@@ -0,0 +1,58 @@
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+use std::process;
+
+/// Return an empty folder in the system temporary directory, unique to a test
+#[allow(dead_code)]
+pub fn temp_dir(name: &str) -> PathBuf {
+    let dir = env::temp_dir().join(format!("crabby-{}-{}", name, process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+/// Return a filesystem in memory whose current folder holds a `default.crc` file with one rule
+#[allow(dead_code)]
+pub fn default_fs() -> std::sync::Arc<crabby::MemFs> {
+    let fs = crabby::MemFs::new();
+    fs.add_file(
+        "default.crc",
+        "mv file.mp3 to C:\\\\Users\\\\Username\\\\Music      // This rule moves `file.mp3` to the Music folder",
+    );
+    std::sync::Arc::new(fs)
+}
+
+/// Return the path of the `default.crc` file of `default_fs`
+#[allow(dead_code)]
+pub fn default_crc() -> String {
+    PathBuf::from(".")
+        .join("default.crc")
+        .to_string_lossy()
+        .to_string()
+}
+
+/// Return a filesystem in memory holding some files, whose content is their path, and some empty folders
+#[allow(dead_code)]
+pub fn mem_fs(files: &[&str], dirs: &[&str]) -> std::sync::Arc<crabby::MemFs> {
+    let fs = crabby::MemFs::new();
+    for file in files {
+        fs.add_file(file, file);
+    }
+    for dir in dirs {
+        fs.add_dir(dir);
+    }
+    std::sync::Arc::new(fs)
+}
+
+/// Clean a folder of a filesystem in memory with the rules of a `.crc` content
+#[allow(dead_code)]
+pub fn clean(fs: &std::sync::Arc<crabby::MemFs>, target: &str, rules: &str) {
+    crabby::App::builder()
+        .filesystem(fs.clone())
+        .target(target)
+        .config_str(rules)
+        .build()
+        .unwrap()
+        .launch();
+}
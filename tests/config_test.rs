@@ -1,14 +1,20 @@
-use crabby::App;
+use crabby::{explain, App, Config};
+use std::fs;
+
+mod common;
 
 #[test]
 fn config_get_filename_works() {
-    let app: App = App::new_for_test();
-    assert_eq!(".\\default.crc", app.get_configuration(0).get_filename());
+    let app: App = App::new_for_test_in(common::default_fs());
+    assert_eq!(
+        &common::default_crc(),
+        app.get_configuration(0).get_filename()
+    );
 }
 
 #[test]
 fn config_get_rule_works() {
-    let app: App = App::new_for_test();
+    let app: App = App::new_for_test_in(common::default_fs());
     assert_eq!(
         "mv file.mp3 to C:\\\\Users\\\\Username\\\\Music",
         app.get_configuration(0).get_rule(0).get_line_value()
@@ -17,9 +23,45 @@ fn config_get_rule_works() {
 
 #[test]
 fn config_get_rules_works() {
-    let app: App = App::new_for_test();
+    let app: App = App::new_for_test_in(common::default_fs());
     assert_eq!(
         "mv file.mp3 to C:\\\\Users\\\\Username\\\\Music",
         app.get_configuration(0).get_rules()[0].get_line_value()
     );
 }
+
+#[test]
+fn config_include_works() {
+    let dir = common::temp_dir("config-include");
+    fs::write(dir.join("common.crc"), "// shared\nmv *.crc to ./Rules\n").unwrap();
+    fs::write(
+        dir.join("rules.crc"),
+        "include common.crc\nmv *.pdf to ./Docs\n",
+    )
+    .unwrap();
+    let config = Config::from_file(&dir.join("rules.crc").to_string_lossy()).unwrap();
+    let rules = config.get_rules();
+    assert_eq!(
+        &dir.join("common.crc").to_string_lossy().to_string(),
+        rules[0].get_config_file()
+    );
+    assert_eq!(&2, rules[0].get_line_number());
+    assert_eq!(
+        &dir.join("rules.crc").to_string_lossy().to_string(),
+        rules[1].get_config_file()
+    );
+
+    let explanations = explain(&[config], "", &dir.join("common.crc").to_string_lossy());
+    assert_eq!(
+        dir.join("common.crc").to_string_lossy(),
+        explanations[0].config
+    );
+    assert!(!config_concerns(&dir, "common.crc"));
+    assert!(!config_concerns(&dir, "rules.crc"));
+}
+
+/// Check if the first rule of the `rules.crc` file of a folder touches one of its files
+fn config_concerns(dir: &std::path::Path, file: &str) -> bool {
+    let config = Config::from_file(&dir.join("rules.crc").to_string_lossy()).unwrap();
+    config.get_rules()[0].concerns(&dir.join(file).to_string_lossy())
+}
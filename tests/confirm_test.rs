@@ -0,0 +1,67 @@
+use crabby::{
+    Answer, Config, Confirmation, Confirmer, Output, OutputFormat, Rule, ScriptedConfirmer,
+    TerminalConfirmer,
+};
+use std::fs;
+use std::io::Cursor;
+
+mod common;
+
+#[test]
+fn scripted_confirmer_works() {
+    let dir = common::temp_dir("confirm-scripted");
+    for file in &["a.txt", "b.txt", "c.txt", "a.md", "b.md", "a.log"] {
+        fs::write(dir.join(file), "content").unwrap();
+    }
+    fs::write(
+        dir.join("rules.crc"),
+        "pre *.txt with old_\npre *.md with old_\npre *.log with old_\n",
+    )
+    .unwrap();
+    let config = Config::from_file(&dir.join("rules.crc").to_string_lossy()).unwrap();
+    let mut confirmer = ScriptedConfirmer::new(vec![Answer::No, Answer::YesToRule, Answer::Quit]);
+    let mut confirmation = Confirmation::new(&mut confirmer);
+    let mut output = Output::buffered(OutputFormat::Text);
+    config.execute_rules_with(
+        dir.to_string_lossy().to_string(),
+        false,
+        &mut confirmation,
+        &mut output,
+    );
+    assert!(confirmation.is_quit());
+    assert_eq!(3, confirmer.get_asked().len());
+    let renamed = ["a.txt", "b.txt", "c.txt"]
+        .iter()
+        .filter(|x| dir.join(format!("old_{}", x)).exists())
+        .count();
+    assert_eq!(2, renamed);
+    assert!(dir.join("a.md").exists());
+    assert!(dir.join("b.md").exists());
+    assert!(dir.join("a.log").exists());
+}
+
+#[test]
+fn confirmation_yes_to_all_works() {
+    let rule: Rule = Rule::new_from_line(&"pre *.txt with old_".to_string(), &0);
+    let operations = rule
+        .plan(&["a.txt".to_string(), "b.txt".to_string()])
+        .unwrap();
+    let mut confirmer = ScriptedConfirmer::new(vec![Answer::YesToAll]);
+    let mut confirmation = Confirmation::new(&mut confirmer);
+    confirmation.start_rule();
+    assert!(confirmation.accepts(&rule, &operations[0]));
+    confirmation.start_rule();
+    assert!(confirmation.accepts(&rule, &operations[1]));
+    assert_eq!(1, confirmer.get_asked().len());
+}
+
+#[test]
+fn terminal_confirmer_works() {
+    let rule: Rule = Rule::new_from_line(&"pre *.txt with old_".to_string(), &0);
+    let operations = rule.plan(&["a.txt".to_string()]).unwrap();
+    let input = Cursor::new(b"maybe\nN\n".to_vec());
+    let mut confirmer = TerminalConfirmer::with_io(Box::new(input), Box::new(Vec::new()));
+    assert_eq!(Answer::NoToAll, confirmer.confirm(&rule, &operations[0]));
+    assert_eq!(Answer::Quit, confirmer.confirm(&rule, &operations[0]));
+    assert_eq!(Some(Answer::YesToRule), Answer::from_input("yes-to-rule"));
+}
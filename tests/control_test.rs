@@ -0,0 +1,98 @@
+use crabby::{send, ControlServer, Request, Response, Watcher};
+use std::fs;
+use std::io;
+use std::net::Shutdown;
+use std::os::unix::net::UnixStream;
+use std::thread;
+
+mod common;
+
+#[test]
+fn request_json_works() {
+    let request = Request::RunNow {
+        target: String::from("/home/user/Downloads"),
+    };
+    let json = serde_json::to_string(&request).unwrap();
+    assert_eq!(
+        r#"{"command":"run-now","target":"/home/user/Downloads"}"#,
+        json
+    );
+    assert_eq!(
+        Request::LastReport,
+        serde_json::from_str(r#"{"command":"last-report"}"#).unwrap()
+    );
+    assert_eq!(
+        r#"{"ok":true}"#,
+        serde_json::to_string(&Response::ok()).unwrap()
+    );
+}
+
+#[test]
+fn watcher_answer_works() {
+    let dir = common::temp_dir("control-answer");
+    fs::create_dir(dir.join("Docs")).unwrap();
+    let docs = dir.join("Docs").to_string_lossy().to_string();
+    fs::write(dir.join("rules.crc"), format!("mv *.pdf to {}\n", docs)).unwrap();
+    fs::write(dir.join("file.pdf"), "content").unwrap();
+    let mut watcher = Watcher::new(vec![dir.clone()], dir.join("rules.crc"), false).unwrap();
+
+    assert!(watcher.answer(Request::Pause).ok);
+    assert!(watcher.status().paused);
+    assert!(!watcher.answer(Request::LastReport).ok);
+    assert!(!watcher.answer(Request::RunNow { target: docs }).ok);
+
+    let target = dir.to_string_lossy().to_string();
+    let response = watcher.answer(Request::RunNow { target });
+    let report = response.report.unwrap();
+    assert_eq!("run-now", report.trigger);
+    assert_eq!(1, report.entries[0].files);
+    assert!(report.entries[0].succeeded);
+    assert!(dir.join("Docs").join("file.pdf").exists());
+
+    let status = watcher.answer(Request::Status).status.unwrap();
+    assert_eq!(1, status.targets[0].rules[0].runs);
+    assert_eq!(0, status.targets[0].rules[0].errors);
+    assert!(status.recent_errors.is_empty());
+}
+
+#[test]
+fn control_socket_works() {
+    let dir = common::temp_dir("control-socket");
+    let socket = dir.join("crabby.sock");
+    let server = ControlServer::bind(&socket).unwrap();
+    let client_socket = socket.clone();
+    let client = thread::spawn(move || send(&client_socket, &Request::Pause).unwrap());
+    let mut received = None;
+    while !client.is_finished() {
+        server.serve(|x| {
+            received = Some(x);
+            Response::ok()
+        });
+    }
+    assert!(client.join().unwrap().ok);
+    assert_eq!(Some(Request::Pause), received);
+    drop(server);
+    assert!(!socket.exists());
+}
+
+#[test]
+fn control_socket_keeps_files_works() {
+    let dir = common::temp_dir("control-socket-file");
+    let path = dir.join("notes.txt");
+    fs::write(&path, "keep me").unwrap();
+    let error = ControlServer::bind(&path).err().unwrap();
+    assert_eq!(io::ErrorKind::AlreadyExists, error.kind());
+    assert_eq!("keep me", fs::read_to_string(&path).unwrap());
+}
+
+#[test]
+fn control_socket_errors_works() {
+    let dir = common::temp_dir("control-socket-errors");
+    let socket = dir.join("crabby.sock");
+    let server = ControlServer::bind(&socket).unwrap();
+    let stream = UnixStream::connect(&socket).unwrap();
+    stream.shutdown(Shutdown::Both).unwrap();
+    drop(stream);
+    let errors = server.serve(|_| Response::ok());
+    assert_eq!(1, errors.len());
+}
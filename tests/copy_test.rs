@@ -0,0 +1,74 @@
+use crabby::{checksum, copy_file, ConflictPolicy, CopyOptions, CopyOutcome, LinkMode, Rule};
+use std::fs;
+
+mod common;
+
+#[test]
+fn copy_file_works() {
+    let dir = common::temp_dir("copy-file");
+    fs::write(dir.join("a.txt"), "content").unwrap();
+    let options = CopyOptions {
+        verify: true,
+        preserve: true,
+        ..CopyOptions::default()
+    };
+    let outcome = copy_file(&dir.join("a.txt"), &dir.join("b.txt"), &options).unwrap();
+    assert_eq!(CopyOutcome::Copied(dir.join("b.txt")), outcome);
+    assert_eq!("content", fs::read_to_string(dir.join("a.txt")).unwrap());
+    assert_eq!(
+        checksum(&dir.join("a.txt")).unwrap(),
+        checksum(&dir.join("b.txt")).unwrap()
+    );
+}
+
+#[test]
+fn copy_file_conflict_policies_work() {
+    let dir = common::temp_dir("copy-conflict");
+    fs::write(dir.join("a.txt"), "new").unwrap();
+    fs::write(dir.join("b.txt"), "old").unwrap();
+    let mut options = CopyOptions {
+        conflict: ConflictPolicy::Skip,
+        ..CopyOptions::default()
+    };
+    let outcome = copy_file(&dir.join("a.txt"), &dir.join("b.txt"), &options).unwrap();
+    assert_eq!(CopyOutcome::Skipped, outcome);
+    assert_eq!("old", fs::read_to_string(dir.join("b.txt")).unwrap());
+
+    options.conflict = ConflictPolicy::Rename;
+    let outcome = copy_file(&dir.join("a.txt"), &dir.join("b.txt"), &options).unwrap();
+    assert_eq!(CopyOutcome::Copied(dir.join("b (1).txt")), outcome);
+
+    options.conflict = ConflictPolicy::Fail;
+    assert!(copy_file(&dir.join("a.txt"), &dir.join("b.txt"), &options).is_err());
+
+    options.conflict = ConflictPolicy::Overwrite;
+    copy_file(&dir.join("a.txt"), &dir.join("b.txt"), &options).unwrap();
+    assert_eq!("new", fs::read_to_string(dir.join("b.txt")).unwrap());
+}
+
+#[test]
+fn copy_file_hardlink_works() {
+    let dir = common::temp_dir("copy-hardlink");
+    fs::write(dir.join("a.txt"), "content").unwrap();
+    let options = CopyOptions {
+        link: LinkMode::Hardlink,
+        ..CopyOptions::default()
+    };
+    let outcome = copy_file(&dir.join("a.txt"), &dir.join("b.txt"), &options).unwrap();
+    assert_eq!(CopyOutcome::Linked(dir.join("b.txt")), outcome);
+    assert_eq!("content", fs::read_to_string(dir.join("b.txt")).unwrap());
+}
+
+#[test]
+fn rule_cp_to_works() {
+    let dir = common::temp_dir("rule-cp");
+    fs::create_dir(dir.join("Backup")).unwrap();
+    fs::write(dir.join("report.pdf"), "report").unwrap();
+    fs::write(dir.join("notes.txt"), "notes").unwrap();
+    let line = format!("cp *.pdf to {} verify", dir.join("Backup").display());
+    let mut rule: Rule = Rule::new_from_line(&line, &0);
+    rule.execute(dir.to_string_lossy().to_string(), false, false);
+    assert!(dir.join("report.pdf").exists());
+    assert!(dir.join("Backup").join("report.pdf").exists());
+    assert!(!dir.join("Backup").join("notes.txt").exists());
+}
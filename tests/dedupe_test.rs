@@ -0,0 +1,115 @@
+use crabby::{
+    choose_kept, choose_kept_in, find_duplicates, find_duplicates_in, trash_in, Action,
+    DedupeOptions, KeepPolicy, Rule,
+};
+use std::fs;
+use std::path::PathBuf;
+
+mod common;
+
+#[test]
+fn find_duplicates_works() {
+    let dir = common::temp_dir("dedupe-find");
+    fs::write(dir.join("file.pdf"), "content").unwrap();
+    fs::write(dir.join("file (1).pdf"), "content").unwrap();
+    fs::write(dir.join("other.pdf"), "CONTENT").unwrap();
+    fs::write(dir.join("empty.pdf"), "").unwrap();
+    fs::write(dir.join("empty (1).pdf"), "").unwrap();
+    let files: Vec<PathBuf> = fs::read_dir(&dir)
+        .unwrap()
+        .map(|x| x.unwrap().path())
+        .collect();
+    let groups = find_duplicates(&files).unwrap();
+    assert_eq!(
+        vec![vec![dir.join("file (1).pdf"), dir.join("file.pdf")]],
+        groups
+    );
+}
+
+#[test]
+fn choose_kept_works() {
+    let dir = common::temp_dir("dedupe-keep");
+    fs::create_dir(dir.join("Docs")).unwrap();
+    fs::write(dir.join("report.pdf"), "content").unwrap();
+    fs::write(dir.join("Docs").join("report (2).pdf"), "content").unwrap();
+    let group = vec![
+        dir.join("Docs").join("report (2).pdf"),
+        dir.join("report.pdf"),
+    ];
+    let mut options = DedupeOptions {
+        keep: KeepPolicy::Shortest,
+        ..DedupeOptions::default()
+    };
+    assert_eq!(dir.join("report.pdf"), choose_kept(&group, &options));
+    options.prefer = Some(dir.join("Docs"));
+    assert_eq!(
+        dir.join("Docs").join("report (2).pdf"),
+        choose_kept(&group, &options)
+    );
+}
+
+#[test]
+fn rule_dedupe_works() {
+    let dir = common::temp_dir("rule-dedupe");
+    fs::write(dir.join("photo.png"), "pixels").unwrap();
+    fs::write(dir.join("photo (1).png"), "pixels").unwrap();
+    fs::write(dir.join("notes.txt"), "pixels").unwrap();
+    let mut rule: Rule = Rule::new_from_line(&"dedupe *.png keep shortest".to_string(), &0);
+    assert!(*rule.is_valid());
+    let files = rule.files_concerned(dir.to_string_lossy().to_string());
+    let operations = rule.plan(&files).unwrap();
+    assert_eq!(1, operations.len());
+    assert_eq!(Action::RemoveDuplicate, operations[0].action);
+    let duplicate = dir.join("photo (1).png");
+    assert_eq!(duplicate.to_string_lossy(), operations[0].source);
+
+    let trash_dir = dir.join("Trash");
+    let trashed = trash_in(&duplicate, &trash_dir).unwrap();
+    assert_eq!(trash_dir.join("files").join("photo (1).png"), trashed);
+    assert!(!duplicate.exists());
+    assert!(trash_dir
+        .join("info")
+        .join("photo (1).png.trashinfo")
+        .exists());
+}
+
+#[test]
+fn rule_dedupe_hardlink_works() {
+    let dir = common::temp_dir("rule-dedupe-hardlink");
+    fs::write(dir.join("a.txt"), "same").unwrap();
+    fs::write(dir.join("b.txt"), "same").unwrap();
+    let mut rule: Rule = Rule::new_from_line(&"dedupe *.txt hardlink".to_string(), &0);
+    rule.execute(dir.to_string_lossy().to_string(), false, false);
+    assert_eq!("same", fs::read_to_string(dir.join("a.txt")).unwrap());
+    assert_eq!("same", fs::read_to_string(dir.join("b.txt")).unwrap());
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::MetadataExt;
+        let a = fs::metadata(dir.join("a.txt")).unwrap();
+        let b = fs::metadata(dir.join("b.txt")).unwrap();
+        assert_eq!(a.ino(), b.ino());
+    }
+}
+
+#[test]
+fn choose_kept_in_works() {
+    let fs = common::mem_fs(&[], &[]);
+    fs.add_file("Downloads/a.txt", "content");
+    fs.add_file("Keep/a.txt", "content");
+    let group = vec![
+        PathBuf::from("Downloads/a.txt"),
+        PathBuf::from("Keep/a.txt"),
+    ];
+    assert_eq!(
+        vec![group.clone()],
+        find_duplicates_in(&*fs, &group).unwrap()
+    );
+    let options = DedupeOptions {
+        prefer: Some(PathBuf::from("./Keep")),
+        ..DedupeOptions::default()
+    };
+    assert_eq!(
+        PathBuf::from("Keep/a.txt"),
+        choose_kept_in(&*fs, &group, &options)
+    );
+}
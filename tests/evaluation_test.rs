@@ -0,0 +1,111 @@
+use common::clean;
+use crabby::{check, Config, Evaluation, Filesystem, MemFs, Review, Severity};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+mod common;
+
+fn downloads() -> Arc<MemFs> {
+    common::mem_fs(
+        &[
+            "Downloads/a.pdf",
+            "Downloads/report.pdf",
+            "Downloads/report.txt",
+        ],
+        &["Archive", "Backup", "Docs", "Reports"],
+    )
+}
+
+#[test]
+fn first_match_works() {
+    let fs = downloads();
+    clean(
+        &fs,
+        "Downloads",
+        "evaluate first-match\ncp *.pdf to Backup continue\nmv *.pdf to Docs\nmv report* to Reports\n",
+    );
+    let mut files = fs.files();
+    files.sort();
+    let expected: Vec<PathBuf> = vec![
+        "Backup/a.pdf",
+        "Backup/report.pdf",
+        "Docs/a.pdf",
+        "Docs/report.pdf",
+        "Reports/report.txt",
+    ]
+    .into_iter()
+    .map(PathBuf::from)
+    .collect();
+    assert_eq!(expected, files);
+}
+
+#[test]
+fn all_match_works() {
+    let fs = downloads();
+    clean(
+        &fs,
+        "Downloads",
+        "evaluate all-match\nmv *.txt to Archive\npre *.txt with old_\n",
+    );
+    assert!(fs.exists(Path::new("Archive/old_report.txt")));
+}
+
+#[test]
+fn independent_works() {
+    let fs = downloads();
+    clean(
+        &fs,
+        "Downloads",
+        "mv *.txt to Archive\npre *.txt with old_\n",
+    );
+    assert!(fs.exists(Path::new("Archive/report.txt")));
+}
+
+#[test]
+fn stop_works() {
+    let fs = downloads();
+    clean(
+        &fs,
+        "Downloads",
+        "cp report* to Backup stop\nmv *.pdf to Docs\n",
+    );
+    assert!(fs.exists(Path::new("Downloads/report.pdf")));
+    assert!(fs.exists(Path::new("Docs/a.pdf")));
+}
+
+#[test]
+fn evaluation_plan_works() {
+    let fs = downloads();
+    let mut config: Config = "evaluate all-match\npre report* with old_\nmv old_* to Docs\n"
+        .parse()
+        .unwrap_or_else(|x| match x {});
+    config.set_filesystem(fs.clone());
+    assert_eq!(&Evaluation::AllMatch, config.get_evaluation());
+    let review = Review::plan(&[config], "Downloads");
+    let sources: Vec<&str> = review.get_groups()[1]
+        .items
+        .iter()
+        .map(|x| x.operation.source.as_str())
+        .collect();
+    assert_eq!(
+        vec!["Downloads/old_report.pdf", "Downloads/old_report.txt"],
+        sources
+    );
+
+    let diagnostics = check(
+        "rules.crc",
+        "evaluate first-matches\nmv *.pdf to Docs stop continue\nmv *.pdf to Docs\n",
+    );
+    let errors: Vec<usize> = diagnostics
+        .iter()
+        .filter(|x| x.severity == Severity::Error)
+        .map(|x| x.line)
+        .collect();
+    assert_eq!(vec![1, 2], errors);
+
+    let diagnostics = check(
+        "rules.crc",
+        "evaluate first-match\ncp *.pdf to Backup\nmv *.pdf to Docs\n",
+    );
+    assert_eq!("unreachable rule", diagnostics[0].message);
+}
@@ -0,0 +1,71 @@
+use crabby::{explain, Action, App, Command, Config, Decision, MemFs};
+use std::sync::Arc;
+
+fn config(fs: &Arc<MemFs>, content: &str) -> Config {
+    let mut config: Config = content.parse().unwrap_or_else(|x| match x {});
+    config.set_filesystem(fs.clone());
+    config
+}
+
+#[test]
+fn explain_decisions_works() {
+    let fs = Arc::new(MemFs::new());
+    fs.add_file("Downloads/report final.pdf", "r");
+    let config = config(
+        &fs,
+        "ignore *.part\nmv *.txt to Texts\nmv *.pdf to Docs except report*\ncp *.pdf to Backup older-than 7d\nmv *.pdf to Papers every day\n",
+    );
+    let explanations = explain(&[config], "", "Downloads/report final.pdf");
+    let decisions: Vec<Decision> = explanations.into_iter().map(|x| x.decision).collect();
+    assert_eq!(
+        vec![
+            Decision::Pattern {
+                pattern: String::from("*.txt")
+            },
+            Decision::Exception {
+                pattern: String::from("report*")
+            },
+            Decision::TooRecent {
+                older_than: 604_800
+            },
+            Decision::Scheduled,
+        ],
+        decisions
+    );
+}
+
+#[test]
+fn explain_operations_works() {
+    let fs = Arc::new(MemFs::new());
+    fs.add_file("Downloads/report.pdf", "r");
+    let config = config(
+        &fs,
+        "cp *.pdf to Backup\nmv report* to Docs\npre *.pdf with old_\n",
+    );
+    let explanations = explain(&[config], "", "Downloads/report.pdf");
+    assert_eq!(Decision::Matched, explanations[0].decision);
+    assert_eq!(Action::Copy, explanations[0].operations[0].action);
+    assert_eq!(Decision::Matched, explanations[1].decision);
+    assert_eq!("Docs/report.pdf", explanations[1].operations[0].destination);
+    assert_eq!(Decision::Taken { line: 2 }, explanations[2].decision);
+    assert!(explanations[2].operations.is_empty());
+}
+
+#[test]
+fn explain_command_works() {
+    let fs = Arc::new(MemFs::new());
+    fs.add_file("Downloads/report.pdf", "r");
+    let app = |file: &str| {
+        App::builder()
+            .filesystem(fs.clone())
+            .config_str("mv *.pdf to Docs\n")
+            .command(Command::Explain {
+                file: file.to_string(),
+            })
+            .build()
+            .unwrap()
+    };
+    assert_eq!(0, app("Downloads/report.pdf").launch());
+    assert_eq!(1, app("Downloads/missing.pdf").launch());
+    assert_eq!(1, fs.files().len());
+}
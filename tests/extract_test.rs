@@ -0,0 +1,118 @@
+use crabby::{
+    create_archive, extract_archive, parse_size, ArchiveFormat, ArchiveOptions, ExtractOptions,
+    Rule,
+};
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::Path;
+
+mod common;
+
+/// Write a tarball compressed with xz, with one folder around the files
+fn write_tar_xz(archive: &Path, files: &[(&str, &str)]) {
+    let encoder = xz2::write::XzEncoder::new(File::create(archive).unwrap(), 6);
+    let mut builder = tar::Builder::new(encoder);
+    for (name, content) in files {
+        let mut header = tar::Header::new_gnu();
+        header.set_size(content.len() as u64);
+        header.set_mode(0o644);
+        header.set_cksum();
+        builder
+            .append_data(&mut header, name, content.as_bytes())
+            .unwrap();
+    }
+    builder.into_inner().unwrap().finish().unwrap();
+}
+
+#[test]
+fn archive_format_detect_works() {
+    let dir = common::temp_dir("extract-detect");
+    fs::write(dir.join("a.txt"), "content").unwrap();
+    create_archive(
+        ArchiveFormat::Zip,
+        &dir.join("archive.tgz"),
+        &[dir.join("a.txt")],
+        &ArchiveOptions::default(),
+    )
+    .unwrap();
+    write_tar_xz(&dir.join("archive.bin"), &[("a.txt", "content")]);
+    assert_eq!(
+        Some(ArchiveFormat::Zip),
+        ArchiveFormat::detect(&dir.join("archive.tgz")).unwrap()
+    );
+    assert_eq!(
+        Some(ArchiveFormat::TarXz),
+        ArchiveFormat::detect(&dir.join("archive.bin")).unwrap()
+    );
+    assert_eq!(None, ArchiveFormat::detect(&dir.join("a.txt")).unwrap());
+}
+
+#[test]
+fn extract_archive_flatten_works() {
+    let dir = common::temp_dir("extract-flatten");
+    write_tar_xz(
+        &dir.join("project.tar.xz"),
+        &[
+            ("project/README", "read me"),
+            ("project/src/main.rs", "fn main() {}"),
+        ],
+    );
+    let options = ExtractOptions {
+        flatten: true,
+        ..ExtractOptions::default()
+    };
+    let files = extract_archive(&dir.join("project.tar.xz"), &dir.join("out"), &options).unwrap();
+    assert_eq!(2, files);
+    assert!(dir.join("out").join("README").exists());
+    assert!(dir.join("out").join("src").join("main.rs").exists());
+}
+
+#[test]
+fn extract_archive_refuses_path_traversal() {
+    let dir = common::temp_dir("extract-traversal");
+    let mut zip = zip::ZipWriter::new(File::create(dir.join("evil.zip")).unwrap());
+    zip.start_file("../evil.txt", zip::write::SimpleFileOptions::default())
+        .unwrap();
+    zip.write_all(b"evil").unwrap();
+    zip.finish().unwrap();
+    let result = extract_archive(
+        &dir.join("evil.zip"),
+        &dir.join("out"),
+        &ExtractOptions::default(),
+    );
+    assert!(result.is_err());
+    assert!(!dir.join("evil.txt").exists());
+    assert_eq!(0, fs::read_dir(dir.join("out")).unwrap().count());
+}
+
+#[test]
+fn extract_archive_limits_work() {
+    let dir = common::temp_dir("extract-limits");
+    write_tar_xz(
+        &dir.join("bomb.tar.xz"),
+        &[("a.txt", "0123456789"), ("b.txt", "0123456789")],
+    );
+    let options = ExtractOptions {
+        max_size: 15,
+        ..ExtractOptions::default()
+    };
+    assert!(extract_archive(&dir.join("bomb.tar.xz"), &dir.join("size"), &options).is_err());
+    let options = ExtractOptions {
+        max_entries: 1,
+        ..ExtractOptions::default()
+    };
+    assert!(extract_archive(&dir.join("bomb.tar.xz"), &dir.join("entries"), &options).is_err());
+    assert_eq!(Some(20 * 1024 * 1024), parse_size("20M"));
+    assert_eq!(None, parse_size("20X"));
+}
+
+#[test]
+fn rule_extract_to_works() {
+    let dir = common::temp_dir("rule-extract");
+    write_tar_xz(&dir.join("music.tar.xz"), &[("song.mp3", "la la la")]);
+    let mut rule: Rule = Rule::new_from_line(&"extract *.tar.xz to . max-size 1M".to_string(), &0);
+    assert!(*rule.is_valid());
+    assert_eq!(1024 * 1024, rule.get_extract_options().max_size);
+    rule.execute(dir.to_string_lossy().to_string(), false, false);
+    assert!(dir.join("song.mp3").exists());
+}
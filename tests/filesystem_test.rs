@@ -0,0 +1,83 @@
+use crabby::{Config, Confirmation, Filesystem, MemFs, Outcome, Output, OutputFormat, RuleReport};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// Execute the rules of a `.crc` file of a `MemFs` on its `Downloads` folder
+fn execute(fs: &Arc<MemFs>, rules: &str) {
+    fs.add_file("rules.crc", rules);
+    let config = Config::load(fs.clone(), "rules.crc").unwrap();
+    config.execute_rules_with(
+        String::from("Downloads"),
+        false,
+        &mut Confirmation::none(),
+        &mut Output::buffered(OutputFormat::Text),
+    );
+}
+
+#[test]
+fn mem_fs_works() {
+    let fs = MemFs::new();
+    fs.add_file("./Downloads/a.pdf", "a");
+    fs.add_dir("Docs");
+    assert!(fs.is_file(Path::new("Downloads/a.pdf")));
+    assert!(!fs.is_file(Path::new("Docs")));
+    assert_eq!(
+        vec![PathBuf::from("./Downloads/a.pdf")],
+        fs.read_dir(Path::new("./Downloads")).unwrap()
+    );
+    assert!(fs.write(Path::new("Missing/b.pdf"), b"b").is_err());
+    fs.rename(Path::new("Downloads/a.pdf"), Path::new("Docs/a.pdf"))
+        .unwrap();
+    assert_eq!("a", fs.read_to_string(Path::new("Docs/a.pdf")).unwrap());
+    assert_eq!(vec![PathBuf::from("Docs/a.pdf")], fs.files());
+}
+
+#[test]
+fn mem_fs_rules_work() {
+    let fs = Arc::new(MemFs::new());
+    fs.add_file("Downloads/a.pdf", "new");
+    fs.add_file("Downloads/b.txt", "b");
+    fs.add_file("Docs/a.pdf", "old");
+    execute(
+        &fs,
+        "mv *.pdf to Docs conflict rename\npre *.txt with old_\n",
+    );
+    assert_eq!(
+        vec![
+            PathBuf::from("Docs/a (1).pdf"),
+            PathBuf::from("Docs/a.pdf"),
+            PathBuf::from("Downloads/old_b.txt"),
+            PathBuf::from("rules.crc"),
+        ],
+        fs.files()
+    );
+    assert_eq!(
+        "new",
+        fs.read_to_string(Path::new("Docs/a (1).pdf")).unwrap()
+    );
+}
+
+#[test]
+fn mem_fs_failure_works() {
+    let fs = Arc::new(MemFs::new());
+    fs.add_file("Downloads/a.pdf", "a");
+    fs.add_file("Downloads/b.pdf", "b");
+    fs.add_file("Downloads/c.pdf", "c");
+    fs.add_dir("Docs");
+    fs.fail_on("Docs/b.pdf");
+    assert!(Config::load(fs.clone(), "missing.crc").is_err());
+    fs.add_file("rules.crc", "mv *.pdf to Docs\n");
+    let mut rule = Config::load(fs.clone(), "rules.crc").unwrap().get_rule(0);
+    let report: RuleReport = rule.execute_with(
+        String::from("Downloads"),
+        false,
+        &mut Confirmation::none(),
+        &mut Output::buffered(OutputFormat::Text),
+    );
+    assert!(!report.succeeded());
+    assert_eq!(2, report.count(&Outcome::Applied));
+    assert_eq!(1, report.count(&Outcome::Failed(String::new())));
+    assert!(fs.exists(Path::new("Downloads/b.pdf")));
+    assert!(fs.exists(Path::new("Docs/a.pdf")));
+    assert!(fs.exists(Path::new("Docs/c.pdf")));
+}
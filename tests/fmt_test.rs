@@ -0,0 +1,62 @@
+use crabby::{format, App, Command, Filesystem, LineKind, MemFs, SyntaxTree};
+use std::path::Path;
+use std::sync::Arc;
+
+#[test]
+fn syntax_tree_works() {
+    let content =
+        "  mv   *.pdf to ./Docs   // docs  \r\n\n// Title\nignore *.part\t\npre *.txt with old_";
+    let tree = SyntaxTree::parse(content);
+    assert_eq!(content, tree.to_string());
+    let kinds: Vec<LineKind> = tree.lines.iter().map(|x| x.kind()).collect();
+    assert_eq!(
+        vec![
+            LineKind::Rule,
+            LineKind::Blank,
+            LineKind::Comment,
+            LineKind::Ignore,
+            LineKind::Rule
+        ],
+        kinds
+    );
+    let line = tree.line(1).unwrap();
+    assert_eq!("mv *.pdf to ./Docs", line.code());
+    assert_eq!(8, line.words[1].column);
+    assert_eq!("*.pdf", line.word_at(10).unwrap().text);
+}
+
+#[test]
+fn format_works() {
+    let formatted = format(
+        "\n  mv   *.pdf to ./Docs   // docs  \n\n\n// Title\npre *.txt with old_ //x\nmv *.pdf  \n\n",
+    )
+    .unwrap();
+    assert_eq!(
+        "mv *.pdf to ./Docs     // docs\n\n// Title\npre *.txt with old_    //x\nmv *.pdf\n",
+        formatted
+    );
+    assert_eq!(formatted, format(&formatted).unwrap());
+}
+
+#[test]
+fn fmt_command_works() {
+    let fs = Arc::new(MemFs::new());
+    fs.add_file("rules.crc", "mv  *.pdf to ./Docs\n");
+    let app = |check: bool| {
+        App::builder()
+            .filesystem(fs.clone())
+            .command(Command::Fmt {
+                files: vec![String::from("rules.crc")],
+                check,
+            })
+            .build()
+            .unwrap()
+    };
+    assert_eq!(1, app(true).launch());
+    assert_eq!(0, app(false).launch());
+    assert_eq!(
+        "mv *.pdf to ./Docs\n",
+        fs.read_to_string(Path::new("rules.crc")).unwrap()
+    );
+    assert_eq!(0, app(true).launch());
+}
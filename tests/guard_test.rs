@@ -0,0 +1,77 @@
+use crabby::{protection, Config, Rule};
+use std::fs;
+
+mod common;
+
+#[test]
+fn protection_works() {
+    let dir = common::temp_dir("guard-protection");
+    fs::create_dir(dir.join(".git")).unwrap();
+    fs::write(dir.join(".git").join("HEAD"), "ref").unwrap();
+    fs::write(dir.join(".bashrc"), "alias").unwrap();
+    fs::write(dir.join("rules.crc"), "").unwrap();
+    fs::write(dir.join("photo.png"), "pixels").unwrap();
+    let config_file = dir.join("rules.crc");
+    assert_eq!(
+        Some("configuration file"),
+        protection(&dir.join("rules.crc"), Some(&config_file))
+    );
+    assert_eq!(Some("dotfile"), protection(&dir.join(".bashrc"), None));
+    assert_eq!(
+        Some("git directory"),
+        protection(&dir.join(".git").join("HEAD"), None)
+    );
+    assert_eq!(None, protection(&dir.join("photo.png"), Some(&config_file)));
+}
+
+#[test]
+fn rule_except_works() {
+    let dir = common::temp_dir("guard-except");
+    fs::create_dir(dir.join("Docs")).unwrap();
+    fs::write(dir.join("report.pdf"), "report").unwrap();
+    fs::write(dir.join("invoice-march.pdf"), "invoice").unwrap();
+    let line = format!(
+        "mv *.pdf to {} except *invoice*",
+        dir.join("Docs").display()
+    );
+    let mut rule: Rule = Rule::new_from_line(&line, &0);
+    rule.execute(dir.to_string_lossy().to_string(), false, false);
+    assert!(dir.join("Docs").join("report.pdf").exists());
+    assert!(dir.join("invoice-march.pdf").exists());
+}
+
+#[test]
+fn rule_protected_paths_work() {
+    let dir = common::temp_dir("guard-rule");
+    fs::write(dir.join(".hidden.txt"), "hidden").unwrap();
+    let mut rule: Rule = Rule::new_from_line(&"pre *.txt with old_".to_string(), &0);
+    rule.execute(dir.to_string_lossy().to_string(), false, false);
+    assert!(dir.join(".hidden.txt").exists());
+
+    let mut rule: Rule =
+        Rule::new_from_line(&"pre *.txt with old_ allow-protected".to_string(), &0);
+    rule.execute(dir.to_string_lossy().to_string(), false, false);
+    assert!(dir.join("old_.hidden.txt").exists());
+}
+
+#[test]
+fn config_ignore_works() {
+    let dir = common::temp_dir("guard-ignore");
+    fs::write(dir.join("downloads.crc"), "").unwrap();
+    fs::write(dir.join("song.mp3"), "la la la").unwrap();
+    fs::write(dir.join("song.part"), "la la").unwrap();
+    fs::write(
+        dir.join("rules.crc"),
+        "ignore *.part *.crdownload // unfinished downloads\nsuf * with _old\n",
+    )
+    .unwrap();
+    let config: Config = Config::new(dir.join("rules.crc").to_string_lossy().to_string());
+    assert_eq!(
+        &vec![String::from("*.part"), String::from("*.crdownload")],
+        config.get_ignored()
+    );
+    config.execute_rules(dir.to_string_lossy().to_string(), false, false);
+    assert!(dir.join("song_old.mp3").exists());
+    assert!(dir.join("song.part").exists());
+    assert!(dir.join("rules.crc").exists());
+}
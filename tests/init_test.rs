@@ -0,0 +1,127 @@
+use crabby::{
+    check, render_scaffold, scaffold, select_proposals, user_dirs_in, Answer, App, Command,
+    Confirmation, Filesystem, MemFs, ScriptedConfirmer, Severity,
+};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+mod common;
+
+fn downloads() -> Arc<MemFs> {
+    common::mem_fs(
+        &[
+            "/home/u/Downloads/a.pdf",
+            "/home/u/Downloads/b.pdf",
+            "/home/u/Downloads/c.png",
+            "/home/u/Downloads/song.mp3",
+            "/home/u/Downloads/disk.iso",
+            "/home/u/Downloads/.hidden.pdf",
+        ],
+        &[],
+    )
+}
+
+#[test]
+fn user_dirs_works() {
+    let fs = downloads();
+    fs.add_file(
+        "/home/u/.config/user-dirs.dirs",
+        "# written by xdg-user-dirs-update\nXDG_PICTURES_DIR=\"$HOME/Images\"\nXDG_MUSIC_DIR=\"/data/Music\"\n",
+    );
+    let dirs = user_dirs_in(&*fs, Path::new("/home/u"), Path::new("/home/u/.config"));
+    assert_eq!(
+        vec![
+            (String::from("Pictures"), PathBuf::from("/home/u/Images")),
+            (String::from("Music"), PathBuf::from("/data/Music")),
+            (String::from("Videos"), PathBuf::from("/home/u/Videos")),
+            (
+                String::from("Documents"),
+                PathBuf::from("/home/u/Documents")
+            ),
+        ],
+        dirs
+    );
+}
+
+#[test]
+fn scaffold_works() {
+    let fs = downloads();
+    let dirs = vec![
+        (String::from("Music"), PathBuf::from("/home/u/My Music")),
+        (
+            String::from("Documents"),
+            PathBuf::from("/home/u/Documents"),
+        ),
+    ];
+    let found = scaffold(&*fs, "/home/u/Downloads", &dirs);
+    let rules: Vec<String> = found.proposals.iter().map(|x| x.rule()).collect();
+    assert_eq!(vec!["mv *.pdf to /home/u/Documents"], rules);
+    assert_eq!(2, found.proposals[0].files.len());
+    assert_eq!(
+        vec![
+            (String::from("png"), 1),
+            (String::from("mp3"), 1),
+            (String::from("iso"), 1)
+        ],
+        found.unsorted
+    );
+    let content = render_scaffold("/home/u/Downloads", "common.crc", &found);
+    assert!(content.contains("mv *.pdf to /home/u/Documents    // 2 file(s)\n"));
+    assert!(content.contains("`crabby -c /home/u/Downloads -f common.crc --dry-run`"));
+    assert!(check("common.crc", &content)
+        .iter()
+        .all(|x| x.severity != Severity::Error));
+}
+
+#[test]
+fn init_interactive_works() {
+    let fs = downloads();
+    let dirs = user_dirs_in(&*fs, Path::new("/home/u"), Path::new("/home/u/.config"));
+    let found = scaffold(&*fs, "/home/u/Downloads", &dirs);
+    assert_eq!(3, found.proposals.len());
+    let mut confirmer = ScriptedConfirmer::new(vec![Answer::No, Answer::Yes]);
+    let mut confirmation = Confirmation::new(&mut confirmer);
+    let selected = select_proposals(found.proposals, &mut confirmation);
+    let rules: Vec<String> = selected.iter().map(|x| x.rule()).collect();
+    assert_eq!(vec!["mv *.mp3 to /home/u/Music"], rules);
+    assert_eq!(3, confirmer.get_asked().len());
+}
+
+#[test]
+fn init_command_works() {
+    let fs = downloads();
+    let app = || {
+        App::builder()
+            .filesystem(fs.clone())
+            .target("/home/u/Downloads")
+            .command(Command::Init {
+                file: String::from("common.crc"),
+            })
+            .build()
+            .unwrap()
+    };
+    assert_eq!(0, app().launch());
+    let content = fs.read_to_string(Path::new("common.crc")).unwrap();
+    assert!(content.contains("mv *.pdf to "));
+    assert!(!content.contains("hidden"));
+    assert_eq!(1, app().launch());
+}
+
+#[test]
+fn scaffold_case_works() {
+    let fs = downloads();
+    fs.add_file("/home/u/Downloads/scan.PDF", "s");
+    fs.add_file("/home/u/Downloads/TRACK.Mp3", "t");
+    let dirs = user_dirs_in(&*fs, Path::new("/home/u"), Path::new("/home/u/.config"));
+    let found = scaffold(&*fs, "/home/u/Downloads", &dirs);
+    let rules: Vec<String> = found.proposals.iter().map(|x| x.rule()).collect();
+    assert_eq!(
+        vec![
+            "mv *.png to /home/u/Pictures",
+            "mv *.mp3 to /home/u/Music ignore-case",
+            "mv *.pdf to /home/u/Documents ignore-case"
+        ],
+        rules
+    );
+    assert_eq!(3, found.proposals[2].files.len());
+}
@@ -0,0 +1,153 @@
+use crabby::{read_message, write_message, LanguageServer, MemFs};
+use serde_json::{json, Value};
+use std::io::Cursor;
+use std::sync::Arc;
+
+/// Run a server on the messages of a scripted client, and return the messages it wrote
+fn exchange(fs: Arc<MemFs>, messages: Vec<Value>) -> Vec<Value> {
+    let mut input: Vec<u8> = vec![];
+    for message in &messages {
+        write_message(&mut input, message).unwrap();
+    }
+    let mut server = LanguageServer::new(Cursor::new(input), vec![]);
+    server.set_filesystem(fs);
+    server.run().unwrap();
+    let mut output = Cursor::new(server.into_output());
+    let mut answers: Vec<Value> = vec![];
+    while let Some(x) = read_message(&mut output).unwrap() {
+        answers.push(x);
+    }
+    answers
+}
+
+fn request(id: u64, method: &str, params: Value) -> Value {
+    json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": params })
+}
+
+fn open(text: &str) -> Value {
+    json!({
+        "jsonrpc": "2.0",
+        "method": "textDocument/didOpen",
+        "params": { "textDocument": { "uri": "file:///ws/rules.crc", "languageId": "crc", "version": 1, "text": text } },
+    })
+}
+
+fn at(line: u64, character: u64) -> Value {
+    json!({ "textDocument": { "uri": "file:///ws/rules.crc" }, "position": { "line": line, "character": character } })
+}
+
+fn answer(answers: &[Value], id: u64) -> Value {
+    answers.iter().find(|x| x["id"] == id).unwrap()["result"].clone()
+}
+
+#[test]
+fn lsp_diagnostics_works() {
+    let answers = exchange(
+        Arc::new(MemFs::new()),
+        vec![
+            request(1, "initialize", json!({ "rootUri": "file:///ws" })),
+            open("mv *.pdf to ./Docs\nmv *.txt ot ./Texts\ninclude missing.crc\n"),
+            request(2, "shutdown", Value::Null),
+            json!({ "jsonrpc": "2.0", "method": "exit" }),
+        ],
+    );
+    assert_eq!(true, answer(&answers, 1)["capabilities"]["hoverProvider"]);
+    let published = answers
+        .iter()
+        .find(|x| x["method"] == "textDocument/publishDiagnostics")
+        .unwrap();
+    let diagnostics = published["params"]["diagnostics"].as_array().unwrap();
+    assert_eq!(2, diagnostics.len());
+    assert_eq!(1, diagnostics[0]["range"]["start"]["line"]);
+    assert_eq!(9, diagnostics[0]["range"]["start"]["character"]);
+    assert_eq!(1, diagnostics[0]["severity"]);
+    assert!(diagnostics[1]["message"]
+        .as_str()
+        .unwrap()
+        .contains("/ws/missing.crc"));
+    assert_eq!(Value::Null, answer(&answers, 2));
+}
+
+#[test]
+fn lsp_completion_works() {
+    let answers = exchange(
+        Arc::new(MemFs::new()),
+        vec![
+            open("let docs = ./Docs\nmv *.pdf \nmv *.pdf to $d\nzip *.log to logs.zip \n"),
+            request(1, "textDocument/completion", at(0, 0)),
+            request(2, "textDocument/completion", at(1, 9)),
+            request(3, "textDocument/completion", at(2, 14)),
+            request(4, "textDocument/completion", at(3, 22)),
+        ],
+    );
+    let labels = |id: u64| -> Vec<String> {
+        answer(&answers, id)
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|x| x["label"].as_str().unwrap().to_string())
+            .collect()
+    };
+    assert!(labels(1).contains(&String::from("mv")));
+    assert!(labels(1).contains(&String::from("include")));
+    assert_eq!(vec!["to"], labels(2));
+    assert_eq!(vec!["docs"], labels(3));
+    assert!(labels(4).contains(&String::from("remove")));
+}
+
+#[test]
+fn lsp_hover_and_definition_works() {
+    let fs = Arc::new(MemFs::new());
+    fs.add_file("/ws/a.pdf", "a");
+    fs.add_file("/ws/b.pdf", "b");
+    fs.add_file("/ws/c.txt", "c");
+    fs.add_file("/ws/common.crc", "// shared\nlet docs = ./Docs\n");
+    let answers = exchange(
+        fs,
+        vec![
+            request(1, "initialize", json!({ "rootUri": "file:///ws" })),
+            open("include common.crc\nmv *.pdf to $docs\n"),
+            request(2, "textDocument/hover", at(1, 0)),
+            request(3, "textDocument/hover", at(1, 14)),
+            request(4, "textDocument/definition", at(1, 14)),
+            request(5, "textDocument/definition", at(0, 10)),
+        ],
+    );
+    let hover = answer(&answers, 2)["contents"]["value"].clone();
+    assert!(hover.as_str().unwrap().contains("`./Docs`"));
+    assert!(hover.as_str().unwrap().contains("2 file(s)"));
+    assert_eq!(
+        "`$docs` = `./Docs`",
+        answer(&answers, 3)["contents"]["value"]
+    );
+    let definition = answer(&answers, 4);
+    assert_eq!("file:///ws/common.crc", definition["uri"]);
+    assert_eq!(1, definition["range"]["start"]["line"]);
+    assert_eq!("file:///ws/common.crc", answer(&answers, 5)["uri"]);
+}
+
+#[test]
+fn lsp_uri_works() {
+    let fs = Arc::new(MemFs::new());
+    fs.add_file("/my ws#1/café.crc", "let docs = ./Docs\n");
+    let answers = exchange(
+        fs,
+        vec![
+            request(1, "initialize", json!({ "rootUri": "file:///my%20ws%231" })),
+            json!({
+                "jsonrpc": "2.0",
+                "method": "textDocument/didOpen",
+                "params": { "textDocument": { "uri": "file:///my%20ws%231/rules.crc", "languageId": "crc", "version": 1, "text": "include café.crc\nmv *.pdf to $docs\n" } },
+            }),
+            request(
+                2,
+                "textDocument/definition",
+                json!({ "textDocument": { "uri": "file:///my%20ws%231/rules.crc" }, "position": { "line": 1, "character": 14 } }),
+            ),
+        ],
+    );
+    assert_eq!(
+        "file:///my%20ws%231/caf%C3%A9.crc",
+        answer(&answers, 2)["uri"]
+    );
+}
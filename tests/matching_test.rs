@@ -0,0 +1,97 @@
+use common::clean;
+use crabby::{check, Config, Filesystem, Matching, MemFs, Rule, Severity};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+mod common;
+
+/// `café.jpg` written with a decomposed `é`, as macOS does
+const DECOMPOSED: &str = "cafe\u{301}.jpg";
+
+fn downloads() -> Arc<MemFs> {
+    common::mem_fs(
+        &[
+            "Downloads/Photo.JPG",
+            "Downloads/photo.jpg",
+            &format!("Downloads/{}", DECOMPOSED),
+        ],
+        &["Pictures"],
+    )
+}
+
+#[test]
+fn ignore_case_works() {
+    let fs = downloads();
+    clean(&fs, "Downloads", "mv *.jpg to Pictures except cafe*\n");
+    assert!(fs.exists(Path::new("Downloads/Photo.JPG")));
+
+    let fs = downloads();
+    clean(
+        &fs,
+        "Downloads",
+        "mv *.jpg to Pictures ignore-case except PHOTO.JPG\n",
+    );
+    assert!(fs.exists(Path::new("Downloads/Photo.JPG")));
+    assert!(fs.exists(Path::new("Downloads/photo.jpg")));
+
+    let fs = downloads();
+    clean(
+        &fs,
+        "Downloads",
+        "match ignore-case\nmv photo* to Pictures\n",
+    );
+    assert!(fs.exists(Path::new("Pictures/Photo.JPG")));
+    assert!(fs.exists(Path::new("Pictures/photo.jpg")));
+}
+
+#[test]
+fn unicode_works() {
+    let rule = Rule::new_from_line(&String::from("mv café* to Pictures"), &0);
+    assert!(!rule.concerns(DECOMPOSED));
+    let rule = Rule::new_from_line(&String::from("mv café* to Pictures unicode"), &0);
+    assert_eq!(
+        &Matching {
+            ignore_case: false,
+            unicode: true
+        },
+        rule.get_matching()
+    );
+    assert!(rule.concerns(DECOMPOSED));
+
+    let fs = downloads();
+    clean(&fs, "Downloads", "normalize caf*\n");
+    let mut files = fs.files();
+    files.sort();
+    let expected: Vec<PathBuf> = vec![
+        "Downloads/Photo.JPG",
+        "Downloads/café.jpg",
+        "Downloads/photo.jpg",
+    ]
+    .into_iter()
+    .map(PathBuf::from)
+    .collect();
+    assert_eq!(expected, files);
+
+    let fs = downloads();
+    clean(&fs, "Downloads", "normalize *.JPG nfd\n");
+    assert!(fs.exists(Path::new("Downloads/Photo.JPG")));
+}
+
+#[test]
+fn matching_check_works() {
+    let diagnostics = check(
+        "rules.crc",
+        "match ignore-case unicode\nmatch case\nnormalize *.jpg nfkc\nmv *.jpg to Pictures unicode\n",
+    );
+    let errors: Vec<(usize, usize)> = diagnostics
+        .iter()
+        .filter(|x| x.severity == Severity::Error)
+        .map(|x| (x.line, x.column))
+        .collect();
+    assert_eq!(vec![(2, 1), (3, 17)], errors);
+
+    let config: Config = "mv *.jpg to Pictures\nmatch unicode\n"
+        .parse()
+        .unwrap_or_else(|x| match x {});
+    assert!(config.get_rules()[0].get_matching().unicode);
+}
@@ -0,0 +1,139 @@
+use crabby::{
+    check, normalized_name, Config, Filesystem, MemFs, NormalizeOptions, Review, Severity,
+    UnicodeForm,
+};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+mod common;
+
+fn downloads() -> Arc<MemFs> {
+    common::mem_fs(
+        &[
+            "Downloads/Report%20Final (2) [copy].PDF",
+            "Downloads/notes (1).txt",
+            "Downloads/photo.jpg",
+            "Downloads/photo (1).jpg",
+        ],
+        &[],
+    )
+}
+
+#[test]
+fn normalized_name_works() {
+    let options = NormalizeOptions {
+        url_decode: true,
+        lowercase_extension: true,
+        spaces: Some('_'),
+        strip_copies: true,
+        ..NormalizeOptions::default()
+    };
+    assert_eq!(
+        "Report_Final.pdf",
+        normalized_name("Report%20Final (2) [copy].PDF", &options)
+    );
+    assert_eq!("(1).png", normalized_name("(1).png", &options));
+
+    let options = NormalizeOptions {
+        ascii: true,
+        spaces: Some('-'),
+        max_length: Some(10),
+        ..NormalizeOptions::default()
+    };
+    assert_eq!("Cafe-a.txt", normalized_name("Café à Paris.txt", &options));
+    assert_eq!(
+        "cafe\u{301}",
+        normalized_name(
+            "café",
+            &NormalizeOptions {
+                form: UnicodeForm::Nfd,
+                ..NormalizeOptions::default()
+            }
+        )
+    );
+}
+
+#[test]
+fn normalize_rule_works() {
+    let fs = downloads();
+    common::clean(
+        &fs,
+        "Downloads",
+        "normalize * url-decode strip-copies spaces - lowercase-ext\n",
+    );
+    let mut files = fs.files();
+    files.sort();
+    let expected: Vec<PathBuf> = vec![
+        "Downloads/Report-Final.pdf",
+        "Downloads/notes.txt",
+        "Downloads/photo (1).jpg",
+        "Downloads/photo.jpg",
+    ]
+    .into_iter()
+    .map(PathBuf::from)
+    .collect();
+    assert_eq!(expected, files);
+}
+
+#[test]
+fn normalize_plan_works() {
+    let fs = downloads();
+    let mut config: Config = "normalize *.PDF url-decode max-length 8\n"
+        .parse()
+        .unwrap_or_else(|x| match x {});
+    config.set_filesystem(fs.clone());
+    let review = Review::plan(&[config], "Downloads");
+    let destinations: Vec<&str> = review.get_groups()[0]
+        .items
+        .iter()
+        .map(|x| x.operation.destination.as_str())
+        .collect();
+    assert_eq!(vec!["Downloads/Repo.PDF"], destinations);
+
+    let diagnostics = check(
+        "rules.crc",
+        "normalize * spaces +\nnormalize * max-length 0\nnormalize * ascii nfd strip-copies\n",
+    );
+    let errors: Vec<(usize, usize)> = diagnostics
+        .iter()
+        .filter(|x| x.severity == Severity::Error)
+        .map(|x| (x.line, x.column))
+        .collect();
+    assert_eq!(vec![(1, 20), (2, 24)], errors);
+}
+
+#[test]
+fn rename_taken_works() {
+    let fs = common::mem_fs(
+        &[
+            "Downloads/a.txt",
+            "Downloads/a_old.txt",
+            "Downloads/b.txt",
+            "Downloads/old_c.txt",
+            "Downloads/c.txt",
+        ],
+        &[],
+    );
+    common::clean(
+        &fs,
+        "Downloads",
+        "suf a*.txt with _old\npre c.txt with old_\n",
+    );
+    let mut files = fs.files();
+    files.sort();
+    let expected: Vec<PathBuf> = vec![
+        "Downloads/a.txt",
+        "Downloads/a_old_old.txt",
+        "Downloads/b.txt",
+        "Downloads/c.txt",
+        "Downloads/old_c.txt",
+    ]
+    .into_iter()
+    .map(PathBuf::from)
+    .collect();
+    assert_eq!(expected, files);
+    assert_eq!(
+        "Downloads/c.txt",
+        fs.read_to_string(Path::new("Downloads/c.txt")).unwrap()
+    );
+}
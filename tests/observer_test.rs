@@ -0,0 +1,101 @@
+use crabby::{Config, Confirmation, Observer, Operation, Outcome, Rule, RuleReport};
+use std::fs;
+
+mod common;
+
+/// An `Observer` remembering what it was told
+#[derive(Default)]
+struct Recorder {
+    calls: Vec<String>,
+}
+
+impl Observer for Recorder {
+    fn on_rule_start(&mut self, rule: &Rule) {
+        self.calls.push(format!("start {}", rule.get_line_number()));
+    }
+
+    fn on_file_matched(&mut self, _rule: &Rule, file: &str) {
+        self.calls
+            .push(format!("matched {}", file.rsplit('/').next().unwrap()));
+    }
+
+    fn on_file_skipped(&mut self, _rule: &Rule, file: &str, reason: &str) {
+        self.calls.push(format!(
+            "skipped {} ({})",
+            file.rsplit('/').next().unwrap(),
+            reason
+        ));
+    }
+
+    fn on_operation(&mut self, operation: &Operation, outcome: &Outcome) {
+        self.calls
+            .push(format!("{:?} {:?}", operation.action, outcome));
+    }
+
+    fn on_error(&mut self, rule: &Rule, _error: &str) {
+        self.calls.push(format!("error {}", rule.get_line_number()));
+    }
+
+    fn on_finish(&mut self, report: &RuleReport) {
+        self.calls
+            .push(format!("finish {} {}", report.line, report.succeeded()));
+    }
+}
+
+#[test]
+fn observer_works() {
+    let dir = common::temp_dir("observer");
+    fs::write(dir.join("a.txt"), "a").unwrap();
+    fs::write(
+        dir.join("rules.crc"),
+        "pre *.txt with old_\nmv *.pdf to\nmv *.pdf to ./Docs every day\n",
+    )
+    .unwrap();
+    let config = Config::from_file(&dir.join("rules.crc").to_string_lossy()).unwrap();
+    let mut recorder = Recorder::default();
+    config.execute_rules_with(
+        dir.to_string_lossy().to_string(),
+        false,
+        &mut Confirmation::none(),
+        &mut recorder,
+    );
+    assert_eq!(
+        vec![
+            "start 1",
+            "matched a.txt",
+            "Prefix Applied",
+            "finish 1 true",
+            "start 2",
+            "error 2",
+            "finish 2 false",
+        ],
+        recorder.calls
+    );
+    assert!(dir.join("old_a.txt").exists());
+}
+
+#[test]
+fn observer_skipped_works() {
+    let fs = common::mem_fs(&["Downloads/a.txt", "Downloads/old_a.txt"], &[]);
+    let mut config: Config = "pre a.txt with old_\n"
+        .parse()
+        .unwrap_or_else(|x| match x {});
+    config.set_filesystem(fs.clone());
+    let mut recorder = Recorder::default();
+    config.execute_rules_with(
+        String::from("Downloads"),
+        false,
+        &mut Confirmation::none(),
+        &mut recorder,
+    );
+    assert_eq!(
+        vec![
+            "start 1",
+            "matched a.txt",
+            "skipped a.txt (another file is named `old_a.txt`)",
+            "finish 1 true",
+        ],
+        recorder.calls
+    );
+    assert_eq!(2, fs.files().len());
+}
@@ -0,0 +1,135 @@
+use crabby::{Config, Confirmation, Event, Output, OutputFormat, SCHEMA_VERSION};
+use serde_json::Value;
+use std::fs;
+
+mod common;
+
+#[test]
+fn output_ndjson_works() {
+    let dir = common::temp_dir("output-ndjson");
+    fs::create_dir(dir.join("Docs")).unwrap();
+    fs::write(dir.join("a.pdf"), "a").unwrap();
+    fs::write(dir.join("b.pdf"), "b").unwrap();
+    fs::write(
+        dir.join("rules.crc"),
+        format!("mv *.pdf to {}\n", dir.join("Docs").display()),
+    )
+    .unwrap();
+    let config = Config::from_file(&dir.join("rules.crc").to_string_lossy()).unwrap();
+    let mut output = Output::buffered(OutputFormat::Ndjson);
+    config.execute_rules_with(
+        dir.to_string_lossy().to_string(),
+        true,
+        &mut Confirmation::none(),
+        &mut output,
+    );
+    output.finish();
+    let events: Vec<Value> = output
+        .written()
+        .lines()
+        .map(|x| serde_json::from_str(x).unwrap())
+        .collect();
+    assert!(events.iter().all(|x| x["schema_version"] == SCHEMA_VERSION));
+    let names: Vec<&str> = events
+        .iter()
+        .map(|x| x["event"].as_str().unwrap())
+        .collect();
+    assert_eq!(
+        vec![
+            "rule-parsed",
+            "file-matched",
+            "file-matched",
+            "operation-planned",
+            "operation-planned",
+            "rule-finished",
+            "summary"
+        ],
+        names
+    );
+    assert_eq!("move", events[3]["action"]);
+    assert_eq!(2, events[6]["planned"]);
+    assert!(dir.join("a.pdf").exists());
+    assert!(dir.join("b.pdf").exists());
+}
+
+#[test]
+fn output_json_works() {
+    let mut output = Output::buffered(OutputFormat::Json);
+    output.message("info", String::from("hello"));
+    assert_eq!("", output.written());
+    output.finish();
+    let document: Value = serde_json::from_str(&output.written()).unwrap();
+    assert_eq!(SCHEMA_VERSION, document["schema_version"]);
+    assert_eq!("message", document["events"][0]["event"]);
+    assert_eq!("hello", document["events"][0]["text"]);
+    assert_eq!("summary", document["events"][1]["event"]);
+}
+
+#[test]
+fn output_text_works() {
+    let mut output = Output::buffered(OutputFormat::Text);
+    output.emit(Event::FileMatched {
+        line: 1,
+        name: None,
+        file: String::from("a.pdf"),
+    });
+    output.message("error", String::from("something failed"));
+    output.finish();
+    assert_eq!("error: something failed.\n", output.written());
+}
+
+#[test]
+fn output_text_dry_run_works() {
+    let dir = common::temp_dir("output-text-dry-run");
+    fs::write(dir.join("a.pdf"), "a").unwrap();
+    fs::write(dir.join("b.pdf"), "b").unwrap();
+    let config: Config = "mv *.pdf to Docs\npre *.txt with old_\n"
+        .parse()
+        .unwrap_or_else(|x| match x {});
+    let mut output = Output::buffered(OutputFormat::Text);
+    config.execute_rules_with(
+        dir.to_string_lossy().to_string(),
+        true,
+        &mut Confirmation::none(),
+        &mut output,
+    );
+    output.finish();
+    assert_eq!(
+        "[dry-run][line 1] move all files following the pattern `*.pdf` to `Docs`\nsummary: 2 operation(s) planned.\n",
+        output.written()
+    );
+}
+
+#[test]
+fn output_rule_name_works() {
+    let fs = common::mem_fs(&["Downloads/a.pdf", "Downloads/old_a.pdf"], &[]);
+    let mut config: Config = "@name(docs) pre a.pdf with old_\n"
+        .parse()
+        .unwrap_or_else(|x| match x {});
+    config.set_filesystem(fs);
+    let mut output = Output::buffered(OutputFormat::Text);
+    config.execute_rules_with(
+        String::from("Downloads"),
+        false,
+        &mut Confirmation::none(),
+        &mut output,
+    );
+    assert_eq!(
+        "skipped: rule `docs` does not touch `Downloads/a.pdf` (another file is named `old_a.pdf`).\n",
+        output.written()
+    );
+
+    let mut output = Output::buffered(OutputFormat::Ndjson);
+    config.execute_rules_with(
+        String::from("Downloads"),
+        false,
+        &mut Confirmation::none(),
+        &mut output,
+    );
+    let events: Vec<Value> = output
+        .written()
+        .lines()
+        .map(|x| serde_json::from_str(x).unwrap())
+        .collect();
+    assert!(events.iter().all(|x| x["name"] == "docs"));
+}
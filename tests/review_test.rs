@@ -0,0 +1,84 @@
+use crabby::{
+    Config, Filesystem, MemFs, Outcome, Output, OutputFormat, Review, ReviewFilter, Step, Tui,
+};
+use crossterm::event::KeyCode;
+use std::path::Path;
+use std::sync::Arc;
+
+/// Plan the rules of a `.crc` file of a `MemFs` on its `Downloads` folder
+fn review(fs: &Arc<MemFs>, rules: &str) -> Review {
+    fs.add_file("rules.crc", rules);
+    let config = Config::load(fs.clone(), "rules.crc").unwrap();
+    Review::plan(&[config], "Downloads")
+}
+
+fn memfs() -> Arc<MemFs> {
+    let fs = Arc::new(MemFs::new());
+    fs.add_file("Downloads/a.pdf", "a");
+    fs.add_file("Downloads/b.pdf", "b");
+    fs.add_file("Downloads/c.txt", "c");
+    fs.add_dir("Docs");
+    fs
+}
+
+#[test]
+fn review_works() {
+    let fs = memfs();
+    let mut review = review(&fs, "mv *.pdf to Docs\npre *.txt with old_\nmv *.pdf to\n");
+    assert_eq!(3, review.get_groups().len());
+    assert!(review.get_groups()[2].error.is_some());
+    assert_eq!(3, review.selected());
+    review.toggle(0, 1);
+    review.set_destination(0, 0, "Docs/report.pdf").unwrap();
+    let reports = review.apply(false, &mut Output::buffered(OutputFormat::Text));
+    assert_eq!(1, reports[0].count(&Outcome::Declined));
+    assert!(!reports[2].succeeded());
+    assert!(fs.exists(Path::new("Docs/report.pdf")));
+    assert!(fs.exists(Path::new("Downloads/b.pdf")));
+    assert!(fs.exists(Path::new("Downloads/old_c.txt")));
+}
+
+#[test]
+fn review_filter_works() {
+    let fs = memfs();
+    let mut review = review(&fs, "mv *.pdf to Docs\npre * with old_\n");
+    assert_eq!(5, review.visible().len());
+    review.set_filter(ReviewFilter {
+        line: Some(2),
+        extension: Some(String::from("PDF")),
+    });
+    assert_eq!(vec![(1, 0), (1, 1)], review.visible());
+    review.select_visible(false);
+    assert_eq!(3, review.selected());
+    review.apply(true, &mut Output::buffered(OutputFormat::Text));
+    assert!(fs.exists(Path::new("Downloads/a.pdf")));
+}
+
+#[test]
+fn tui_keys_work() {
+    let fs = memfs();
+    let mut tui = Tui::new(review(&fs, "mv *.pdf to Docs\n"), "Downloads");
+    for key in vec![
+        KeyCode::Char(' '),
+        KeyCode::Char('e'),
+        KeyCode::Backspace,
+        KeyCode::Backspace,
+        KeyCode::Backspace,
+        KeyCode::Char('t'),
+        KeyCode::Char('x'),
+        KeyCode::Char('t'),
+        KeyCode::Enter,
+    ] {
+        assert_eq!(Step::Continue, tui.handle(key));
+    }
+    let (rows, cursor) = tui.rows();
+    assert_eq!(Some(2), cursor);
+    assert_eq!("  [ ] move `Downloads/a.pdf` to `Docs/a.pdf`", rows[1]);
+    assert_eq!("  [x] move `Downloads/b.pdf` to `Docs/b.txt`", rows[2]);
+    assert_eq!(Step::Continue, tui.handle(KeyCode::Enter));
+    assert_eq!(Step::Continue, tui.handle(KeyCode::Char('n')));
+    assert_eq!(Step::Quit, tui.handle(KeyCode::Char('q')));
+    assert_eq!(Step::Continue, tui.handle(KeyCode::Enter));
+    assert_eq!(Step::Apply, tui.handle(KeyCode::Char('y')));
+    assert_eq!(1, tui.get_review().selected());
+}
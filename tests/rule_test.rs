@@ -1,4 +1,6 @@
-use crabby::{App, Rule};
+use crabby::{App, ConflictPolicy, KeepPolicy, LinkMode, Rule};
+
+mod common;
 
 #[test]
 fn rule_get_line_value_works() {
@@ -38,6 +40,100 @@ fn rule_get_tokens_works() {
 #[test]
 fn rule_is_valid_works() {
     // TODO add more assertions for all the rules
-    let app: App = App::new_for_test();
+    let app: App = App::new_for_test_in(common::default_fs());
     assert!(*app.get_configuration(0).get_rule(0).is_valid());
 }
+
+#[test]
+fn rule_cp_options_works() {
+    let rule: Rule = Rule::new_from_line(
+        &"cp *.pdf to ./Backup conflict rename preserve verify hardlink".to_string(),
+        &0,
+    );
+    assert!(*rule.is_valid());
+    assert_eq!(ConflictPolicy::Rename, rule.get_copy_options().conflict);
+    assert!(rule.get_copy_options().preserve);
+    assert!(rule.get_copy_options().verify);
+    assert_eq!(LinkMode::Hardlink, rule.get_copy_options().link);
+}
+
+#[test]
+fn rule_invalid_options_works() {
+    let rule: Rule = Rule::new_from_line(&"cp *.pdf to ./Backup conflict maybe".to_string(), &0);
+    assert_eq!("Invalid conflict policy.", rule.get_error_message());
+    let rule: Rule = Rule::new_from_line(&"pre *.pdf with urgent verify".to_string(), &0);
+    assert!(!*rule.is_valid());
+}
+
+#[test]
+fn rule_archive_options_works() {
+    let rule: Rule = Rule::new_from_line(
+        &"tar.gz *.log into logs-{now:%Y-%m}.tar.gz remove append".to_string(),
+        &0,
+    );
+    assert!(*rule.is_valid());
+    assert!(rule.get_archive_options().remove);
+    assert!(rule.get_archive_options().append);
+    let rule: Rule = Rule::new_from_line(&"zip *.log to logs.zip".to_string(), &0);
+    assert_eq!("Keywords' pair doesn't exists.", rule.get_error_message());
+}
+
+#[test]
+fn rule_dedupe_options_works() {
+    let rule: Rule = Rule::new_from_line(
+        &"dedupe *.pdf keep newest prefer ./Docs hardlink".to_string(),
+        &0,
+    );
+    assert!(*rule.is_valid());
+    assert_eq!(KeepPolicy::Newest, rule.get_dedupe_options().keep);
+    assert!(rule.get_dedupe_options().hardlink);
+    let rule: Rule = Rule::new_from_line(&"dedupe *.pdf keep forever".to_string(), &0);
+    assert_eq!("Invalid keep policy.", rule.get_error_message());
+}
+
+#[test]
+fn rule_except_options_works() {
+    let rule: Rule = Rule::new_from_line(
+        &"mv *.pdf to ~/Docs except *invoice* except *.tmp.pdf conflict skip".to_string(),
+        &0,
+    );
+    assert!(*rule.is_valid());
+    assert_eq!(
+        &vec![String::from("*invoice*"), String::from("*.tmp.pdf")],
+        rule.get_exceptions()
+    );
+    assert_eq!(ConflictPolicy::Skip, rule.get_copy_options().conflict);
+    let rule: Rule = Rule::new_from_line(&"pre *.pdf with old_ except".to_string(), &0);
+    assert_eq!("Missing exception pattern.", rule.get_error_message());
+}
+
+#[test]
+fn rule_pattern_special_characters_works() {
+    let rule: Rule = Rule::new_from_line(&"mv report(*.pdf to ./Docs".to_string(), &0);
+    assert!(*rule.is_valid());
+    assert!(rule.concerns("report(1).pdf"));
+    assert!(!rule.concerns("report.pdf"));
+    let rule: Rule = Rule::new_from_line(&"mv a.pdf to ./Docs except [a]+".to_string(), &0);
+    assert!(rule.concerns("a.pdf"));
+    assert!(!rule.concerns("axpdf"));
+}
+
+#[test]
+fn rule_same_folder_works() {
+    let dir = common::temp_dir("same-folder");
+    std::fs::write(dir.join("a.pdf"), "a").unwrap();
+    std::fs::write(dir.join("b.txt"), "b").unwrap();
+    let target = dir.to_string_lossy().to_string();
+    App::builder()
+        .target(&target)
+        .config_str(&format!(
+            "mv *.pdf to {}\ncp *.txt to {}/.\n",
+            target, target
+        ))
+        .build()
+        .unwrap()
+        .launch();
+    assert_eq!("a", std::fs::read_to_string(dir.join("a.pdf")).unwrap());
+    assert_eq!("b", std::fs::read_to_string(dir.join("b.txt")).unwrap());
+    assert_eq!(2, std::fs::read_dir(&dir).unwrap().count());
+}
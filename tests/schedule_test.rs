@@ -0,0 +1,179 @@
+use chrono::{Local, NaiveTime, TimeZone, Weekday};
+use crabby::{parse_duration, Config, Every, Rule, Schedule, ScheduleState, Scheduler};
+use std::fs;
+use std::time::Duration;
+
+mod common;
+
+#[test]
+fn schedule_from_clauses_works() {
+    let sunday = Schedule::from_clauses(Some("sunday"), Some("18:00")).unwrap();
+    assert_eq!(
+        Some(Schedule {
+            every: Every::Weekday(Weekday::Sun),
+            at: NaiveTime::from_hms_opt(18, 0, 0).unwrap(),
+        }),
+        sunday
+    );
+    assert_eq!(
+        Every::Interval(Duration::from_secs(6 * 3600)),
+        Schedule::from_clauses(Some("6h"), None)
+            .unwrap()
+            .unwrap()
+            .every
+    );
+    assert_eq!(
+        Every::Day,
+        Schedule::from_clauses(None, Some("08:30"))
+            .unwrap()
+            .unwrap()
+            .every
+    );
+    assert_eq!(None, Schedule::from_clauses(None, None).unwrap());
+    assert!(Schedule::from_clauses(Some("6h"), Some("08:30")).is_err());
+    assert!(Schedule::from_clauses(Some("someday"), None).is_err());
+    assert!(Schedule::from_clauses(None, Some("25:00")).is_err());
+    assert_eq!(Some(Duration::from_secs(7 * 86400)), parse_duration("7d"));
+    assert_eq!(None, parse_duration("7y"));
+}
+
+#[test]
+fn schedule_next_after_works() {
+    let sunday = Schedule::from_clauses(Some("sunday"), Some("18:00"))
+        .unwrap()
+        .unwrap();
+    // Tuesday, 7 September 2021
+    let tuesday = Local.with_ymd_and_hms(2021, 9, 7, 10, 0, 0).unwrap();
+    assert_eq!(
+        Local.with_ymd_and_hms(2021, 9, 12, 18, 0, 0).unwrap(),
+        sunday.next_after(tuesday)
+    );
+    let run = Local.with_ymd_and_hms(2021, 9, 12, 18, 0, 0).unwrap();
+    assert_eq!(
+        Local.with_ymd_and_hms(2021, 9, 19, 18, 0, 0).unwrap(),
+        sunday.next_after(run)
+    );
+    let hourly = Schedule::from_clauses(Some("hour"), None).unwrap().unwrap();
+    assert_eq!(
+        Local.with_ymd_and_hms(2021, 9, 7, 11, 0, 0).unwrap(),
+        hourly.next_after(tuesday)
+    );
+}
+
+#[test]
+fn schedule_is_due_works() {
+    let sunday = Schedule::from_clauses(Some("sunday"), None)
+        .unwrap()
+        .unwrap();
+    let last = Local.with_ymd_and_hms(2021, 9, 1, 10, 0, 0).unwrap();
+    let before = Local.with_ymd_and_hms(2021, 9, 4, 10, 0, 0).unwrap();
+    let weeks_later = Local.with_ymd_and_hms(2021, 9, 30, 10, 0, 0).unwrap();
+    assert!(sunday.is_due(None, before));
+    assert!(!sunday.is_due(Some(last), before));
+    assert!(sunday.is_due(Some(last), weeks_later));
+}
+
+#[test]
+fn schedule_state_works() {
+    let dir = common::temp_dir("schedule-state");
+    let path = dir.join("state").join("schedule.state");
+    let time = Local.with_ymd_and_hms(2021, 9, 12, 18, 0, 0).unwrap();
+    let mut state = ScheduleState::load(&path).unwrap();
+    assert_eq!(None, state.last_run("rule"));
+    state.record("rule", time);
+    state.save().unwrap();
+    assert_eq!(
+        Some(time),
+        ScheduleState::load(&path).unwrap().last_run("rule")
+    );
+}
+
+#[test]
+fn scheduler_run_due_works() {
+    let dir = common::temp_dir("schedule-run");
+    let target = dir.join("Screenshots");
+    fs::create_dir(&target).unwrap();
+    fs::write(target.join("screen.png"), "pixels").unwrap();
+    fs::write(target.join("notes.txt"), "text").unwrap();
+    let archive = dir.join("screens.zip");
+    fs::write(
+        dir.join("rules.crc"),
+        format!(
+            "zip *.png into {} remove every sunday at 18:00\npre *.txt with old_\n",
+            archive.display()
+        ),
+    )
+    .unwrap();
+    let config_file = dir.join("rules.crc").to_string_lossy().to_string();
+    let state_file = dir.join("schedule.state");
+    let mut scheduler = Scheduler::new(
+        vec![target.to_string_lossy().to_string()],
+        config_file.clone(),
+        &state_file,
+        false,
+    )
+    .unwrap();
+    let now = Local::now();
+    assert_eq!(1, scheduler.run_due(now).unwrap());
+    assert!(archive.exists());
+    assert!(!target.join("screen.png").exists());
+    assert!(target.join("notes.txt").exists());
+    assert_eq!(0, scheduler.run_due(now).unwrap());
+
+    let mut scheduler = Scheduler::new(
+        vec![target.to_string_lossy().to_string()],
+        config_file.clone(),
+        &state_file,
+        false,
+    )
+    .unwrap();
+    assert_eq!(0, scheduler.run_due(now).unwrap());
+
+    fs::write(target.join("screen.png"), "pixels").unwrap();
+    Config::from_file(&config_file).unwrap().execute_rules(
+        target.to_string_lossy().to_string(),
+        false,
+        false,
+    );
+    assert!(target.join("screen.png").exists());
+    assert!(target.join("old_notes.txt").exists());
+}
+
+#[test]
+fn rule_older_than_works() {
+    let dir = common::temp_dir("rule-older-than");
+    fs::write(dir.join("new.png"), "pixels").unwrap();
+    let rule: Rule = Rule::new_from_line(&"mv *.png to ./Old older-than 7d".to_string(), &0);
+    assert!(*rule.is_valid());
+    assert_eq!(&Some(Duration::from_secs(7 * 86400)), rule.get_older_than());
+    assert!(!rule.concerns(&dir.join("new.png").to_string_lossy()));
+    let rule: Rule = Rule::new_from_line(&"mv *.png to ./Old older-than 0m".to_string(), &0);
+    assert!(rule.concerns(&dir.join("new.png").to_string_lossy()));
+    let rule: Rule = Rule::new_from_line(&"mv *.png to ./Old older-than soon".to_string(), &0);
+    assert!(!*rule.is_valid());
+}
+
+#[test]
+fn scheduler_block_works() {
+    let dir = common::temp_dir("schedule-block");
+    fs::create_dir(dir.join("Screenshots")).unwrap();
+    fs::write(dir.join("a.png"), "pixels").unwrap();
+    fs::write(dir.join("Screenshots").join("b.png"), "pixels").unwrap();
+    fs::write(dir.join("Screenshots").join("c.png"), "pixels").unwrap();
+    fs::write(
+        dir.join("rules.crc"),
+        "in ./Screenshots {\n    pre b.png with old_ stop every day\n    pre *.png with new_ every day\n}\n",
+    )
+    .unwrap();
+    let mut scheduler = Scheduler::new(
+        vec![dir.to_string_lossy().to_string()],
+        dir.join("rules.crc").to_string_lossy().to_string(),
+        &dir.join("schedule.state"),
+        false,
+    )
+    .unwrap();
+    assert_eq!(2, scheduler.run_due(Local::now()).unwrap());
+    assert!(dir.join("a.png").exists());
+    assert!(dir.join("Screenshots").join("old_b.png").exists());
+    assert!(dir.join("Screenshots").join("new_c.png").exists());
+}
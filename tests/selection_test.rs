@@ -0,0 +1,101 @@
+use crabby::{check, explain, App, Config, Decision, Filesystem, MemFs, Rule, Selection};
+use std::path::Path;
+use std::sync::Arc;
+
+mod common;
+
+const RULES: &str = "@name(music) @tag(media) mv *.mp3 to Music\n@name(videos) @tag(media) mv *.mp4 to Videos\nmv *.pdf to Docs\n";
+
+fn downloads() -> Arc<MemFs> {
+    common::mem_fs(
+        &["Downloads/a.mp3", "Downloads/b.mp4", "Downloads/c.pdf"],
+        &["Music", "Videos", "Docs"],
+    )
+}
+
+#[test]
+fn annotations_works() {
+    let rule = Rule::new_from_line(
+        &String::from("@name(music) @tag(media) @tag(audio) mv *.mp3 to Music"),
+        &0,
+    );
+    assert!(*rule.is_valid());
+    assert_eq!(&Some(String::from("music")), rule.get_name());
+    assert_eq!(
+        &vec![String::from("media"), String::from("audio")],
+        rule.get_tags()
+    );
+    assert_eq!("mv", rule.get_token(0));
+    assert!(rule.is_labeled("audio"));
+
+    let rule = Rule::new_from_line(&String::from("@name(a) @name(b) mv *.mp3 to Music"), &0);
+    assert_eq!("Only one `@name` is allowed.", rule.get_error_message());
+    let rule = Rule::new_from_line(&String::from("@label(a) mv *.mp3 to Music"), &0);
+    assert!(!*rule.is_valid());
+
+    let diagnostics = check(
+        "rules.crc",
+        "@name(a) mv *.mp3 to Music\n@name(a) mv *.mp4 too Videos\n@name(a) mv *.pdf to Docs\n",
+    );
+    let found: Vec<(usize, usize, &str)> = diagnostics
+        .iter()
+        .map(|x| (x.line, x.column, x.message.as_str()))
+        .collect();
+    assert_eq!(
+        vec![
+            (2, 19, "invalid second keyword"),
+            (3, 1, "duplicate rule name")
+        ],
+        found
+    );
+}
+
+#[test]
+fn only_and_skip_works() {
+    let fs = downloads();
+    let app = |fs: &Arc<MemFs>, only: &[&str], skip: &[&str]| {
+        let builder = App::builder()
+            .filesystem(fs.clone())
+            .target("Downloads")
+            .config_str(RULES);
+        let builder = only.iter().fold(builder, |x, y| x.only(y));
+        skip.iter().fold(builder, |x, y| x.skip(y))
+    };
+    app(&fs, &["media"], &["videos"]).build().unwrap().launch();
+    assert!(fs.exists(Path::new("Music/a.mp3")));
+    assert!(fs.exists(Path::new("Downloads/b.mp4")));
+    assert!(fs.exists(Path::new("Downloads/c.pdf")));
+
+    let fs = downloads();
+    app(&fs, &[], &["media"]).build().unwrap().launch();
+    assert!(fs.exists(Path::new("Docs/c.pdf")));
+    assert!(fs.exists(Path::new("Downloads/a.mp3")));
+
+    let error = app(&fs, &["movies"], &[]).build().unwrap_err();
+    assert_eq!("no rule is named or tagged `movies`", error.to_string());
+}
+
+#[test]
+fn selection_explain_works() {
+    let fs = downloads();
+    let mut config: Config = RULES.parse().unwrap_or_else(|x| match x {});
+    config.set_filesystem(fs.clone());
+    config.set_selection(Selection {
+        only: vec![String::from("videos")],
+        skip: vec![],
+    });
+    let decisions: Vec<Decision> = explain(&[config], "", "Downloads/a.mp3")
+        .into_iter()
+        .map(|x| x.decision)
+        .collect();
+    assert_eq!(
+        vec![
+            Decision::Deselected,
+            Decision::Pattern {
+                pattern: String::from("*.mp4")
+            },
+            Decision::Deselected
+        ],
+        decisions
+    );
+}
@@ -0,0 +1,78 @@
+use crabby::{
+    install_service_in, installed_units_in, journal_filter, render_service, uninstall_service_in,
+    validate_service, ServiceOptions,
+};
+use std::fs;
+use std::path::Path;
+
+mod common;
+
+#[test]
+fn validate_service_works() {
+    let dir = common::temp_dir("service-validate");
+    fs::write(dir.join("good.crc"), "mv *.pdf to ./Docs\n").unwrap();
+    fs::write(dir.join("bad.crc"), "mv *.pdf into ./Docs\n").unwrap();
+    let mut options = ServiceOptions {
+        targets: vec![dir.clone()],
+        config_files: vec![dir.join("good.crc")],
+        ..ServiceOptions::default()
+    };
+    assert!(validate_service(&options).is_ok());
+    options.config_files = vec![dir.join("bad.crc")];
+    assert!(validate_service(&options).is_err());
+    options.config_files = vec![dir.join("missing.crc")];
+    assert!(validate_service(&options).is_err());
+    options.config_files = vec![dir.join("good.crc"), dir.join("good.crc")];
+    assert!(validate_service(&options).is_err());
+    options.timer = Some(String::from("hourly"));
+    assert!(validate_service(&options).is_ok());
+}
+
+#[test]
+fn render_service_works() {
+    let options = ServiceOptions {
+        targets: vec![Path::new("/home/user/Downloads 100%").to_path_buf()],
+        config_files: vec![Path::new("/home/user/rules.crc").to_path_buf()],
+        ..ServiceOptions::default()
+    };
+    let unit = render_service(&options, Path::new("/usr/bin/crabby"));
+    assert!(unit.contains(
+        "ExecStart=\"/usr/bin/crabby\" \"watch\" \"-c\" \"/home/user/Downloads 100%%\" \"-f\" \"/home/user/rules.crc\"\n"
+    ));
+    assert!(unit.contains("WantedBy=default.target"));
+    assert_eq!(
+        "journalctl --user -u crabby.service -f",
+        journal_filter("crabby")
+    );
+}
+
+#[test]
+fn install_service_works() {
+    let dir = common::temp_dir("service-install");
+    fs::write(dir.join("rules.crc"), "mv *.pdf to ./Docs\n").unwrap();
+    let options = ServiceOptions {
+        name: String::from("crabby-test"),
+        targets: vec![dir.clone()],
+        config_files: vec![dir.join("rules.crc")],
+        timer: Some(String::from("hourly")),
+    };
+    let unit_dir = dir.join("config").join("systemd").join("user");
+    let units = install_service_in(&options, Path::new("/usr/bin/crabby"), &unit_dir).unwrap();
+    assert_eq!(
+        vec![
+            unit_dir.join("crabby-test.service"),
+            unit_dir.join("crabby-test.timer")
+        ],
+        units
+    );
+    let timer = fs::read_to_string(unit_dir.join("crabby-test.timer")).unwrap();
+    assert!(timer.contains("OnCalendar=hourly"));
+    let service = fs::read_to_string(unit_dir.join("crabby-test.service")).unwrap();
+    assert!(service.contains("Type=oneshot"));
+    assert_eq!(units, installed_units_in("crabby-test", &unit_dir));
+    assert_eq!(
+        units,
+        uninstall_service_in("crabby-test", &unit_dir).unwrap()
+    );
+    assert!(installed_units_in("crabby-test", &unit_dir).is_empty());
+}
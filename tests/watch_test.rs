@@ -0,0 +1,67 @@
+use crabby::Watcher;
+use std::fs;
+use std::path::Path;
+use std::time::Duration;
+
+mod common;
+
+#[test]
+fn is_partial_download_works() {
+    assert!(Watcher::is_partial_download(Path::new("file.pdf.part")));
+    assert!(Watcher::is_partial_download(Path::new("file.crdownload")));
+    assert!(!Watcher::is_partial_download(Path::new("file.pdf")));
+    assert!(!Watcher::is_partial_download(Path::new("Makefile")));
+}
+
+#[test]
+fn watcher_ready_files_works() {
+    let dir = common::temp_dir("watch-ready");
+    fs::write(dir.join("rules.crc"), "mv *.pdf to ./Docs\n").unwrap();
+    let mut watcher = Watcher::new(vec![dir.clone()], dir.join("rules.crc"), false).unwrap();
+    watcher.set_settle(Duration::from_millis(50));
+    let dir = fs::canonicalize(&dir).unwrap();
+    fs::write(dir.join("file.pdf"), "content").unwrap();
+    fs::write(dir.join("file.zip.part"), "cont").unwrap();
+    watcher.notice(dir.join("file.pdf"));
+    watcher.notice(dir.join("file.zip.part"));
+    assert!(watcher.ready_files().is_empty());
+    std::thread::sleep(Duration::from_millis(60));
+    fs::write(dir.join("file.pdf"), "content, still downloading").unwrap();
+    assert!(watcher.ready_files().is_empty());
+    std::thread::sleep(Duration::from_millis(60));
+    assert_eq!(vec![dir.join("file.pdf")], watcher.ready_files());
+}
+
+#[test]
+fn watcher_handle_works() {
+    let dir = common::temp_dir("watch-handle");
+    fs::create_dir(dir.join("Docs")).unwrap();
+    let docs = dir.join("Docs").to_string_lossy().to_string();
+    fs::write(
+        dir.join("rules.crc"),
+        format!("mv *.pdf to {}\ncp *.png to {}\n", docs, docs),
+    )
+    .unwrap();
+    let mut watcher = Watcher::new(vec![dir.clone()], dir.join("rules.crc"), false).unwrap();
+    let dir = fs::canonicalize(&dir).unwrap();
+    fs::write(dir.join("file.pdf"), "content").unwrap();
+    fs::write(dir.join("other.pdf"), "content").unwrap();
+    assert_eq!(1, watcher.handle(&dir.join("file.pdf")));
+    assert!(dir.join("Docs").join("file.pdf").exists());
+    assert!(dir.join("other.pdf").exists());
+}
+
+#[test]
+fn watcher_reload_works() {
+    let dir = common::temp_dir("watch-reload");
+    fs::write(dir.join("rules.crc"), "mv *.pdf to ./Docs\n").unwrap();
+    let mut watcher = Watcher::new(vec![dir.clone()], dir.join("rules.crc"), false).unwrap();
+    assert_eq!(1, watcher.get_config().get_rules().len());
+    fs::write(
+        dir.join("rules.crc"),
+        "mv *.pdf to ./Docs\ncp *.png to ./Images\n",
+    )
+    .unwrap();
+    watcher.notice(fs::canonicalize(dir.join("rules.crc")).unwrap());
+    assert_eq!(2, watcher.get_config().get_rules().len());
+}